@@ -0,0 +1,18 @@
+// The TOML grammar of tree-sitter-toml 0.20.0
+// (https://github.com/Mathspy/tree-sitter-toml, MIT, its license is in
+// `grammars/toml/LICENSE`), built here as its crate needs a newer
+// tree-sitter than the other grammars.
+fn main() {
+    let dir = std::path::Path::new("grammars/toml");
+    let (parser, scanner) = (dir.join("parser.c"), dir.join("scanner.c"));
+    println!("cargo:rerun-if-changed={}", parser.display());
+    println!("cargo:rerun-if-changed={}", scanner.display());
+    cc::Build::new()
+        .include(dir)
+        .flag_if_supported("-Wno-unused-parameter")
+        .flag_if_supported("-Wno-unused-but-set-variable")
+        .flag_if_supported("-Wno-trigraphs")
+        .file(&parser)
+        .file(&scanner)
+        .compile("tree-sitter-toml");
+}
@@ -0,0 +1,189 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// Directory of files attached to notes, like images, in a repo.
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Extensions of attachments linked as images.
+const IMAGE_EXTENSIONS: [&str; 7] =
+    ["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+
+/// File attached to notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub path: PathBuf,
+    /// Link to the file from a note, like `@/attachments/<hash>.png`.
+    pub link: String,
+    /// Zettels linking to the file.
+    pub used_by: Vec<String>,
+}
+
+/// Link of a note to a file that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingFile {
+    pub zid: String,
+    pub link: String,
+}
+
+/// Attachments of a repo, and the files notes link to but are missing.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub attachments: Vec<Attachment>,
+    pub missing: Vec<MissingFile>,
+}
+
+impl Report {
+    /// Attachments no note links to.
+    pub fn orphaned(&self) -> impl Iterator<Item = &Attachment> {
+        self.attachments.iter().filter(|a| a.used_by.is_empty())
+    }
+}
+
+/// Files attached to notes, kept in `attachments/` of a repo and named by
+/// the hash of their content, so a file attached twice is stored once.
+pub struct Attachments {
+    repo: PathBuf,
+}
+
+impl Attachments {
+    pub fn new(repo_path: &str) -> Self {
+        Attachments {
+            repo: PathBuf::from(repo_path),
+        }
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.repo.join(ATTACHMENTS_DIR)
+    }
+
+    /// Keep `data` as an attachment with extension `ext`, returns its path.
+    pub fn add(&self, data: &[u8], ext: &str) -> Result<PathBuf> {
+        let hash = Sha256::digest(data);
+        let name: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        let dir = self.dir();
+        let path = if ext.is_empty() {
+            dir.join(name)
+        } else {
+            dir.join(format!("{}.{}", name, ext))
+        };
+        if path.exists() {
+            return Ok(path);
+        }
+        fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        let tmp = dir.join(".attach.tmp");
+        fs::write(&tmp, data).map_err(|e| Error::io(&tmp, e))?;
+        fs::rename(&tmp, &path).map_err(|e| Error::io(&path, e))?;
+        Ok(path)
+    }
+
+    /// Keep a copy of the file of `path` as an attachment, returns the path
+    /// of the copy.
+    pub fn add_file(&self, path: &Path) -> Result<PathBuf> {
+        let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        self.add(&data, ext)
+    }
+
+    /// Files of the attachments, by name.
+    pub fn list(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.dir();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Error::io(&dir, e)),
+        };
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry.map_err(|e| Error::io(&dir, e))?.path();
+            let hidden = path
+                .file_name()
+                .map_or(true, |n| n.to_string_lossy().starts_with('.'));
+            if path.is_file() && !hidden {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path).map_err(|e| Error::io(path, e))
+    }
+
+    /// Link to the file of `path` from any note of the repo.
+    pub fn link(&self, path: &Path) -> String {
+        let rel = path.strip_prefix(&self.repo).unwrap_or(path);
+        format!("@/{}", rel.display())
+    }
+
+    /// Markdown linking to the attachment of `path` as `name`, an image
+    /// link if it's a picture.
+    pub fn markdown(&self, path: &Path, name: &str) -> String {
+        let link = self.link(path);
+        let link = if link.contains(' ') {
+            format!("<{}>", link)
+        } else {
+            link
+        };
+        if is_image(path) {
+            format!("![{}]({})", name, link)
+        } else {
+            format!("[{}]({})", name, link)
+        }
+    }
+}
+
+/// Whether the file of `path` is a picture, by its extension.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| {
+            IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::Attachments;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_add_and_markdown() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let attachments = Attachments::new(dir.to_str().unwrap());
+        assert!(attachments.list().unwrap().is_empty());
+
+        let a = attachments.add(b"cat", "PNG").unwrap();
+        let b = attachments.add(b"cat", "png").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(fs::read(&a).unwrap(), b"cat");
+        let name = a.file_name().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), 64 + 4);
+        assert!(name.ends_with(".png"));
+
+        let src = dir.join("notes.txt");
+        fs::write(&src, "dog").unwrap();
+        let c = attachments.add_file(&src).unwrap();
+        assert_eq!(attachments.list().unwrap().len(), 2);
+
+        let link = attachments.link(&a);
+        assert_eq!(link, format!("@/attachments/{}", name));
+        assert_eq!(
+            attachments.markdown(&a, "cat"),
+            format!("![cat]({})", link)
+        );
+        assert!(attachments.markdown(&c, "dog").starts_with("[dog](@/"));
+        let spaced = attachments.markdown(Path::new("a b.pdf"), "x");
+        assert_eq!(spaced, "[x](<@/a b.pdf>)");
+    }
+}
@@ -1,4 +1,5 @@
-use std::env::var;
+use std::collections::BTreeMap;
+use std::env::var_os;
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
@@ -6,17 +7,80 @@ use std::str::from_utf8;
 
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug, Clone)]
+pub use toml::Value;
+
+use crate::error::{Error, Position, Result};
+use crate::highlight::KINDS;
+
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Config {
+    #[serde(default)]
     repo: Repo,
+    #[serde(default)]
     shortcut: Shortcut,
+    #[serde(default)]
+    editor: Editor,
+
+    /// Key chords or sequences, mapped to command ids.
+    #[serde(default)]
+    keys: BTreeMap<String, String>,
+
+    /// Named repos to switch between, the `[repo]` one is `default`.
+    #[serde(default)]
+    workspaces: BTreeMap<String, String>,
+
+    /// Name of the workspace `repo` points to.
+    #[serde(skip)]
+    workspace: String,
+
+    /// File the config is loaded from.
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// Raw content of the file, to locate keys when reporting errors.
+    #[serde(skip)]
+    source: String,
+
+    #[serde(skip)]
+    home: PathBuf,
+
+    /// Directory relative repo paths start from, where we are started.
+    #[serde(skip)]
+    cwd: PathBuf,
+
+    /// Repo path given on the command line, wins over `[repo]`.
+    #[serde(skip)]
+    repo_override: Option<PathBuf>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct Shortcut {
     find: String,
     insert: String,
     quit: String,
+    preferences: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Editor {
+    font: String,
+    font_size: i32,
+    theme: Theme,
+
+    /// Seconds after the last edit to save a note, 0 to save by hand only.
+    autosave: u32,
+
+    /// Colours of highlighted code by kind, over those of the theme.
+    code: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
 }
 
 impl Config {
@@ -24,20 +88,302 @@ impl Config {
         &self.repo.path
     }
 
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
     pub fn shortcut(&self) -> &Shortcut {
         &self.shortcut
     }
+
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    /// Bindings of `[keys]`, e.g. `"<Control>x <Control>s" = "save"`.
+    pub fn keys(&self) -> &BTreeMap<String, String> {
+        &self.keys
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Find where `key` of `[table]` is set in the config file.
+    pub fn locate(&self, table: &str, key: &str) -> Option<Position> {
+        let header = format!("[{}]", table);
+        let mut in_table = false;
+        for (n, line) in self.source.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_table = line == header;
+                continue;
+            }
+            if !in_table {
+                continue;
+            }
+            if let Some((k, _)) = line.split_once('=') {
+                if k.trim().trim_matches('"') == key {
+                    return Some(Position {
+                        line: n + 1,
+                        column: 1,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Error about the value of `key` of `[table]`.
+    pub fn invalid(&self, table: &str, key: &str, message: &str) -> Error {
+        Error::Config {
+            path: self.path.clone(),
+            pos: self.locate(table, key),
+            message: format!("{}.{}: {}", table, key, message),
+        }
+    }
+
+    pub fn set_repo_path(&mut self, path: &str) {
+        self.repo.path = path.to_string();
+    }
+
+    /// Name of the current workspace.
+    pub fn workspace(&self) -> &str {
+        &self.workspace
+    }
+
+    /// Names of all workspaces, the default one first.
+    pub fn workspaces(&self) -> Vec<&str> {
+        let mut names = vec![DEFAULT_WORKSPACE];
+        names.extend(
+            self.workspaces
+                .keys()
+                .map(|k| k.as_str())
+                .filter(|k| *k != DEFAULT_WORKSPACE),
+        );
+        names
+    }
+
+    /// Point the repo to workspace `name`.
+    pub fn switch_workspace(&mut self, name: &str) -> Result<()> {
+        let path = match self.workspaces.get(name) {
+            Some(path) => path.clone(),
+            None => {
+                return Err(self.invalid(
+                    "workspaces",
+                    name,
+                    "no such workspace",
+                ))
+            }
+        };
+        let prev = std::mem::replace(&mut self.repo.path, path);
+        if let Err(err) = self.validate() {
+            self.repo.path = prev;
+            return Err(err);
+        }
+        self.workspace = name.to_string();
+        Ok(())
+    }
+
+    /// Write `edits` of `(table, key, value)` into the config file, keeping
+    /// the rest of it as is, then reload. The file is read again first, not
+    /// to lose edits made to it since loading. Editing the repo path drops
+    /// the one given on the command line.
+    pub fn save(&mut self, edits: &[(&str, &str, Value)]) -> Result<()> {
+        let source = fs::read_to_string(&self.path)
+            .map_err(|e| Error::io(&self.path, e))?;
+        let mut doc = source.parse::<toml_edit::Document>().map_err(|e| {
+            Error::Config {
+                path: self.path.clone(),
+                pos: None,
+                message: e.to_string(),
+            }
+        })?;
+        for (table, key, value) in edits {
+            let value = value
+                .to_string()
+                .parse::<toml_edit::Value>()
+                .map_err(|e| self.invalid(table, key, &e.to_string()))?;
+            if !doc.contains_key(table) {
+                doc[table] = toml_edit::table();
+            }
+            doc[table][key] = toml_edit::value(value);
+        }
+        let tmp = self.path.with_extension("toml.tmp");
+        fs::write(&tmp, doc.to_string()).map_err(|e| Error::io(&tmp, e))?;
+        fs::rename(&tmp, &self.path).map_err(|e| Error::io(&self.path, e))?;
+        let repo_edited = edits
+            .iter()
+            .any(|(table, key, _)| (*table, *key) == ("repo", "path"));
+        let repo_override = if repo_edited {
+            self.repo_override.take()
+        } else {
+            None
+        };
+        let reloaded = self.reload();
+        if reloaded.is_err() && repo_override.is_some() {
+            self.repo_override = repo_override;
+        }
+        reloaded.map(|_| ())
+    }
+
+    /// Read the config file again, staying in the current workspace if it's
+    /// still there. Nothing changes on error, or if the file is the same as
+    /// last read, e.g. when it's our own save: false is returned then.
+    pub fn reload(&mut self) -> Result<bool> {
+        let mut config = read_config(&self.path)?;
+        if config.source == self.source {
+            return Ok(false);
+        }
+        config.repo_override = self.repo_override.clone();
+        config.resolve(&self.home, &self.cwd)?;
+        if config.workspaces.contains_key(&self.workspace) {
+            config.switch_workspace(&self.workspace)?;
+        } else {
+            config.validate()?;
+        }
+        *self = config;
+        Ok(true)
+    }
+
+    /// Expand `~` to `home` and resolve relative repo paths against `cwd`.
+    fn resolve(&mut self, home: &Path, cwd: &Path) -> Result<()> {
+        if self.workspaces.contains_key(DEFAULT_WORKSPACE) {
+            return Err(self.invalid(
+                "workspaces",
+                DEFAULT_WORKSPACE,
+                "is the name of the [repo] one, pick another",
+            ));
+        }
+        self.home = home.to_path_buf();
+        self.cwd = cwd.to_path_buf();
+        if let Some(path) = self.repo_override.as_ref() {
+            self.repo.path = path.to_string_lossy().to_string();
+        }
+        let resolve = |path: &str| {
+            let path = expand_home(path, home);
+            let path = if path.is_relative() {
+                cwd.join(path)
+            } else {
+                path
+            };
+            path.to_string_lossy().to_string()
+        };
+        self.repo.path = resolve(&self.repo.path);
+        for path in self.workspaces.values_mut() {
+            *path = resolve(path);
+        }
+        self.workspaces
+            .insert(DEFAULT_WORKSPACE.to_string(), self.repo.path.clone());
+        self.workspace = DEFAULT_WORKSPACE.to_string();
+        Ok(())
+    }
+
+    /// Check the repo path. A missing directory is fine, it's created with
+    /// the first note.
+    pub fn validate(&self) -> Result<()> {
+        if self.repo.path.trim().is_empty() {
+            return Err(self.invalid("repo", "path", "must not be empty"));
+        }
+        for (kind, color) in self.editor.code.iter() {
+            if !KINDS.contains(&kind.as_str()) {
+                return Err(self.invalid(
+                    "editor.code",
+                    kind,
+                    &format!(
+                        "no such kind of code, one of {}",
+                        KINDS.join(", ")
+                    ),
+                ));
+            }
+            if !is_color(color) {
+                return Err(self.invalid(
+                    "editor.code",
+                    kind,
+                    "must be a colour like #RRGGBB",
+                ));
+            }
+        }
+        let path = Path::new(&self.repo.path);
+        if path.exists() && !path.is_dir() {
+            return Err(self.invalid(
+                "repo",
+                "path",
+                &format!("{} is not a directory", path.display()),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl std::str::FromStr for Config {
-    type Err = anyhow::Error;
+    type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let config = toml::from_str(s)?;
+    fn from_str(s: &str) -> Result<Self> {
+        let mut config: Config =
+            toml::from_str(s).map_err(|e| Error::config(s, &e))?;
+        config.source = s.to_string();
         Ok(config)
     }
 }
 
+impl Default for Shortcut {
+    fn default() -> Self {
+        Shortcut {
+            find: "<Control><Shift>f".to_string(),
+            insert: "<Control>i".to_string(),
+            quit: "<Meta>q".to_string(),
+            preferences: "<Control>comma".to_string(),
+        }
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Editor {
+            font: "Victor Mono".to_string(),
+            font_size: 14,
+            theme: Theme::Light,
+            autosave: 0,
+            code: BTreeMap::new(),
+        }
+    }
+}
+
+impl Editor {
+    pub fn font(&self) -> &str {
+        &self.font
+    }
+
+    pub fn font_size(&self) -> i32 {
+        self.font_size
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    pub fn autosave(&self) -> u32 {
+        self.autosave
+    }
+
+    /// Colour set for code of `kind`, one of `KINDS`, if any.
+    pub fn code_color(&self, kind: &str) -> Option<&str> {
+        self.code.get(kind).map(|c| c.as_str())
+    }
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Light, Theme::Dark];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+}
+
 impl Shortcut {
     pub fn find(&self) -> &str {
         &self.find
@@ -50,36 +396,122 @@ impl Shortcut {
     pub fn quit(&self) -> &str {
         &self.quit
     }
+
+    pub fn preferences(&self) -> &str {
+        &self.preferences
+    }
+}
+
+/// Whether `s` is a hex colour, `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`.
+fn is_color(s: &str) -> bool {
+    match s.strip_prefix('#') {
+        Some(hex) => {
+            [3, 4, 6, 8].contains(&hex.len())
+                && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+/// Replace a leading `~` with `home`.
+pub fn expand_home(path: &str, home: &Path) -> PathBuf {
+    if path == "~" {
+        return home.to_path_buf();
+    }
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(path),
+    }
 }
 
 #[derive(Default)]
 pub struct ConfigLoader {
-    #[allow(dead_code)]
     home: PathBuf,
+    cwd: PathBuf,
     dir: PathBuf,
     path: PathBuf,
+    repo: Option<String>,
+    workspace: Option<String>,
 }
 
 impl ConfigLoader {
+    /// Loader of `$XDG_CONFIG_HOME/rustybrain/config.toml`, or of
+    /// `~/.rustybrain/config.toml` if it's still there.
     pub fn new() -> Self {
-        let raw_home = var("HOME").unwrap();
-        let home = Path::new(&raw_home).to_path_buf();
-        let dir = home.join(".rustybrain");
+        Self::from_env(
+            var_os("HOME").map(PathBuf::from).unwrap_or_default(),
+            var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+            std::env::current_dir().unwrap_or_default(),
+        )
+    }
+
+    fn from_env(
+        home: PathBuf,
+        config_home: Option<PathBuf>,
+        cwd: PathBuf,
+    ) -> Self {
+        let legacy = home.join(".rustybrain");
+        let dir = if legacy.join("config.toml").is_file() {
+            legacy
+        } else {
+            config_home
+                .filter(|p| p.is_absolute())
+                .unwrap_or_else(|| home.join(".config"))
+                .join("rustybrain")
+        };
         let path = dir.join("config.toml");
-        ConfigLoader { home, dir, path }
+        ConfigLoader {
+            home,
+            cwd,
+            dir,
+            path,
+            repo: None,
+            workspace: None,
+        }
+    }
+
+    /// Load config from `path` instead.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = expand_home(path, &self.home);
+        self.dir = match self.path.parent() {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::new(),
+        };
+        self
+    }
+
+    /// Use repo at `path` whatever the config file says.
+    pub fn with_repo(mut self, path: &str) -> Self {
+        self.repo = Some(path.to_string());
+        self
+    }
+
+    /// Start in workspace `name` instead of the default one.
+    pub fn with_workspace(mut self, name: &str) -> Self {
+        self.workspace = Some(name.to_string());
+        self
     }
 
-    pub fn load(&self) -> Result<Config, anyhow::Error> {
+    pub fn load(&self) -> Result<Config> {
         self.create_dir()?;
         self.attempt_set_default()?;
-        self.load_config()
+        let mut config = read_config(&self.path)?;
+        if let Some(repo) = self.repo.as_ref() {
+            config.repo_override = Some(PathBuf::from(repo));
+        }
+        config.resolve(&self.home, &self.cwd)?;
+        match self.workspace.as_ref() {
+            Some(name) => config.switch_workspace(name)?,
+            None => config.validate()?,
+        }
+        Ok(config)
     }
 
-    fn create_dir(&self) -> Result<(), io::Error> {
-        if Self::is_exists(&self.dir) {
+    fn create_dir(&self) -> Result<()> {
+        if self.dir.as_os_str().is_empty() || Self::is_exists(&self.dir) {
             return Ok(());
         }
-        fs::create_dir(&self.dir)?;
+        fs::create_dir_all(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
         Ok(())
     }
 
@@ -87,54 +519,272 @@ impl ConfigLoader {
         fs::metadata(path).is_ok()
     }
 
-    fn attempt_set_default(&self) -> Result<(), io::Error> {
+    fn attempt_set_default(&self) -> Result<()> {
         match File::open(&self.path) {
             Ok(_) => Ok(()),
             Err(err) => match err.kind() {
-                io::ErrorKind::NotFound => self.create_default(),
-                _ => Err(err),
+                io::ErrorKind::NotFound => {
+                    self.create_default().map_err(|e| Error::io(&self.path, e))
+                }
+                _ => Err(Error::io(&self.path, err)),
             },
         }
     }
 
-    fn create_default(&self) -> Result<(), io::Error> {
+    fn create_default(&self) -> io::Result<()> {
         let mut f = File::create(&self.path)?;
         f.write_all(DEFAULT_CONFIG_CONTENT.as_bytes())?;
         Ok(())
     }
+}
 
-    fn load_config(&self) -> Result<Config, anyhow::Error> {
-        let mut f = File::open(&self.path)?;
-        let mut buf = vec![];
-        f.read_to_end(&mut buf)?;
-        let s = from_utf8(&buf)?;
-        s.parse()
-    }
+fn read_config(path: &Path) -> Result<Config> {
+    let mut buf = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut buf))
+        .map_err(|e| Error::io(path, e))?;
+    let s = from_utf8(&buf).map_err(|e| Error::Config {
+        path: path.to_path_buf(),
+        pos: None,
+        message: e.to_string(),
+    })?;
+    let mut config = s.parse::<Config>().map_err(|e| e.at(path))?;
+    config.path = path.to_path_buf();
+    Ok(config)
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Repo {
     path: String,
+
+    /// Commit notes to a git repository in the repo on save.
+    #[serde(default)]
+    git: bool,
+}
+
+impl Repo {
+    pub fn git(&self) -> bool {
+        self.git
+    }
 }
 
+impl Default for Repo {
+    fn default() -> Self {
+        Repo {
+            path: "~/RustyBrain".to_string(),
+            git: false,
+        }
+    }
+}
+
+pub const DEFAULT_WORKSPACE: &str = "default";
+
 const DEFAULT_CONFIG_CONTENT: &str = r#"
 [repo]
-path = "RustyBrain"
+# Relative paths start from the directory rustybrain is started in.
+path = "~/RustyBrain"
+# Keep versions of notes in a local git repository of the repo.
+git = false
 
 [shortcut]
 find = "<Control><Shift>f"
 insert = "<Control>i"
 quit = "<Meta>q"
+preferences = "<Control>comma"
+
+[editor]
+font = "Victor Mono"
+font_size = 14
+# light or dark
+theme = "light"
+# Seconds after the last edit to save the note, 0 to save by hand only.
+autosave = 0
+
+# Colours of highlighted code, over those of the theme, for kinds keyword,
+# string, comment, number, function, type, constant, property, operator,
+# punctuation and attribute.
+[editor.code]
+# keyword = '#A626A4'
+
+# More slip-boxes to switch between, the one of [repo] is "default".
+[workspaces]
+# work = "~/Work/RustyBrain"
+
+# Bind chords, or sequences of chords separated by spaces, to commands.
+[keys]
+# "<Control>x <Control>s" = "save"
 
 "#;
 
 #[cfg(test)]
 mod tests {
-    use super::ConfigLoader;
+    use std::path::Path;
+
+    use super::{Config, ConfigLoader, Value};
+    use crate::testing::temp_dir;
 
     #[test]
     fn test_default_config_loader() {
-        let loader = ConfigLoader::new();
-        loader.load().unwrap();
+        let tmp = temp_dir();
+        let home = tmp.path();
+        let loader =
+            ConfigLoader::from_env(home.to_path_buf(), None, home.join("cwd"));
+        let config = loader.load().unwrap();
+        assert!(home.join(".config/rustybrain/config.toml").is_file());
+        let repo = home.join("RustyBrain");
+        assert_eq!(config.repo_path(), repo.to_str().unwrap());
+        assert!(!repo.exists());
+    }
+
+    #[test]
+    fn test_merge_default_keys() {
+        let config: Config =
+            "[shortcut]\nfind = \"<Control>f\"\n".parse().unwrap();
+        assert_eq!(config.shortcut().find(), "<Control>f");
+        assert_eq!(config.shortcut().quit(), "<Meta>q");
+        assert_eq!(config.repo_path(), "~/RustyBrain");
+        assert_eq!(config.editor().autosave(), 0);
+        assert!(!config.repo().git());
+
+        let config: Config = "[editor]\nautosave = 3\n".parse().unwrap();
+        assert_eq!(config.editor().autosave(), 3);
+        assert_eq!(config.editor().font_size(), 14);
+    }
+
+    #[test]
+    fn test_resolve_repo_path() {
+        let mut config = Config::default();
+        config.set_repo_path("~/notes");
+        config
+            .resolve(Path::new("/home/me"), Path::new("/work"))
+            .unwrap();
+        assert_eq!(config.repo_path(), "/home/me/notes");
+
+        let mut config = Config::default();
+        config.set_repo_path("notes");
+        config
+            .resolve(Path::new("/home/me"), Path::new("/work"))
+            .unwrap();
+        assert_eq!(config.repo_path(), "/work/notes");
+    }
+
+    #[test]
+    fn test_switch_workspace() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let mut config: Config = format!(
+            "[repo]\npath = \"/\"\n[workspaces]\nwork = \"{}\"\n",
+            dir.display()
+        )
+        .parse()
+        .unwrap();
+        config
+            .resolve(Path::new("/home/me"), Path::new("/"))
+            .unwrap();
+        assert_eq!(config.workspaces(), vec!["default", "work"]);
+        assert_eq!(config.workspace(), "default");
+
+        config.switch_workspace("work").unwrap();
+        assert_eq!(config.workspace(), "work");
+        assert_eq!(config.repo_path(), dir.to_str().unwrap());
+
+        assert!(config.switch_workspace("nope").is_err());
+        assert_eq!(config.workspace(), "work");
+
+        config.switch_workspace("default").unwrap();
+        assert_eq!(config.repo_path(), "/");
+
+        let mut config: Config =
+            "[workspaces]\ndefault = \"/\"\n".parse().unwrap();
+        assert!(config
+            .resolve(Path::new("/home/me"), Path::new("/"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_save_keeps_comments() {
+        let tmp = temp_dir();
+        let path = tmp.path().join("config.toml");
+        std::fs::write(&path, "# mine\n[repo]\npath = \"/\"\n").unwrap();
+        let mut config = super::read_config(&path).unwrap();
+        config
+            .resolve(Path::new("/home/me"), Path::new("/"))
+            .unwrap();
+        // Edited by hand after loading.
+        std::fs::write(&path, "# mine\n[repo]\npath = \"/tmp\"\n").unwrap();
+        config
+            .save(&[
+                ("editor", "font_size", Value::Integer(20)),
+                ("shortcut", "quit", Value::String("<Control>q".into())),
+            ])
+            .unwrap();
+        assert_eq!(config.editor().font_size(), 20);
+        assert_eq!(config.shortcut().quit(), "<Control>q");
+        assert_eq!(config.repo_path(), "/tmp");
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("# mine"));
+        // Nothing to reload after our own save.
+        assert!(!config.reload().unwrap());
+    }
+
+    #[test]
+    fn test_save_repo_drops_override() {
+        let tmp = temp_dir();
+        let path = tmp.path().join("config.toml");
+        std::fs::write(&path, "[repo]\npath = \"/\"\n").unwrap();
+        let mut config = super::read_config(&path).unwrap();
+        config.repo_override = Some("/usr".into());
+        config
+            .resolve(Path::new("/home/me"), Path::new("/"))
+            .unwrap();
+        assert_eq!(config.repo_path(), "/usr");
+
+        config
+            .save(&[("editor", "font_size", Value::Integer(20))])
+            .unwrap();
+        assert_eq!(config.repo_path(), "/usr");
+        config
+            .save(&[("repo", "path", Value::String("/tmp".into()))])
+            .unwrap();
+        assert_eq!(config.repo_path(), "/tmp");
+    }
+
+    #[test]
+    fn test_locate_key() {
+        let config: Config =
+            "[repo]\npath = \"a\"\n\n[shortcut]\nquit = \"x\"\n"
+                .parse()
+                .unwrap();
+        let pos = config.locate("shortcut", "quit").unwrap();
+        assert_eq!(pos.line, 5);
+        assert!(config.locate("shortcut", "find").is_none());
+    }
+
+    #[test]
+    fn test_code_colors() {
+        let config: Config =
+            "[editor.code]\nkeyword = \"#C678DD\"\n".parse().unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.editor().code_color("keyword"), Some("#C678DD"));
+        assert_eq!(config.editor().code_color("string"), None);
+
+        let config: Config =
+            "[editor.code]\nkeywords = \"#C678DD\"\n".parse().unwrap();
+        assert!(config.validate().is_err());
+        let config: Config =
+            "[editor.code]\nkeyword = \"purple\"\n".parse().unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("editor.code.keyword"));
+    }
+
+    #[test]
+    fn test_keys() {
+        let config: Config = "[keys]\n\"<Control>x <Control>s\" = \"save\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(config.keys().get("<Control>x <Control>s").unwrap(), "save");
+        let pos = config.locate("keys", "<Control>x <Control>s").unwrap();
+        assert_eq!(pos.line, 2);
     }
 }
@@ -0,0 +1,235 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// 1-based line and column inside a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Locate a byte offset of `text`, shifted down by `line_offset` lines
+    /// when `text` is only a part of the file.
+    pub fn from_offset(text: &str, offset: usize, line_offset: usize) -> Self {
+        let offset = offset.min(text.len());
+        let before = &text.as_bytes()[..offset];
+        let line = before.iter().filter(|b| **b == b'\n').count();
+        let line_start = before
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let column = String::from_utf8_lossy(&before[line_start..])
+            .chars()
+            .count();
+        Position {
+            line: line + line_offset + 1,
+            column: column + 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The config file is not valid TOML or holds invalid values.
+    Config {
+        path: PathBuf,
+        pos: Option<Position>,
+        message: String,
+    },
+    /// Reading or writing a file or directory failed.
+    Io { path: PathBuf, source: io::Error },
+    /// The `+++` header of a note can not be parsed.
+    Header {
+        path: PathBuf,
+        pos: Option<Position>,
+        message: String,
+    },
+    /// The full text index failed to build, update or query.
+    Index(Box<dyn std::error::Error + Send + Sync>),
+    /// A link points to a note that is not in the slip-box.
+    Link(String),
+    /// The markdown parser can not be set up.
+    Markdown(String),
+    /// The highlighter of a code block can not be set up.
+    Highlight(String),
+    /// A note is changed on disk since it was loaded, saving would lose
+    /// those changes.
+    Conflict { path: PathBuf },
+    /// Versioning the repo with git failed.
+    Git(git2::Error),
+    /// A search pattern is not a valid regular expression.
+    Pattern(regex::Error),
+}
+
+impl Error {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub(crate) fn config(s: &str, err: &toml::de::Error) -> Self {
+        Error::Config {
+            path: PathBuf::new(),
+            pos: err.span().map(|r| Position::from_offset(s, r.start, 0)),
+            message: err.message().to_string(),
+        }
+    }
+
+    pub(crate) fn header(
+        s: &str,
+        line_offset: usize,
+        err: &toml::de::Error,
+    ) -> Self {
+        Error::Header {
+            path: PathBuf::new(),
+            pos: err
+                .span()
+                .map(|r| Position::from_offset(s, r.start, line_offset)),
+            message: err.message().to_string(),
+        }
+    }
+
+    /// Attach the file the error comes from.
+    pub fn at(self, at: &Path) -> Self {
+        match self {
+            Error::Config { pos, message, .. } => Error::Config {
+                path: at.to_path_buf(),
+                pos,
+                message,
+            },
+            Error::Header { pos, message, .. } => Error::Header {
+                path: at.to_path_buf(),
+                pos,
+                message,
+            },
+            Error::Io { source, .. } => Error::Io {
+                path: at.to_path_buf(),
+                source,
+            },
+            e => e,
+        }
+    }
+
+    /// File the error comes from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Config { path, .. }
+            | Error::Io { path, .. }
+            | Error::Header { path, .. }
+            | Error::Conflict { path } => Some(path.as_path()),
+            _ => None,
+        }
+    }
+
+    /// Where in `path` the error is, if known.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Error::Config { pos, .. } | Error::Header { pos, .. } => *pos,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config { path, pos, message } => {
+                write!(f, "invalid config {}", path.display())?;
+                if let Some(pos) = pos {
+                    write!(f, " at {}", pos)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Io { path, source } => {
+                write!(f, "{}: {}", path.display(), source)
+            }
+            Error::Header { path, pos, message } => {
+                write!(f, "invalid note header in {}", path.display())?;
+                if let Some(pos) = pos {
+                    write!(f, " at {}", pos)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Index(e) => write!(f, "index error: {}", e),
+            Error::Link(link) => write!(f, "can not resolve link {}", link),
+            Error::Markdown(message) => {
+                write!(f, "markdown parser error: {}", message)
+            }
+            Error::Highlight(message) => {
+                write!(f, "code highlighter error: {}", message)
+            }
+            Error::Conflict { path } => {
+                write!(f, "{} is changed on disk since loaded", path.display())
+            }
+            Error::Git(e) => write!(f, "git error: {}", e.message()),
+            Error::Pattern(e) => write!(f, "invalid pattern: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Index(e) => Some(e.as_ref()),
+            Error::Git(e) => Some(e),
+            Error::Pattern(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<tantivy::TantivyError> for Error {
+    fn from(e: tantivy::TantivyError) -> Self {
+        Error::Index(Box::new(e))
+    }
+}
+
+impl From<tantivy::query::QueryParserError> for Error {
+    fn from(e: tantivy::query::QueryParserError) -> Self {
+        Error::Index(Box::new(e))
+    }
+}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Self {
+        Error::Git(e)
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Error::Pattern(e)
+    }
+}
+
+impl From<tree_sitter::LanguageError> for Error {
+    fn from(e: tree_sitter::LanguageError) -> Self {
+        Error::Markdown(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Position;
+
+    #[test]
+    fn test_position_from_offset() {
+        let text = "title = \"a\"\ndate = x\n";
+        let pos = Position::from_offset(text, 19, 1);
+        assert_eq!(pos, Position { line: 3, column: 8 });
+    }
+}
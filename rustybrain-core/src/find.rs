@@ -0,0 +1,220 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::error::Result;
+
+/// Text to look for in notes, as typed with its options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub pattern: String,
+    /// The pattern is a regular expression, not plain text.
+    pub regex: bool,
+    pub match_case: bool,
+}
+
+/// Matcher of a query.
+pub struct Finder {
+    regex: Regex,
+    /// Groups like `$1` in replacements are expanded.
+    expand: bool,
+}
+
+/// Byte range of a match, and what it is replaced with.
+type Replacing = (Range<usize>, String);
+
+/// Line of a zettel with matches, and how it reads once replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// Line in the content, from 0.
+    pub line: usize,
+    pub text: String,
+    pub replaced: String,
+}
+
+/// Lines of a zettel with matches, to preview a replace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMatches {
+    pub zid: String,
+    pub title: String,
+    pub lines: Vec<LineMatch>,
+}
+
+/// Text of a zettel before and after a replace, saved with the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub zid: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// Zettels changed by a replace in all of them, to undo it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replacement {
+    pub edits: Vec<Edit>,
+}
+
+impl Finder {
+    pub fn new(query: &Query) -> Result<Self> {
+        let pattern = if query.regex {
+            query.pattern.clone()
+        } else {
+            regex::escape(&query.pattern)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!query.match_case)
+            .multi_line(true)
+            .build()?;
+        Ok(Finder {
+            regex,
+            expand: query.regex,
+        })
+    }
+
+    /// Byte ranges of the matches in `text`, empty ones left out.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .map(|m| m.range())
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+
+    /// `text` with the matches replaced by `with`, where `$1` or `${name}`
+    /// are groups of a regular expression.
+    pub fn replace(&self, text: &str, with: &str) -> String {
+        splice(text, 0, &self.replacements(text, with))
+    }
+
+    /// Byte range of each match in `text`, empty ones too, and what it is
+    /// replaced with.
+    fn replacements(&self, text: &str, with: &str) -> Vec<Replacing> {
+        self.regex
+            .captures_iter(text)
+            .filter_map(|caps| {
+                let range = caps.get(0)?.range();
+                let mut to = String::new();
+                if self.expand {
+                    caps.expand(with, &mut to);
+                } else {
+                    to.push_str(with);
+                }
+                Some((range, to))
+            })
+            .collect()
+    }
+
+    /// Lines of `content` with matches, and how they read with the
+    /// matches replaced by `with`. Matches are found in the whole content
+    /// as a replace does, lines a match spans are shown together.
+    pub fn lines(&self, content: &str, with: &str) -> Vec<LineMatch> {
+        let mut spans: Vec<(Range<usize>, Vec<Replacing>)> = vec![];
+        for (range, to) in self.replacements(content, with) {
+            let start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            // A match ending with a line break doesn't reach the next line.
+            let last = match content[range.clone()].strip_suffix('\n') {
+                Some(m) => range.start + m.len(),
+                None => range.end,
+            };
+            let end = content[last..]
+                .find('\n')
+                .map_or(content.len(), |i| last + i);
+            match spans.last_mut() {
+                Some((span, reps)) if start <= span.end => {
+                    span.end = span.end.max(end);
+                    reps.push((range, to));
+                }
+                _ => spans.push((start..end, vec![(range, to)])),
+            }
+        }
+        let (mut line, mut counted) = (0, 0);
+        spans
+            .into_iter()
+            .map(|(span, reps)| {
+                line += content[counted..span.start].matches('\n').count();
+                counted = span.start;
+                let text = &content[span.clone()];
+                LineMatch {
+                    line,
+                    text: text.to_string(),
+                    replaced: splice(text, span.start, &reps),
+                }
+            })
+            .collect()
+    }
+}
+
+/// `text` starting at `offset` of the whole, with the ranges of the whole
+/// replaced. A range may run past the end by the line break left out.
+fn splice(text: &str, offset: usize, reps: &[Replacing]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (range, to) in reps {
+        out.push_str(&text[last..range.start - offset]);
+        out.push_str(to);
+        last = (range.end - offset).min(text.len());
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Finder, Query};
+
+    fn finder(pattern: &str, regex: bool, match_case: bool) -> Finder {
+        Finder::new(&Query {
+            pattern: pattern.to_string(),
+            regex,
+            match_case,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_finder() {
+        let text = "Call Bob (a.k.a. bob).\nbobby\n";
+        assert_eq!(
+            finder("bob", false, false).find(text),
+            vec![5..8, 17..20, 23..26]
+        );
+        assert_eq!(finder("bob", false, true).find(text), vec![17..20, 23..26]);
+        assert_eq!(finder("a.k.a.", false, true).find(text), vec![10..16]);
+        assert_eq!(finder(r"\bbob\b", true, true).find(text), vec![17..20]);
+        assert!(finder("x*", true, true).find(text).is_empty());
+        assert!(Finder::new(&Query {
+            pattern: "(".to_string(),
+            regex: true,
+            match_case: false,
+        })
+        .is_err());
+
+        assert_eq!(finder("(a", false, true).replace("(a $1", "$1"), "$1 $1");
+        assert_eq!(
+            finder(r"(\w+)@(\w+)", true, true).replace("me@home", "$2@$1"),
+            "home@me"
+        );
+        let lines = finder("bob", false, true).lines(text, "Rob");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].line, 0);
+        assert_eq!(lines[0].replaced, "Call Bob (a.k.a. Rob).");
+        assert_eq!(lines[1].replaced, "Robby");
+
+        let text = "one\ntwo\nthree\nfour\n";
+        let lines = finder(r"o\nt", true, true).lines(text, "o t");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line, 1);
+        assert_eq!(lines[0].text, "two\nthree");
+        assert_eq!(lines[0].replaced, "two three");
+        let lines = finder("^", true, true).lines(text, "- ");
+        // As a replace does, the empty end after the last line matches too.
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[3].line, 3);
+        assert_eq!(lines[3].replaced, "- four");
+        assert_eq!(lines[4].replaced, "- ");
+        let lines = finder("two\n", false, true).lines(text, "");
+        assert_eq!(lines[0].line, 1);
+        assert_eq!(lines[0].text, "two");
+        assert_eq!(lines[0].replaced, "");
+    }
+}
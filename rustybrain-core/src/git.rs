@@ -0,0 +1,310 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, TimeZone};
+use git2::{Index, IndexEntry, IndexTime, Oid, Repository, Signature, Tree};
+
+use crate::error::{Error, Result};
+use crate::state::STATE_DIR;
+
+/// Commit of the history of a repo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    /// Id of the first parent, none for the first commit.
+    pub parent: Option<String>,
+    pub time: DateTime<Local>,
+    pub message: String,
+    /// Files changed by the commit, relative to the repo.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Versions of the notes of a repo, kept as commits of a local git
+/// repository in the repo directory.
+pub struct Versions {
+    repo: Repository,
+    root: PathBuf,
+}
+
+impl Versions {
+    /// Open the git repository of `repo_path`, creating it if there's none.
+    pub fn open(repo_path: &str) -> Result<Self> {
+        let root = PathBuf::from(repo_path);
+        let repo = match Repository::open(&root) {
+            Ok(repo) => repo,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                let repo = Repository::init(&root)?;
+                let ignore = root.join(".gitignore");
+                if !ignore.exists() {
+                    fs::write(&ignore, format!("{}/\n", STATE_DIR))
+                        .map_err(|e| Error::io(&ignore, e))?;
+                }
+                repo
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Versions { repo, root })
+    }
+
+    /// Nothing is committed yet.
+    pub fn is_empty(&self) -> bool {
+        self.head().map_or(true, |c| c.is_none())
+    }
+
+    /// The file of `path` is in the last commit.
+    pub fn is_tracked(&self, path: &Path) -> bool {
+        match self.head() {
+            Ok(Some(c)) => c
+                .tree()
+                .map_or(false, |t| t.get_path(self.relative(path)).is_ok()),
+            _ => false,
+        }
+    }
+
+    /// Commit the files of `paths` as they are on disk, a deleted file is
+    /// removed. Only those are committed, not whatever else the user staged
+    /// in a repository adopted. Returns none if nothing changed since the
+    /// last commit.
+    pub fn commit(
+        &self,
+        paths: &[&Path],
+        message: &str,
+    ) -> Result<Option<Commit>> {
+        let parent = self.head()?;
+        let parent_tree = match parent.as_ref() {
+            Some(p) => Some(p.tree()?),
+            None => None,
+        };
+        let mut index = Index::new()?;
+        if let Some(tree) = parent_tree.as_ref() {
+            index.read_tree(tree)?;
+        }
+        for path in paths {
+            let rel = self.relative(path);
+            let file = self.root.join(rel);
+            if file.exists() {
+                let data = fs::read(&file).map_err(|e| Error::io(&file, e))?;
+                index.add(&Self::entry(rel, self.repo.blob(&data)?, &data))?;
+            } else if index.get_path(rel, 0).is_some() {
+                index.remove(rel, 0)?;
+            }
+        }
+        let tree = self.repo.find_tree(index.write_tree_to(&self.repo)?)?;
+        if parent_tree.as_ref().map(|t| t.id()) == Some(tree.id()) {
+            return Ok(None);
+        }
+        let sig = self.signature()?;
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let id = self.repo.commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            message,
+            &tree,
+            &parents,
+        )?;
+        self.sync_index(paths, parent_tree.as_ref())?;
+        self.describe(&self.repo.find_commit(id)?).map(Some)
+    }
+
+    /// Index entry of a file at `rel` of the repo, with `data` as `blob`.
+    fn entry(rel: &Path, blob: Oid, data: &[u8]) -> IndexEntry {
+        IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: data.len() as u32,
+            id: blob,
+            flags: 0,
+            flags_extended: 0,
+            path: rel.to_string_lossy().as_bytes().to_vec(),
+        }
+    }
+
+    /// Bring the committed paths the user has nothing staged for up to
+    /// date in the index of the repository, others are left as they are.
+    fn sync_index(&self, paths: &[&Path], before: Option<&Tree>) -> Result<()> {
+        let mut index = self.repo.index()?;
+        for path in paths {
+            let rel = self.relative(path);
+            let staged = index.get_path(rel, 0).map(|e| e.id);
+            let committed =
+                before.and_then(|t| t.get_path(rel).ok()).map(|e| e.id());
+            if staged != committed {
+                continue;
+            }
+            if self.root.join(rel).exists() {
+                index.add_path(rel)?;
+            } else if staged.is_some() {
+                index.remove_path(rel)?;
+            }
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    /// Commits changing the file of `path`, the newest first.
+    pub fn history(&self, path: &Path) -> Result<Vec<Commit>> {
+        let rel = self.relative(path);
+        let mut commits = vec![];
+        for c in self.walk()? {
+            let c = c?;
+            let blob = |tree: Option<Tree>| {
+                tree.and_then(|t| t.get_path(rel).ok()).map(|e| e.id())
+            };
+            let parent = c.parents().next().and_then(|p| p.tree().ok());
+            if blob(c.tree().ok()) != blob(parent) {
+                commits.push(self.describe(&c)?);
+            }
+        }
+        Ok(commits)
+    }
+
+    /// Latest `limit` commits across the repo, the newest first.
+    pub fn timeline(&self, limit: usize) -> Result<Vec<Commit>> {
+        let mut commits = vec![];
+        for c in self.walk()?.take(limit) {
+            commits.push(self.describe(&c?)?);
+        }
+        Ok(commits)
+    }
+
+    /// Text of the file of `path` as of commit `id`, none if the file is
+    /// not in it.
+    pub fn file_at(&self, id: &str, path: &Path) -> Result<Option<String>> {
+        let commit = self.repo.find_commit(Oid::from_str(id)?)?;
+        let entry = match commit.tree()?.get_path(self.relative(path)) {
+            Ok(entry) => entry,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let blob = self.repo.find_blob(entry.id())?;
+        Ok(Some(String::from_utf8_lossy(blob.content()).to_string()))
+    }
+
+    fn head(&self) -> Result<Option<git2::Commit<'_>>> {
+        match self.repo.head() {
+            Ok(head) => Ok(Some(head.peel_to_commit()?)),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Commits from HEAD back, the newest first.
+    fn walk(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<git2::Commit<'_>>> + '_> {
+        let mut walk = self.repo.revwalk()?;
+        if !self.is_empty() {
+            walk.push_head()?;
+        }
+        walk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+        Ok(walk.map(move |id| Ok(self.repo.find_commit(id?)?)))
+    }
+
+    fn describe(&self, c: &git2::Commit) -> Result<Commit> {
+        let parent = c.parents().next();
+        let old = match parent.as_ref() {
+            Some(p) => Some(p.tree()?),
+            None => None,
+        };
+        let diff = self.repo.diff_tree_to_tree(
+            old.as_ref(),
+            Some(&c.tree()?),
+            None,
+        )?;
+        let paths = diff
+            .deltas()
+            .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+            .map(Path::to_path_buf)
+            .collect();
+        Ok(Commit {
+            id: c.id().to_string(),
+            parent: parent.map(|p| p.id().to_string()),
+            time: Local
+                .timestamp_opt(c.time().seconds(), 0)
+                .single()
+                .unwrap_or_else(Local::now),
+            message: c.message().unwrap_or_default().trim_end().to_string(),
+            paths,
+        })
+    }
+
+    /// Identity of the git config, or a local one if there's none.
+    fn signature(&self) -> Result<Signature<'static>> {
+        match self.repo.signature() {
+            Ok(sig) => Ok(sig.to_owned()),
+            Err(_) => Ok(Signature::now("RustyBrain", "rustybrain@localhost")?),
+        }
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::Versions;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_commit_and_history() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        let versions = Versions::open(dir.to_str().unwrap()).unwrap();
+        assert!(versions.is_empty());
+        assert!(dir.join(".gitignore").exists());
+
+        let a = dir.join("notes/a.md");
+        let b = dir.join("notes/b.md");
+        fs::write(&a, "one\n").unwrap();
+        fs::write(&b, "b\n").unwrap();
+        let first = versions.commit(&[&a, &b], "Add a and b").unwrap().unwrap();
+        assert_eq!(first.parent, None);
+        assert_eq!(first.paths.len(), 2);
+        assert!(versions.is_tracked(&a));
+        assert!(!versions.is_tracked(&dir.join("notes/c.md")));
+        assert!(versions.commit(&[&a], "Nothing").unwrap().is_none());
+
+        fs::write(&a, "two\n").unwrap();
+        let second = versions.commit(&[&a], "Update a").unwrap().unwrap();
+        assert_eq!(second.parent.as_ref(), Some(&first.id));
+        assert_eq!(second.paths, vec![Path::new("notes/a.md")]);
+
+        let history = versions.history(&a).unwrap();
+        assert_eq!(history, vec![second.clone(), first.clone()]);
+        assert_eq!(versions.history(&b).unwrap().len(), 1);
+        assert_eq!(
+            versions.file_at(&first.id, &a).unwrap().as_deref(),
+            Some("one\n")
+        );
+        assert_eq!(versions.file_at(&first.id, Path::new("x")).unwrap(), None);
+
+        // Staged by the user, not ours to commit.
+        let staged = dir.join("staged.md");
+        fs::write(&staged, "mine\n").unwrap();
+        let mut index = versions.repo.index().unwrap();
+        index.add_path(Path::new("staged.md")).unwrap();
+        index.write().unwrap();
+
+        fs::remove_file(&b).unwrap();
+        versions.commit(&[&b], "Delete b").unwrap().unwrap();
+        assert!(!versions.is_tracked(&staged));
+        let index = versions.repo.index().unwrap();
+        assert!(index.get_path(Path::new("staged.md"), 0).is_some());
+        assert!(index.get_path(Path::new("notes/b.md"), 0).is_none());
+        let timeline = versions.timeline(2).unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].message, "Delete b");
+        assert_eq!(timeline[1], second);
+    }
+}
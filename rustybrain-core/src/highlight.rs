@@ -0,0 +1,283 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::rc::Rc;
+
+use tree_sitter::{Language, Parser, Query, QueryCursor};
+
+use crate::error::{Error, Result};
+
+/// Kinds of highlighted code, each styled by a `code-<kind>` text tag.
+pub const KINDS: [&str; 11] = [
+    "keyword",
+    "string",
+    "comment",
+    "number",
+    "function",
+    "type",
+    "constant",
+    "property",
+    "operator",
+    "punctuation",
+    "attribute",
+];
+
+/// Highlighted part of a code block, by byte range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub range: Range<usize>,
+    /// One of `KINDS`.
+    pub kind: &'static str,
+}
+
+/// Languages code blocks are highlighted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lang {
+    Rust,
+    Python,
+    JavaScript,
+    Shell,
+    Json,
+    Toml,
+}
+
+const SHELL_QUERY: &str = r#"
+(comment) @comment
+[(string) (raw_string) (ansii_c_string) (heredoc_body)] @string
+(command_name) @function
+(function_definition name: (word) @function)
+[(variable_name) (special_variable_name)] @property
+(file_descriptor) @number
+(test_operator) @operator
+[
+  "case" "declare" "do" "done" "elif" "else" "esac" "export" "fi" "for"
+  "function" "if" "in" "local" "readonly" "then" "typeset" "unset"
+  "unsetenv" "while"
+] @keyword
+"#;
+
+const JSON_QUERY: &str = r#"
+(pair key: (string) @property)
+(string) @string
+(escape_sequence) @string
+(number) @number
+[(true) (false) (null)] @constant
+"#;
+
+const TOML_QUERY: &str = r#"
+(table [(bare_key) (quoted_key) (dotted_key)] @type)
+(table_array_element [(bare_key) (quoted_key) (dotted_key)] @type)
+(comment) @comment
+[(bare_key) (quoted_key)] @property
+(string) @string
+[(integer) (float)] @number
+(boolean) @constant
+[
+  (offset_date_time) (local_date_time) (local_date) (local_time)
+] @string.special
+"=" @operator
+["." "," "[" "]" "[[" "]]" "{" "}"] @punctuation
+"#;
+
+extern "C" {
+    /// Built from `grammars/toml` by the build script.
+    fn tree_sitter_toml() -> Language;
+}
+
+thread_local! {
+    /// Highlight queries by language, compiled once.
+    static QUERIES: RefCell<HashMap<Lang, Rc<Query>>> =
+        RefCell::new(HashMap::new());
+}
+
+impl Lang {
+    /// Language named by the info string of a fenced code block, like
+    /// `rust` or `py title="x"`.
+    pub fn from_info(info: &str) -> Option<Self> {
+        let name = info.split_whitespace().next()?.to_lowercase();
+        let lang = match name.trim_start_matches('{').trim_start_matches('.') {
+            "rust" | "rs" => Lang::Rust,
+            "python" | "py" | "python3" => Lang::Python,
+            "javascript" | "js" | "jsx" | "mjs" | "cjs" | "node" => {
+                Lang::JavaScript
+            }
+            "sh" | "bash" | "shell" | "zsh" | "console" => Lang::Shell,
+            "json" => Lang::Json,
+            "toml" => Lang::Toml,
+            _ => return None,
+        };
+        Some(lang)
+    }
+
+    fn grammar(&self) -> (Language, &'static str) {
+        match self {
+            Lang::Rust => (
+                tree_sitter_rust::language(),
+                tree_sitter_rust::HIGHLIGHT_QUERY,
+            ),
+            Lang::Python => (
+                tree_sitter_python::language(),
+                tree_sitter_python::HIGHLIGHT_QUERY,
+            ),
+            Lang::JavaScript => (
+                tree_sitter_javascript::language(),
+                tree_sitter_javascript::HIGHLIGHT_QUERY,
+            ),
+            Lang::Shell => (tree_sitter_bash::language(), SHELL_QUERY),
+            Lang::Json => (tree_sitter_json::language(), JSON_QUERY),
+            Lang::Toml => (unsafe { tree_sitter_toml() }, TOML_QUERY),
+        }
+    }
+
+    fn query(&self, language: Language, source: &str) -> Result<Rc<Query>> {
+        QUERIES.with(|queries| {
+            if let Some(q) = queries.borrow().get(self) {
+                return Ok(q.clone());
+            }
+            let q = Rc::new(
+                Query::new(language, source)
+                    .map_err(|e| Error::Highlight(e.message))?,
+            );
+            queries.borrow_mut().insert(*self, q.clone());
+            Ok(q)
+        })
+    }
+}
+
+/// Highlight `code` of a fenced code block with info string `info`, none
+/// if the language is not known.
+pub fn highlight(info: &str, code: &str) -> Result<Vec<Span>> {
+    let lang = match Lang::from_info(info) {
+        Some(lang) => lang,
+        None => return Ok(vec![]),
+    };
+    let (language, source) = lang.grammar();
+    let query = lang.query(language, source)?;
+    let mut parser = Parser::new();
+    parser
+        .set_language(language)
+        .map_err(|e| Error::Highlight(e.to_string()))?;
+    let tree = match parser.parse(code, None) {
+        Some(tree) => tree,
+        None => return Ok(vec![]),
+    };
+    let names = query.capture_names();
+    let mut cursor = QueryCursor::new();
+    let mut seen = HashSet::new();
+    let mut spans = vec![];
+    let captures =
+        cursor.captures(&query, tree.root_node(), |n| &code[n.byte_range()]);
+    for (m, i) in captures {
+        let c = m.captures[i];
+        // The first pattern matching a node wins, as in tree-sitter's own
+        // highlighter.
+        if !seen.insert(c.node.byte_range()) {
+            continue;
+        }
+        // Nodes in a highlighted one keep its kind, e.g. the keys of a
+        // dotted TOML table name.
+        if spans
+            .last()
+            .map_or(false, |s: &Span| c.node.start_byte() < s.range.end)
+        {
+            continue;
+        }
+        if let Some(kind) = kind_of(&names[c.index as usize]) {
+            spans.push(Span {
+                range: c.node.byte_range(),
+                kind,
+            });
+        }
+    }
+    Ok(spans)
+}
+
+/// Kind of a capture name of a highlight query, like `function.method`.
+fn kind_of(capture: &str) -> Option<&'static str> {
+    let kind = match capture {
+        "constructor" | "tag" => "type",
+        "variable.builtin" | "constant.builtin" | "boolean" => "constant",
+        "escape" | "string.special" => "string",
+        "label" => "attribute",
+        "variable.parameter" => return None,
+        _ => capture.split('.').next()?,
+    };
+    KINDS.iter().find(|k| **k == kind).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight, Lang};
+
+    fn kinds<'a>(code: &'a str, info: &str) -> Vec<(&'a str, &'static str)> {
+        highlight(info, code)
+            .unwrap()
+            .into_iter()
+            .map(|s| (&code[s.range], s.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_info() {
+        assert_eq!(Lang::from_info("rust"), Some(Lang::Rust));
+        assert_eq!(Lang::from_info("py title=\"a\""), Some(Lang::Python));
+        assert_eq!(Lang::from_info("Bash"), Some(Lang::Shell));
+        assert_eq!(Lang::from_info("haskell"), None);
+        assert_eq!(Lang::from_info(""), None);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let spans = kinds("fn main() { let x = \"hi\"; } // end\n", "rust");
+        assert!(spans.contains(&("fn", "keyword")));
+        assert!(spans.contains(&("main", "function")));
+        assert!(spans.contains(&("\"hi\"", "string")));
+        assert!(spans.contains(&("// end", "comment")));
+
+        let spans = kinds("{\"a\": 1, \"b\": true}", "json");
+        assert!(spans.contains(&("\"a\"", "property")));
+        assert!(spans.contains(&("1", "number")));
+        assert!(spans.contains(&("true", "constant")));
+
+        let spans = kinds("if true; then echo $HOME; fi # x\n", "sh");
+        assert!(spans.contains(&("if", "keyword")));
+        assert!(spans.contains(&("echo", "function")));
+        assert!(spans.contains(&("# x", "comment")));
+
+        assert!(kinds("def f(): pass", "python").contains(&("def", "keyword")));
+        assert!(kinds("const a = 1;", "js").contains(&("const", "keyword")));
+        assert!(kinds("x", "text").is_empty());
+    }
+
+    #[test]
+    fn test_highlight_toml() {
+        let code = "[repo] # main\npath = \"~/a\"\nn = -1\nt = { on = true }\n";
+        let spans = kinds(code, "toml");
+        assert_eq!(
+            spans,
+            vec![
+                ("[", "punctuation"),
+                ("repo", "type"),
+                ("]", "punctuation"),
+                ("# main", "comment"),
+                ("path", "property"),
+                ("=", "operator"),
+                ("\"~/a\"", "string"),
+                ("n", "property"),
+                ("=", "operator"),
+                ("-1", "number"),
+                ("t", "property"),
+                ("=", "operator"),
+                ("{", "punctuation"),
+                ("on", "property"),
+                ("=", "operator"),
+                ("true", "constant"),
+                ("}", "punctuation"),
+            ]
+        );
+        let spans = kinds("[[a.b]]\nd = 1979-05-27\n", "toml");
+        assert!(spans.contains(&("a.b", "type")));
+        assert!(!spans.contains(&("b", "property")));
+        assert!(spans.contains(&("1979-05-27", "string")));
+    }
+}
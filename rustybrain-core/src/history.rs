@@ -0,0 +1,113 @@
+/// Browser like history of visited items.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    back: Vec<T>,
+    current: Option<T>,
+    forward: Vec<T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History {
+            back: vec![],
+            current: None,
+            forward: vec![],
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Move to `item`, the forward history is dropped.
+    pub fn visit(&mut self, item: T) {
+        if let Some(cur) = self.current.take() {
+            self.back.push(cur);
+        }
+        self.current = Some(item);
+        self.forward.clear();
+    }
+
+    pub fn current(&self) -> Option<&T> {
+        self.current.as_ref()
+    }
+
+    /// Move to the previous item, and return it.
+    pub fn back(&mut self) -> Option<&T> {
+        let prev = self.back.pop()?;
+        if let Some(cur) = self.current.replace(prev) {
+            self.forward.push(cur);
+        }
+        self.current.as_ref()
+    }
+
+    /// Move to the item `back` left, and return it.
+    pub fn forward(&mut self) -> Option<&T> {
+        let next = self.forward.pop()?;
+        if let Some(cur) = self.current.replace(next) {
+            self.back.push(cur);
+        }
+        self.current.as_ref()
+    }
+
+    /// Item `back` would move to, staying where we are.
+    pub fn peek_back(&self) -> Option<&T> {
+        self.back.last()
+    }
+
+    /// Item `forward` would move to, staying where we are.
+    pub fn peek_forward(&self) -> Option<&T> {
+        self.forward.last()
+    }
+
+    pub fn can_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::History;
+
+    #[test]
+    fn test_visit() {
+        let mut h = History::default();
+        assert!(h.current().is_none());
+        h.visit(1);
+        h.visit(2);
+        assert_eq!(h.current(), Some(&2));
+        assert_eq!(h.back, vec![1]);
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut h = History::default();
+        h.visit(1);
+        h.visit(2);
+        h.visit(3);
+        assert_eq!(h.back(), Some(&2));
+        assert_eq!(h.back(), Some(&1));
+        assert_eq!(h.back(), None);
+        assert_eq!(h.current(), Some(&1));
+        assert_eq!(h.forward(), Some(&2));
+        assert!(h.can_forward());
+
+        h.visit(4);
+        assert!(!h.can_forward());
+        assert_eq!(h.back(), Some(&2));
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut h = History::default();
+        h.visit(1);
+        h.visit(2);
+        assert_eq!(h.peek_back(), Some(&1));
+        assert_eq!(h.peek_forward(), None);
+        assert_eq!(h.current(), Some(&2));
+        h.back();
+        assert_eq!(h.peek_forward(), Some(&2));
+    }
+}
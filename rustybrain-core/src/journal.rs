@@ -0,0 +1,141 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::state::STATE_DIR;
+
+/// Unsaved edits of a zettel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub zid: String,
+    pub title: String,
+    pub content: String,
+}
+
+/// Swap files of dirty buffers, kept in `.rustybrain/journal/` of a repo
+/// until the zettels are saved, to recover edits after a crash.
+pub struct Journal {
+    dir: PathBuf,
+}
+
+impl Journal {
+    pub fn new(repo_path: &str) -> Self {
+        Journal {
+            dir: Path::new(repo_path).join(STATE_DIR).join("journal"),
+        }
+    }
+
+    pub fn write(&self, swap: &Swap) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| Error::io(&self.dir, e))?;
+        let path = self.path(&swap.zid);
+        let s = toml::to_string(swap).map_err(|e| {
+            Error::io(&path, io::Error::new(io::ErrorKind::InvalidData, e))
+        })?;
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, s).map_err(|e| Error::io(&tmp, e))?;
+        fs::rename(&tmp, &path).map_err(|e| Error::io(&path, e))
+    }
+
+    /// Forget edits of zettel `zid`, e.g. it is saved.
+    pub fn remove(&self, zid: &str) -> Result<()> {
+        let path = self.path(zid);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(Error::io(&path, e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Edits left by last session.
+    pub fn entries(&self) -> Result<Vec<Swap>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Error::io(&self.dir, e)),
+        };
+        let mut swaps = vec![];
+        for entry in dir {
+            let path = entry.map_err(|e| Error::io(&self.dir, e))?.path();
+            if path.extension().map_or(true, |ext| ext != "toml") {
+                continue;
+            }
+            let s =
+                fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+            let swap: Swap = toml::from_str(&s).map_err(|e| {
+                Error::io(&path, io::Error::new(io::ErrorKind::InvalidData, e))
+            })?;
+            swaps.push(swap);
+        }
+        swaps.sort_by(|a, b| a.zid.cmp(&b.zid));
+        Ok(swaps)
+    }
+
+    /// Forget all edits.
+    pub fn clear(&self) -> Result<()> {
+        for swap in self.entries()? {
+            self.remove(&swap.zid)?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, zid: &str) -> PathBuf {
+        self.dir.join(file_name(zid)).with_extension("toml")
+    }
+}
+
+/// Name for a file of zettel `zid`, the hash of it, so no two zettels
+/// share one whatever their ids are made of.
+pub(crate) fn file_name(zid: &str) -> String {
+    let hash = Sha256::digest(zid.as_bytes());
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{file_name, Journal, Swap};
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_write_and_remove() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let journal = Journal::new(dir.to_str().unwrap());
+        assert!(journal.entries().unwrap().is_empty());
+
+        let swap = Swap {
+            zid: "@/notes/a.md".to_string(),
+            title: "A".to_string(),
+            content: "Hello\n\n\"world\"\n".to_string(),
+        };
+        journal.write(&swap).unwrap();
+        journal
+            .write(&Swap {
+                zid: "@/notes/b.md".to_string(),
+                ..swap.clone()
+            })
+            .unwrap();
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], swap);
+
+        journal.remove("@/notes/b.md").unwrap();
+        journal.remove("@/notes/b.md").unwrap();
+        assert_eq!(journal.entries().unwrap(), vec![swap]);
+
+        journal.clear().unwrap();
+        assert!(journal.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("@/a.md"), file_name("@/a.md"));
+        assert_ne!(file_name("@/a-b.md"), file_name("@/a_b.md"));
+        assert_ne!(file_name("@/笔记.md"), file_name("@/日记.md"));
+    }
+}
@@ -1,8 +1,10 @@
 use std::{
-    cell::RefCell,
+    cell::{Ref, RefCell},
     collections::{HashMap, HashSet},
     fs::{self, create_dir_all, DirEntry},
-    path::{Path, PathBuf},
+    io,
+    ops::Range,
+    path::{Component, Path, PathBuf},
     rc::Rc,
     slice::Iter,
     time::SystemTime,
@@ -13,15 +15,77 @@ use chrono::Local;
 use tantivy::{
     collector::TopDocs,
     query::QueryParser,
-    schema::{Field, Schema, Value, STORED, TEXT},
-    Document, Index,
+    schema::{Field, Schema, Value, STORED, STRING, TEXT},
+    Document, Index, Term,
 };
 
-use crate::{config::Config, zettel::Zettel};
+use crate::{
+    attachment::{self, Attachment, Attachments, MissingFile, ATTACHMENTS_DIR},
+    config::Config,
+    error::{Error, Result},
+    find::{Edit, FileMatches, Finder, Replacement},
+    git::Versions,
+    journal::Journal,
+    snapshot::Snapshots,
+    state::STATE_DIR,
+    task::{self, Task},
+    zettel::{Anchor, LinkContext, Zettel},
+};
+
+/// How many versions of a zettel are looked through for the text it was
+/// loaded as.
+const BASE_MAX: usize = 20;
+
+/// Zettel a link points to, and the part of it the anchor of the link
+/// points to.
+pub struct Target {
+    pub zettel: Rc<RefCell<Zettel>>,
+    /// Byte range of the section or block in the content.
+    pub range: Option<Range<usize>>,
+}
+
+/// A zettel is saved, keeping a version of it may have failed still.
+#[derive(Debug)]
+pub struct Saved {
+    /// Why no version of the zettel is kept.
+    pub version: Option<Error>,
+}
+
+/// Zettels a replace in all of them is saved in, with why it stopped at the
+/// others, or why no version of them is kept.
+#[derive(Debug)]
+pub struct Replaced {
+    /// Edits of the zettels saved, to undo them.
+    pub replacement: Replacement,
+    pub error: Option<Error>,
+}
+
+impl Saved {
+    /// Failing to keep the version as an error, for callers not telling it
+    /// from failing to save.
+    pub fn versioned(self) -> Result<()> {
+        match self.version {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Title or alias of a zettel found in another one without a link.
+#[derive(Debug)]
+pub struct Mention {
+    pub zettel: Rc<RefCell<Zettel>>,
+    /// Byte range of the mention in the content of `zettel`.
+    pub range: Range<usize>,
+    pub context: LinkContext,
+}
 
 #[derive(Clone)]
 pub struct Kasten {
     config: Rc<RefCell<Config>>,
+    /// Repo of config when the kasten is built, kept while config moves on
+    /// to another one.
+    repo_path: String,
 
     #[allow(dead_code)]
     schema: Schema,
@@ -36,16 +100,19 @@ pub struct Kasten {
 }
 
 impl Kasten {
-    pub fn new(config: Rc<RefCell<Config>>) -> Result<Self, anyhow::Error> {
+    pub fn new(config: Rc<RefCell<Config>>) -> Result<Self> {
         let mut schema_builder = Schema::builder();
         let title = schema_builder.add_text_field("title", TEXT | STORED);
-        let path = schema_builder.add_text_field("path", TEXT | STORED);
+        // Kept whole, to replace the document of a saved zettel by it.
+        let path = schema_builder.add_text_field("path", STRING | STORED);
         let body = schema_builder.add_text_field("body", TEXT);
         let schema = schema_builder.build();
         let index = Index::create_in_ram(schema.clone());
 
+        let repo_path = config.borrow().repo_path().to_string();
         let mut kasten = Kasten {
             config,
+            repo_path,
             schema,
             index,
             title,
@@ -59,27 +126,71 @@ impl Kasten {
         Ok(kasten)
     }
 
-    fn build(&mut self) -> Result<(), anyhow::Error> {
-        self.build_index()?;
+    fn build(&mut self) -> Result<()> {
         let mut zettels = vec![];
-        let mut backlinks: HashMap<String, Vec<usize>> = HashMap::new();
         for entry in self.iter_from_disk() {
-            let z = entry?;
-            for link_to in z.link_to_iter() {
-                if let Some(v) = backlinks.get_mut(link_to) {
-                    v.push(zettels.len());
-                } else {
-                    backlinks.insert(link_to.to_string(), vec![zettels.len()]);
+            zettels.push(Rc::new(RefCell::new(entry?)));
+        }
+        self.zettels = zettels;
+        self.build_backlinks();
+        // Indexed from the zettels just read, not the ones before.
+        self.build_index()?;
+        Ok(())
+    }
+
+    /// Index links by the ids of the zettels they point to, with links by
+    /// titles, aliases and anchors resolved.
+    fn build_backlinks(&mut self) {
+        let mut names: HashMap<String, String> = HashMap::new();
+        let mut zids = HashSet::new();
+        for z in self.zettels.iter() {
+            let z = z.borrow();
+            zids.insert(z.zid().to_string());
+            for name in std::iter::once(z.title())
+                .chain(z.aliases().iter().map(|a| a.as_str()))
+            {
+                names
+                    .entry(name.to_lowercase())
+                    .or_insert_with(|| z.zid().to_string());
+            }
+        }
+        let mut backlinks: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, z) in self.zettels.iter().enumerate() {
+            for link_to in z.borrow().link_to_iter() {
+                let key_of = |note: &str| {
+                    if note.starts_with("@/") {
+                        zids.get(note).cloned()
+                    } else {
+                        names.get(&note.trim().to_lowercase()).cloned()
+                    }
+                };
+                let key = Anchor::split_by(link_to, |n| key_of(n).is_some())
+                    .and_then(|(note, _)| key_of(note))
+                    .unwrap_or_else(|| {
+                        // Missing, kept by the name it would have.
+                        let (note, _) = Anchor::split(link_to);
+                        if note.starts_with("@/") {
+                            note.to_string()
+                        } else {
+                            note.trim().to_lowercase()
+                        }
+                    });
+                let v = backlinks.entry(key).or_default();
+                // A zettel linking twice is one backlink.
+                if v.last() != Some(&i) {
+                    v.push(i);
                 }
             }
-            zettels.push(Rc::new(RefCell::new(z)));
         }
-        self.zettels = zettels;
         self.backlinks = backlinks;
-        Ok(())
     }
 
-    fn build_index(&self) -> Result<(), anyhow::Error> {
+    /// Read every zettel from disk again, and rebuild the index.
+    pub fn reindex(&mut self) -> Result<()> {
+        self.build()
+    }
+
+    fn build_index(&self) -> Result<()> {
         {
             let mut index_writer = self.index.writer(50_000_000)?;
             index_writer.delete_all_documents()?;
@@ -93,8 +204,11 @@ impl Kasten {
         Ok(())
     }
 
-    fn add_doc(&self, z: &Zettel) -> Result<(), anyhow::Error> {
+    fn add_doc(&self, z: &Zettel) -> Result<()> {
         let mut index_writer = self.index.writer(50_000_000)?;
+        if let Some(p) = z.path().to_str() {
+            index_writer.delete_term(Term::from_field_text(self.path, p));
+        }
         let title = self.title;
         let body = self.body;
         let mut doc = Document::default();
@@ -108,10 +222,7 @@ impl Kasten {
         Ok(())
     }
 
-    pub fn search_title(
-        &self,
-        kw: &str,
-    ) -> Result<HashSet<String>, anyhow::Error> {
+    pub fn search_title(&self, kw: &str) -> Result<HashSet<String>> {
         let reader = self
             .index
             .reader_builder()
@@ -132,25 +243,131 @@ impl Kasten {
         Ok(set)
     }
 
+    /// Zettels to offer for a link being typed as `text`, at most `limit`.
+    /// Titles and aliases starting with the text come first, then ones
+    /// having it, then hits of the title index, which matches whole words.
+    pub fn complete_title(
+        &self,
+        text: &str,
+        limit: usize,
+    ) -> Vec<Rc<RefCell<Zettel>>> {
+        let text = text.trim().to_lowercase();
+        let rank = |z: &Zettel| {
+            let names: Vec<String> = std::iter::once(z.title())
+                .chain(z.aliases().iter().map(String::as_str))
+                .map(str::to_lowercase)
+                .collect();
+            if names.iter().any(|n| n.starts_with(&text)) {
+                Some(0)
+            } else if names.iter().any(|n| n.contains(&text)) {
+                Some(1)
+            } else {
+                None
+            }
+        };
+        let mut ranked: Vec<(usize, Rc<RefCell<Zettel>>)> = self
+            .zettels
+            .iter()
+            .filter_map(|z| Some((rank(&z.borrow())?, z.clone())))
+            .collect();
+        ranked.sort_by_key(|(r, z)| (*r, z.borrow().title().to_lowercase()));
+        let mut found: Vec<Rc<RefCell<Zettel>>> =
+            ranked.into_iter().map(|(_, z)| z).collect();
+        // Partial words don't parse as queries, or don't match.
+        let paths = self.search_title(&text).unwrap_or_default();
+        for z in self.zettels.iter() {
+            let hit = z
+                .borrow()
+                .path()
+                .to_str()
+                .map_or(false, |p| paths.contains(p));
+            if hit && !found.iter().any(|f| Rc::ptr_eq(f, z)) {
+                found.push(z.clone());
+            }
+        }
+        found.truncate(limit);
+        found
+    }
+
+    /// Zettels whose body mentions the title or an alias of `z`, without
+    /// linking to it.
+    pub fn unlinked_mentions(&self, z: &Zettel) -> Result<Vec<Mention>> {
+        let reader = self
+            .index
+            .reader_builder()
+            .reload_policy(tantivy::ReloadPolicy::OnCommit)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let query_parser = QueryParser::for_index(&self.index, vec![self.body]);
+
+        let names: Vec<&str> = std::iter::once(z.title())
+            .chain(z.aliases().iter().map(|a| a.as_str()))
+            .filter(|n| !n.trim().is_empty())
+            .collect();
+        let mut paths = HashSet::new();
+        for name in names.iter() {
+            let phrase = format!("\"{}\"", name.replace('"', " "));
+            // Names made of no words can't be searched.
+            let query = match query_parser.parse_query(&phrase) {
+                Ok(query) => query,
+                Err(_) => continue,
+            };
+            for (_score, address) in
+                searcher.search(&query, &TopDocs::with_limit(50))?
+            {
+                let doc: Document = searcher.doc(address)?;
+                if let Some(Value::Str(s)) = doc.get_first(self.path) {
+                    paths.insert(s.to_string());
+                }
+            }
+        }
+
+        let linking = self.backlinks.get(z.zid());
+        let mut mentions = vec![];
+        for (i, other) in self.zettels.iter().enumerate() {
+            let o = other.borrow();
+            let path = o.path().to_string_lossy().to_string();
+            if o.zid() == z.zid()
+                || !paths.contains(&path)
+                || linking.map_or(false, |v| v.contains(&i))
+            {
+                continue;
+            }
+            for name in names.iter() {
+                for range in o.mentions(name) {
+                    mentions.push(Mention {
+                        zettel: other.clone(),
+                        context: o.context(range.clone()),
+                        range,
+                    });
+                }
+            }
+        }
+        Ok(mentions)
+    }
+
+    /// Rewrite the mention into a link to `zid`, and save it.
+    pub fn link_mention(&mut self, mention: &Mention, zid: &str) -> Result<()> {
+        let mut z = mention.zettel.borrow().clone();
+        z.link_range(mention.range.clone(), zid)?;
+        self.save(&mut z)?.versioned()
+    }
+
     pub fn iter(&self) -> Iter<'_, Rc<RefCell<Zettel>>> {
         self.zettels.iter()
     }
 
     fn iter_from_disk(&self) -> SyncDiskIter {
-        let c = (*self.config).borrow();
         SyncDiskIter {
             inner: None,
-            repo_path: c.repo_path().to_string(),
+            repo_path: self.repo_path.clone(),
         }
     }
 
-    pub fn create(
-        &mut self,
-        title: &str,
-    ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+    pub fn create(&mut self, title: &str) -> Result<Rc<RefCell<Zettel>>> {
         let path = self.new_path();
         if let Some(dir) = path.as_path().parent() {
-            create_dir_all(dir)?;
+            create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
         }
         let z = Zettel::create(&self.repo_path(), &path, title)?;
         self.add_doc(&z)?;
@@ -159,21 +376,646 @@ impl Kasten {
         Ok(z)
     }
 
-    pub fn save(&mut self, zettel: &Zettel) -> Result<(), anyhow::Error> {
+    /// Zettel titled `title`, created if there is none.
+    pub fn find_or_create(
+        &mut self,
+        title: &str,
+    ) -> Result<Rc<RefCell<Zettel>>> {
+        if let Some(z) =
+            self.zettels.iter().find(|z| z.borrow().title() == title)
+        {
+            return Ok(z.clone());
+        }
+        self.create(title)
+    }
+
+    /// Zettel made of `text` taken out of `from`, with its tags. The first
+    /// heading or line of the text is the title, a heading is left out of
+    /// the content.
+    pub fn extract(
+        &mut self,
+        from: &Zettel,
+        text: &str,
+    ) -> Result<Rc<RefCell<Zettel>>> {
+        let (title, content) = split_title(text);
+        let mut z = self.create(&title)?.borrow().clone();
+        z.add_tags(from.tags());
+        z.set_content(content)?;
+        self.save(&mut z)?.versioned()?;
+        self.resolve(z.zid())
+    }
+
+    /// Point links to `source` in the other zettels to `into`, and delete
+    /// `source`, once its text is merged into `into` with `Zettel::merge`.
+    /// Returns how many zettels are relinked.
+    pub fn merge(&mut self, source: &Zettel, into: &Zettel) -> Result<usize> {
+        let mut linking = vec![];
+        for z in self.zettels.iter() {
+            let mut z = z.borrow().clone();
+            if z.zid() != source.zid()
+                && z.zid() != into.zid()
+                && z.relink(source, into)? > 0
+            {
+                linking.push(z);
+            }
+        }
+        let message = format!("Relink {} to {}", source.title(), into.title());
+        self.save_all(&mut linking, &message).1?.versioned()?;
+        self.remove(source)?;
+        Ok(linking.len())
+    }
+
+    /// Move `zettel` to the file of id `zid`, like `@/projects/idea.md`.
+    /// Files it links to next to it go along, unless other zettels link to
+    /// them too: its links to those start from the repo then. Links to it
+    /// by id follow it. Returns the ids of the other zettels relinked.
+    pub fn move_zettel(
+        &mut self,
+        zettel: &Zettel,
+        zid: &str,
+    ) -> Result<Vec<String>> {
+        let repo_path = self.repo_path();
+        let repo = Path::new(&repo_path);
+        let to = note_path(&repo_path, zid).ok_or_else(|| {
+            let e = io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "is not a note of the repo, like @/notes/idea.md",
+            );
+            Error::io(Path::new(zid), e)
+        })?;
+        if to == zettel.path() {
+            return Ok(vec![]);
+        }
+        let from_dir = zettel.path().parent().unwrap_or(repo);
+        let to_dir = to.parent().unwrap_or(repo);
+        let mut moves: Vec<(PathBuf, PathBuf)> = vec![];
+        let mut links: HashMap<String, String> = HashMap::new();
+        for link in zettel.link_to_iter() {
+            let name = match file_link(link) {
+                Some(name) if from_dir != to_dir && !name.starts_with("@/") => {
+                    name
+                }
+                _ => continue,
+            };
+            let file = from_dir.join(&name);
+            if !file.is_file() || moves.iter().any(|(f, _)| *f == file) {
+                continue;
+            }
+            let shared = self.zettels.iter().any(|z| {
+                let z = z.borrow();
+                z.zid() != zettel.zid()
+                    && z.link_to_iter().any(|l| {
+                        resolve_file(&repo_path, z.path(), l).as_ref()
+                            == Some(&file)
+                    })
+            });
+            let dest = to_dir.join(&name);
+            let plain = Path::new(&name)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)));
+            let is_note = file.extension().map_or(false, |ext| ext == "md");
+            if plain && !shared && !is_note && !dest.exists() {
+                moves.push((file, dest));
+            } else if let Ok(rel) = file.strip_prefix(repo) {
+                let link = format!("@/{}", rel.display());
+                let link = if link.contains(' ') {
+                    format!("<{}>", link)
+                } else {
+                    link
+                };
+                links.insert(name, link);
+            }
+        }
+
+        let mut moved = zettel.clone();
+        moved.move_to(&repo_path, &to)?;
+        for (from, to) in moves.iter() {
+            if let Some(dir) = to.parent() {
+                create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+            }
+            fs::rename(from, to).map_err(|e| Error::io(to, e))?;
+        }
+        let follow = |link: &str| {
+            let (_, anchor) = Anchor::split_by(link, |n| n == zettel.zid())?;
+            Some(match anchor {
+                Some(anchor) => format!("{}{}", zid, anchor),
+                None => zid.to_string(),
+            })
+        };
+        moved.retarget(|l| {
+            file_link(l)
+                .and_then(|name| links.get(&name).cloned())
+                .or_else(|| follow(l))
+        })?;
+        let mut zettels = vec![moved];
+        for z in self.zettels.iter() {
+            let mut z = z.borrow().clone();
+            if z.zid() != zettel.zid() && z.retarget(follow)? > 0 {
+                zettels.push(z);
+            }
+        }
+
+        self.journal().remove(zettel.zid())?;
+        self.snapshots().rename(zettel.zid(), zid)?;
+        let mut paths = vec![zettel.path()];
+        for (from, to) in moves.iter() {
+            paths.extend([from.as_path(), to.as_path()]);
+        }
+        let message = format!("Move {} to {}", zettel.title(), zid);
+        let (n, saved) = self.save_moved(&mut zettels, &paths, &message);
+        if n == 0 {
+            // The files are moved even so.
+            self.build()?;
+        }
+        saved?.versioned()?;
+        Ok(zettels[1..].iter().map(|z| z.zid().to_string()).collect())
+    }
+
+    /// Delete the file of the zettel, and commit it when the repo is
+    /// versioned with git.
+    pub fn remove(&mut self, zettel: &Zettel) -> Result<()> {
+        let path = zettel.path();
+        fs::remove_file(path).map_err(|e| Error::io(path, e))?;
+        self.journal().remove(zettel.zid())?;
+        if let Some(versions) = self.versions()? {
+            versions.commit(&[path], &format!("Remove {}", zettel.title()))?;
+        }
+        self.build()
+    }
+
+    /// Lines of each zettel with matches of `finder`, and how they read
+    /// with the matches replaced by `with`.
+    pub fn find_all(
+        &self,
+        finder: &Finder,
+        with: &str,
+    ) -> Result<Vec<FileMatches>> {
+        let mut found: Vec<FileMatches> = self
+            .on_disk()?
+            .iter()
+            .map(|z| FileMatches {
+                zid: z.zid().to_string(),
+                title: z.title().to_string(),
+                lines: finder.lines(z.content(), with),
+            })
+            .filter(|m| !m.lines.is_empty())
+            .collect();
+        found.sort_by(|a, b| a.title.cmp(&b.title).then(a.zid.cmp(&b.zid)));
+        Ok(found)
+    }
+
+    /// Zettels as saved, without the unsaved edits of notes open in an
+    /// editor, which share theirs with the kasten.
+    fn on_disk(&self) -> Result<Vec<Zettel>> {
+        self.iter_from_disk().collect()
+    }
+
+    /// Replace the matches of `finder` with `with` in the content of every
+    /// zettel, and save those changed in the order of their ids. Returns
+    /// their texts before and after to undo it, of those saved before any
+    /// failure too.
+    pub fn replace_all(&mut self, finder: &Finder, with: &str) -> Replaced {
+        let mut zettels = match self.on_disk() {
+            Ok(zettels) => zettels,
+            Err(e) => {
+                return Replaced {
+                    replacement: Replacement::default(),
+                    error: Some(e),
+                }
+            }
+        };
+        zettels.sort_by(|a, b| a.zid().cmp(b.zid()));
+        let mut changed = vec![];
+        let mut edits = vec![];
+        for mut z in zettels {
+            let content = finder.replace(z.content(), with);
+            if content == z.content() {
+                continue;
+            }
+            let edit = z.text().and_then(|before| {
+                z.set_content(&content)?;
+                Ok(Edit {
+                    zid: z.zid().to_string(),
+                    before,
+                    after: z.text()?,
+                })
+            });
+            match edit {
+                Ok(edit) => {
+                    edits.push(edit);
+                    changed.push(z);
+                }
+                Err(e) => {
+                    return Replaced {
+                        replacement: Replacement::default(),
+                        error: Some(e),
+                    }
+                }
+            }
+        }
+        let message = format!("Replace in {} notes", changed.len());
+        let (n, saved) = self.save_all(&mut changed, &message);
+        edits.truncate(n);
+        Replaced {
+            replacement: Replacement { edits },
+            error: saved.and_then(Saved::versioned).err(),
+        }
+    }
+
+    /// Bring back the texts of zettels before `replacement`, except for
+    /// those changed since. Returns how many are restored.
+    pub fn undo_replace(&mut self, replacement: &Replacement) -> Result<usize> {
+        let mut restoring = vec![];
+        for edit in replacement.edits.iter() {
+            let mut z = match self.resolve(&edit.zid) {
+                Ok(z) => z.borrow().clone(),
+                Err(_) => continue,
+            };
+            if z.text()? != edit.after {
+                continue;
+            }
+            z.set_text(&edit.before)?;
+            restoring.push(z);
+        }
+        let message = format!("Undo replace in {} notes", restoring.len());
+        self.save_all(&mut restoring, &message).1?.versioned()?;
+        Ok(restoring.len())
+    }
+
+    /// Zettel of today, titled like `2022-01-31`.
+    pub fn daily(&mut self) -> Result<Rc<RefCell<Zettel>>> {
+        let title = Local::now().format("%Y-%m-%d").to_string();
+        self.find_or_create(&title)
+    }
+
+    /// Write every zettel to `dir` as a markdown named by its title, returns
+    /// how many are written.
+    pub fn export(&self, dir: &Path) -> Result<usize> {
+        create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        let mut names = HashSet::new();
+        for z in self.zettels.iter() {
+            let z = z.borrow();
+            let stem: String = z
+                .title()
+                .chars()
+                .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+                .collect();
+            let stem = match stem.trim() {
+                "" => "untitled".to_string(),
+                s => s.to_string(),
+            };
+            let mut name = format!("{}.md", stem);
+            let mut n = 1;
+            while !names.insert(name.clone()) {
+                n += 1;
+                name = format!("{} ({}).md", stem, n);
+            }
+            let path = dir.join(name);
+            fs::write(&path, z.content()).map_err(|e| Error::io(&path, e))?;
+        }
+        Ok(names.len())
+    }
+
+    /// Save the zettel, and commit it when the repo is versioned with git,
+    /// or keep a snapshot of it when not. Failing to keep the version
+    /// doesn't fail the save, the zettel is written already.
+    pub fn save(&mut self, zettel: &mut Zettel) -> Result<Saved> {
         zettel.save()?;
-        self.build()?;
+        self.update(zettel)?;
+        Ok(Saved {
+            version: self.keep_version(zettel).err(),
+        })
+    }
+
+    /// Take in a saved zettel without reading the others again, as the
+    /// newest one.
+    fn update(&mut self, zettel: &Zettel) -> Result<()> {
+        // The zettel may be the one of the kasten, borrowed by the caller.
+        let pos = self.zettels.iter().position(|z| {
+            std::ptr::eq(z.as_ptr(), zettel) || z.borrow().zid() == zettel.zid()
+        });
+        if let Some(pos) = pos {
+            self.zettels.remove(pos);
+        }
+        self.zettels
+            .insert(0, Rc::new(RefCell::new(zettel.clone())));
+        self.build_backlinks();
+        self.add_doc(zettel)
+    }
+
+    /// Save the zettels, rebuilding once for all of them and keeping their
+    /// versions in one commit of `message`. Stops at the first failing to
+    /// save, those before it stay saved. Returns how many are saved.
+    fn save_all(
+        &mut self,
+        zettels: &mut [Zettel],
+        message: &str,
+    ) -> (usize, Result<Saved>) {
+        self.save_moved(zettels, &[], message)
+    }
+
+    /// Save the zettels as `save_all` does, with the files of `moved`, moved
+    /// or deleted, in the same commit.
+    fn save_moved(
+        &mut self,
+        zettels: &mut [Zettel],
+        moved: &[&Path],
+        message: &str,
+    ) -> (usize, Result<Saved>) {
+        let mut failed = None;
+        let mut n = 0;
+        for z in zettels.iter_mut() {
+            if let Err(e) = z.save() {
+                failed = Some(e);
+                break;
+            }
+            n += 1;
+        }
+        if n == 0 {
+            return (0, failed.map_or(Ok(Saved { version: None }), Err));
+        }
+        let built = self.build();
+        let version = self
+            .keep_versions(&zettels[..n], moved, |_| message.to_string())
+            .err();
+        let saved = match (failed, built) {
+            (Some(e), _) | (None, Err(e)) => Err(e),
+            (None, Ok(())) => Ok(Saved { version }),
+        };
+        (n, saved)
+    }
+
+    fn keep_version(&self, zettel: &Zettel) -> Result<()> {
+        self.keep_versions(std::slice::from_ref(zettel), &[], |versions| {
+            let verb = if versions.is_tracked(zettel.path()) {
+                "Update"
+            } else {
+                "Add"
+            };
+            format!("{} {}", verb, zettel.title())
+        })
+    }
+
+    /// Commit the zettels, with the files of `moved`, as `message` when the
+    /// repo is versioned with git, or keep a snapshot of each when not.
+    fn keep_versions(
+        &self,
+        zettels: &[Zettel],
+        moved: &[&Path],
+        message: impl FnOnce(&Versions) -> String,
+    ) -> Result<()> {
+        match self.versions()? {
+            Some(versions) => {
+                // Files attached to the zettels are versioned along with them.
+                let repo_path = self.repo_path();
+                let dir = Path::new(&repo_path).join(ATTACHMENTS_DIR);
+                let attached: Vec<PathBuf> = zettels
+                    .iter()
+                    .flat_map(|z| {
+                        let repo_path = &repo_path;
+                        z.link_to_iter().filter_map(move |l| {
+                            resolve_file(repo_path, z.path(), l)
+                        })
+                    })
+                    .filter(|p| p.starts_with(&dir))
+                    .collect();
+                let mut paths: Vec<&Path> =
+                    zettels.iter().map(|z| z.path()).collect();
+                paths.extend(attached.iter().map(PathBuf::as_path));
+                paths.extend(moved);
+                versions.commit(&paths, &message(&versions))?;
+            }
+            None => {
+                let secs = Local::now().timestamp();
+                for z in zettels {
+                    self.snapshots().take(z.zid(), &z.text()?, secs)?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Path of a new zettel, named by the time, and numbered after the
+    /// first one made in the same second.
     fn new_path(&self) -> PathBuf {
-        let c = (*self.config).borrow();
-        let path = c.repo_path();
+        let path = &self.repo_path;
         let gen = Local::now().format("%Y%m%d%H%M%S").to_string();
-        Path::new(path).join(format!("notes/{}.md", gen))
+        let mut p = Path::new(path).join(format!("notes/{}.md", gen));
+        let mut n = 1;
+        while p.exists() {
+            n += 1;
+            p = Path::new(path).join(format!("notes/{}-{}.md", gen, n));
+        }
+        p
     }
 
     pub fn repo_path(&self) -> String {
-        self.config.borrow().repo_path().to_string()
+        self.repo_path.clone()
+    }
+
+    /// Versions of the notes, if `git` of `[repo]` is on. Notes already in
+    /// the repo are committed when the git repository is new.
+    pub fn versions(&self) -> Result<Option<Versions>> {
+        if !self.config.borrow().repo().git() {
+            return Ok(None);
+        }
+        let versions = Versions::open(&self.repo_path())?;
+        if versions.is_empty() {
+            let ignore = Path::new(&self.repo_path()).join(".gitignore");
+            let zettels: Vec<Ref<Zettel>> =
+                self.zettels.iter().map(|z| z.borrow()).collect();
+            let mut paths: Vec<&Path> =
+                zettels.iter().map(|z| z.path()).collect();
+            paths.push(&ignore);
+            versions.commit(&paths, "Import notes")?;
+        }
+        Ok(Some(versions))
+    }
+
+    /// Text `zettel` was loaded or saved as, looked up in its kept versions
+    /// to merge changes made on disk since. None if it's not one of the
+    /// last `BASE_MAX` versions.
+    pub fn base_of(&self, zettel: &Zettel) -> Result<Option<String>> {
+        match self.versions()? {
+            Some(versions) => {
+                for c in versions.history(zettel.path())?.iter().take(BASE_MAX)
+                {
+                    if let Some(text) =
+                        versions.file_at(&c.id, zettel.path())?
+                    {
+                        if zettel.is_base(&text) {
+                            return Ok(Some(text));
+                        }
+                    }
+                }
+            }
+            None => {
+                let snapshots = self.snapshots();
+                for s in snapshots.list(zettel.zid())?.iter().take(BASE_MAX) {
+                    let text = snapshots.read(s)?;
+                    if zettel.is_base(&text) {
+                        return Ok(Some(text));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Snapshots of zettels, kept when the repo is not versioned with git.
+    pub fn snapshots(&self) -> Snapshots {
+        Snapshots::new(&self.repo_path())
+    }
+
+    /// Files attached to the notes.
+    pub fn attachments(&self) -> Attachments {
+        Attachments::new(&self.repo_path())
+    }
+
+    /// Attachments with the zettels using them, and the files zettels link
+    /// to which are missing.
+    pub fn attachment_report(&self) -> Result<attachment::Report> {
+        let repo_path = self.repo_path();
+        let mut report = attachment::Report::default();
+        let attachments = self.attachments();
+        for path in attachments.list()? {
+            report.attachments.push(Attachment {
+                link: attachments.link(&path),
+                path,
+                used_by: vec![],
+            });
+        }
+        for z in self.zettels.iter() {
+            let z = z.borrow();
+            for link in z.link_to_iter() {
+                if is_external(link) || link.starts_with('#') {
+                    continue;
+                }
+                match resolve_file(&repo_path, z.path(), link) {
+                    Some(path) => {
+                        let used = report
+                            .attachments
+                            .iter_mut()
+                            .find(|a| a.path == path);
+                        if let Some(a) = used {
+                            if a.used_by.last().map(String::as_str)
+                                != Some(z.zid())
+                            {
+                                a.used_by.push(z.zid().to_string());
+                            }
+                        }
+                    }
+                    // Links to notes are by `@/` path or by title.
+                    None if self.resolve_link(link).is_err() => {
+                        report.missing.push(MissingFile {
+                            zid: z.zid().to_string(),
+                            link: link.to_string(),
+                        });
+                    }
+                    None => {}
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Unsaved edits of the repo.
+    pub fn journal(&self) -> Journal {
+        Journal::new(&self.repo_path())
+    }
+
+    /// Find the zettel a link inside the slip-box points to.
+    pub fn resolve(&self, link: &str) -> Result<Rc<RefCell<Zettel>>> {
+        self.zettels
+            .iter()
+            .find(|z| z.borrow().zid() == link)
+            .cloned()
+            .ok_or_else(|| Error::Link(link.to_string()))
+    }
+
+    /// Find the zettel a link in a note points to, either an `@/` path or
+    /// the title or an alias of a wiki link.
+    pub fn resolve_link(&self, link: &str) -> Result<Rc<RefCell<Zettel>>> {
+        self.split_link(link).map(|(z, _)| z)
+    }
+
+    /// Find the zettel a link points to, with the byte range of the section
+    /// or block of its anchor, like `Note#Heading` or `Note^id`.
+    pub fn resolve_anchor(&self, link: &str) -> Result<Target> {
+        let (zettel, anchor) = self.split_link(link)?;
+        let range = match anchor {
+            Some(anchor) => Some(
+                zettel
+                    .borrow()
+                    .anchor_range(&anchor)
+                    .ok_or_else(|| Error::Link(link.to_string()))?,
+            ),
+            None => None,
+        };
+        Ok(Target { zettel, range })
+    }
+
+    /// Zettel of a link and its anchor. The whole link is taken as the
+    /// zettel when the part before its anchor names none, as `C#` does.
+    fn split_link(
+        &self,
+        link: &str,
+    ) -> Result<(Rc<RefCell<Zettel>>, Option<Anchor>)> {
+        let (note, anchor) = Anchor::split(link);
+        self.find_note(note)
+            .map(|z| (z, anchor))
+            .or_else(|| {
+                let whole = if note != link { Some(link) } else { None };
+                whole.and_then(|l| self.find_note(l)).map(|z| (z, None))
+            })
+            .ok_or_else(|| Error::Link(link.to_string()))
+    }
+
+    /// Zettel of an `@/` path, or by its title or an alias.
+    fn find_note(&self, note: &str) -> Option<Rc<RefCell<Zettel>>> {
+        if note.starts_with("@/") {
+            return self.resolve(note).ok();
+        }
+        let name = note.trim().to_lowercase();
+        self.zettels
+            .iter()
+            .find(|z| {
+                let z = z.borrow();
+                z.title().to_lowercase() == name
+                    || z.aliases().iter().any(|a| a.to_lowercase() == name)
+            })
+            .cloned()
+    }
+
+    /// Tasks of all zettels, those due first by date, then the others by
+    /// zettel.
+    pub fn tasks(&self) -> Vec<Task> {
+        let mut tasks: Vec<Task> = self
+            .zettels
+            .iter()
+            .flat_map(|z| task::tasks(&z.borrow()))
+            .collect();
+        tasks.sort_by(|a, b| {
+            (a.due.is_none(), a.due, &a.zid, a.line).cmp(&(
+                b.due.is_none(),
+                b.due,
+                &b.zid,
+                b.line,
+            ))
+        });
+        tasks
+    }
+
+    /// Tags of all zettels, sorted, without duplicates.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .zettels
+            .iter()
+            .flat_map(|z| z.borrow().tags().to_vec())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
     }
 
     pub fn iter_backlinks(&self, z: &Zettel) -> Vec<Rc<RefCell<Zettel>>> {
@@ -189,15 +1031,80 @@ impl Kasten {
     }
 }
 
+/// Title of a zettel made of `text`, and its content: the first heading
+/// without its markers and the text after it, or else the first line and
+/// the whole text.
+fn split_title(text: &str) -> (String, &str) {
+    let text = text.trim_start_matches(['\n', '\r']);
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let heading = first.trim_start_matches('#');
+    let level = first.len() - heading.len();
+    if (1..=6).contains(&level) && heading.starts_with(' ') {
+        let title = heading.trim().trim_end_matches('#').trim_end();
+        return (title.to_string(), rest.trim_start_matches(['\n', '\r']));
+    }
+    (first.trim().to_string(), text)
+}
+
+/// Whether a link points outside the slip-box, e.g. to a web page.
+pub fn is_external(link: &str) -> bool {
+    link.contains("://") || link.starts_with("mailto:")
+}
+
+/// File of repo `repo_path` a link of the note at `note` points to, like
+/// the destination of an image. An `@/` link is relative to the repo,
+/// others are looked up next to the note, then in the repo and in its
+/// attachments. None if it's external or missing.
+pub fn resolve_file(
+    repo_path: &str,
+    note: &Path,
+    link: &str,
+) -> Option<PathBuf> {
+    let link = file_link(link)?;
+    let repo = Path::new(repo_path);
+    let candidates = match link.strip_prefix("@/") {
+        Some(rel) => vec![repo.join(rel)],
+        None => vec![
+            note.parent().unwrap_or(repo).join(&link),
+            repo.join(&link),
+            repo.join(ATTACHMENTS_DIR).join(&link),
+        ],
+    };
+    candidates.into_iter().find(|p| p.is_file())
+}
+
+/// Path of the file a link names, without its `<>` and with `%20` as spaces.
+/// None if it's external or empty.
+fn file_link(link: &str) -> Option<String> {
+    let link = link.trim().trim_start_matches('<').trim_end_matches('>');
+    if link.is_empty() || is_external(link) {
+        return None;
+    }
+    Some(link.replace("%20", " "))
+}
+
+/// File of the note of id `zid` in repo `repo_path`, none if the id isn't
+/// one of a note the repo would load, like `@/notes/idea.md`.
+fn note_path(repo_path: &str, zid: &str) -> Option<PathBuf> {
+    let rel = Path::new(zid.strip_prefix("@/")?);
+    let plain = rel.components().all(|c| match c {
+        Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+        _ => false,
+    });
+    if !plain || rel.extension().map_or(true, |ext| ext != "md") {
+        return None;
+    }
+    Some(Path::new(repo_path).join(rel))
+}
+
 impl IntoIterator for Kasten {
-    type Item = Result<Zettel, anyhow::Error>;
+    type Item = Result<Zettel>;
     type IntoIter = SyncDiskIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        let c = (*self.config).borrow();
         SyncDiskIter {
             inner: None,
-            repo_path: c.repo_path().to_string(),
+            repo_path: self.repo_path,
         }
     }
 }
@@ -208,7 +1115,7 @@ pub struct SyncDiskIter {
 }
 
 impl Iterator for SyncDiskIter {
-    type Item = Result<Zettel, anyhow::Error>;
+    type Item = Result<Zettel>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.inner.is_none() {
@@ -230,17 +1137,22 @@ impl Iterator for SyncDiskIter {
 }
 
 impl SyncDiskIter {
-    fn scan_markdowns(&self) -> Result<Vec<DirEntry>, anyhow::Error> {
+    fn scan_markdowns(&self) -> Result<Vec<DirEntry>> {
         let buf = Path::new(&self.repo_path);
         let mut dirs = vec![buf.to_path_buf()];
         let mut result = vec![];
         while let Some(cur) = dirs.pop() {
-            let rd = fs::read_dir(cur)?;
+            let rd = fs::read_dir(&cur).map_err(|e| Error::io(&cur, e))?;
             for entry in rd {
-                let item = entry?;
-                if item.path().is_dir() {
-                    dirs.push(item.path().to_path_buf());
-                } else {
+                let item = entry.map_err(|e| Error::io(&cur, e))?;
+                if Self::is_own(buf, &item) {
+                    continue;
+                }
+                let path = item.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                // Other files are ones notes link to, like images.
+                } else if path.extension().map_or(false, |ext| ext == "md") {
                     result.push(item);
                 }
             }
@@ -263,11 +1175,534 @@ impl SyncDiskIter {
         Ok(result)
     }
 
-    fn dir_entry_to_zettel(
-        &self,
-        entry: DirEntry,
-    ) -> Result<Zettel, anyhow::Error> {
+    /// Files the app keeps in the repo itself, not notes: its state, the
+    /// git repository of versions and tmp files of saving, named like
+    /// `.note.md`.
+    fn is_own(repo: &Path, entry: &DirEntry) -> bool {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let at_root = entry.path().parent() == Some(repo);
+        (at_root && [STATE_DIR, ".git", ".gitignore"].contains(&name.as_ref()))
+            || (name.starts_with('.') && name.ends_with(".md"))
+    }
+
+    fn dir_entry_to_zettel(&self, entry: DirEntry) -> Result<Zettel> {
         let ze = Zettel::from_md(&self.repo_path, &entry.path())?;
         Ok(ze)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fs, rc::Rc};
+
+    use super::{resolve_file, split_title, Kasten};
+    use crate::config::Config;
+    use crate::error::Error;
+    use crate::find::{Finder, Query};
+    use crate::testing::{self, temp_dir};
+
+    #[test]
+    fn test_find_or_create_and_export() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let mut kasten = testing::kasten(dir);
+
+        let a = kasten.find_or_create("a/b").unwrap();
+        let b = kasten.find_or_create("a/b").unwrap();
+        assert!(Rc::ptr_eq(&a, &b));
+        assert_eq!(kasten.iter().count(), 1);
+
+        let out = dir.join("out");
+        assert_eq!(kasten.export(&out).unwrap(), 1);
+        assert!(out.join("a_b.md").exists());
+    }
+
+    #[test]
+    fn test_create_twice() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let mut kasten = testing::kasten(dir);
+
+        let a = kasten.create("A").unwrap().borrow().zid().to_string();
+        let b = kasten.create("B").unwrap().borrow().zid().to_string();
+        assert_ne!(a, b);
+        assert_eq!(kasten.resolve(&a).unwrap().borrow().title(), "A");
+        assert_eq!(kasten.resolve(&b).unwrap().borrow().title(), "B");
+        assert_eq!(kasten.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_search_title_after_loading() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        testing::kasten(dir).create("Alpha").unwrap();
+
+        let kasten = testing::kasten(dir);
+        assert_eq!(kasten.search_title("alpha").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_save_updates_index() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let alpha = testing::note(dir, "a.md", "Alpha", "one\n");
+        let beta = testing::note(dir, "b.md", "Beta", "two\n");
+        let mut kasten = testing::kasten(dir);
+
+        // Saved through the zettel shared with the kasten, like an editor.
+        let shared = kasten.resolve(&beta).unwrap();
+        shared.borrow_mut().set_title("Gamma");
+        shared.borrow_mut().set_content("[[Alpha]]\n").unwrap();
+        kasten.save(&mut shared.borrow_mut()).unwrap();
+
+        assert_eq!(kasten.iter().count(), 2);
+        assert!(kasten.search_title("beta").unwrap().is_empty());
+        assert_eq!(kasten.search_title("gamma").unwrap().len(), 1);
+        let a = kasten.resolve(&alpha).unwrap().borrow().clone();
+        let linking = kasten.iter_backlinks(&a);
+        assert_eq!(linking.len(), 1);
+        assert_eq!(linking[0].borrow().title(), "Gamma");
+    }
+
+    #[test]
+    fn test_save_without_version() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let zid = testing::note(dir, "a.md", "a", "one\n");
+        // Snapshots can't be kept under a file.
+        fs::write(dir.join(".rustybrain"), "").unwrap();
+        let mut kasten = testing::kasten(dir);
+
+        let mut z = kasten.resolve(&zid).unwrap().borrow().clone();
+        z.set_content("two\n").unwrap();
+        let saved = kasten.save(&mut z).unwrap();
+        assert!(saved.version.is_some());
+        let z = kasten.resolve(&zid).unwrap();
+        assert_eq!(z.borrow().content(), "two\n");
+    }
+
+    #[test]
+    fn test_base_of() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let zid = testing::note(dir, "a.md", "a", "one\n");
+        let mut kasten = testing::kasten(dir);
+
+        let mut z = kasten.resolve(&zid).unwrap().borrow().clone();
+        assert_eq!(kasten.base_of(&z).unwrap(), None);
+        z.set_content("two\n").unwrap();
+        kasten.save(&mut z).unwrap().versioned().unwrap();
+        let base = z.text().unwrap();
+        fs::write(z.path(), "+++\ntitle = \"a\"\n+++\nthree\n").unwrap();
+        assert_eq!(kasten.base_of(&z).unwrap(), Some(base));
+    }
+
+    #[test]
+    fn test_unlinked_mentions() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let zid = testing::note(dir, "rust.md", "Rust", "");
+        testing::note(dir, "a.md", "other", "I like rust a lot.\n");
+        testing::note(dir, "b.md", "other", "Nothing here.\n");
+        let mut kasten = testing::kasten(dir);
+
+        let rust = kasten.resolve_link("rust").unwrap();
+        assert_eq!(rust.borrow().zid(), zid);
+        assert!(kasten.resolve_link("nope").is_err());
+        let mentions = kasten.unlinked_mentions(&rust.borrow()).unwrap();
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].context.text, "I like rust a lot.");
+
+        kasten.link_mention(&mentions[0], &zid).unwrap();
+        let rust = kasten.resolve(&zid).unwrap();
+        assert!(kasten.unlinked_mentions(&rust.borrow()).unwrap().is_empty());
+        assert_eq!(kasten.iter_backlinks(&rust.borrow()).len(), 1);
+    }
+
+    #[test]
+    fn test_complete_title() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        for (i, title) in ["Learning Rust", "Rust", "Python", "Trust issues"]
+            .iter()
+            .enumerate()
+        {
+            testing::note(dir, &format!("{}.md", i), title, "");
+        }
+        let kasten = testing::kasten(dir);
+        let titles = |text: &str, limit: usize| -> Vec<String> {
+            kasten
+                .complete_title(text, limit)
+                .iter()
+                .map(|z| z.borrow().title().to_string())
+                .collect()
+        };
+        assert_eq!(
+            titles("rus", 10),
+            vec!["Rust", "Learning Rust", "Trust issues"]
+        );
+        assert_eq!(titles("rust", 1), vec!["Rust"]);
+        assert_eq!(titles("(", 10), Vec::<String>::new());
+        assert_eq!(titles("", 10).len(), 4);
+    }
+
+    #[test]
+    fn test_extract_twice() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let from = testing::note(dir, "from.md", "From", "One\n\nTwo\n");
+        let mut kasten = testing::kasten(dir);
+        let from = kasten.resolve(&from).unwrap().borrow().clone();
+
+        kasten.create("New").unwrap();
+        let one = kasten.extract(&from, "One\n").unwrap();
+        let two = kasten.extract(&from, "Two\n").unwrap();
+        let (one, two) = (one.borrow(), two.borrow());
+        assert_ne!(one.zid(), two.zid());
+        assert_eq!(one.title(), "One");
+        assert_eq!(two.title(), "Two");
+        assert_eq!(kasten.resolve(one.zid()).unwrap().borrow().title(), "One");
+        assert_eq!(kasten.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_extract_and_merge() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let long = testing::note(
+            dir,
+            "long.md",
+            "Long",
+            "Intro\n\n## Ownership\n\nMoves.\n",
+        );
+        let mut kasten = testing::kasten(dir);
+
+        let mut long = kasten.resolve(&long).unwrap().borrow().clone();
+        long.add_tags(&["rust".to_string()]);
+        kasten.save(&mut long).unwrap();
+        let part = kasten.extract(&long, "## Ownership\n\nMoves.\n").unwrap();
+        let part = part.borrow().clone();
+        assert_eq!(part.title(), "Ownership");
+        assert_eq!(part.content(), "Moves.\n");
+        assert_eq!(part.tags(), ["rust"]);
+
+        let content = format!(
+            "See [[ownership#Moves]], [it]({}) and [[Long]].\n",
+            part.zid()
+        );
+        let other = testing::note(dir, "other.md", "Other", &content);
+        kasten.reindex().unwrap();
+
+        let mut long = kasten.resolve(long.zid()).unwrap().borrow().clone();
+        long.merge(&part).unwrap();
+        assert_eq!(
+            long.content(),
+            "Intro\n\n## Ownership\n\nMoves.\n\n# Ownership\n\nMoves.\n"
+        );
+        kasten.save(&mut long).unwrap();
+        assert_eq!(kasten.merge(&part, &long).unwrap(), 1);
+        assert!(kasten.resolve(part.zid()).is_err());
+        assert!(!part.path().exists());
+        let other = kasten.resolve(&other).unwrap();
+        assert_eq!(
+            other.borrow().content(),
+            format!("See [[Long#Moves]], [it]({}) and [[Long]].\n", long.zid())
+        );
+    }
+
+    #[test]
+    fn test_split_title() {
+        assert_eq!(split_title("# A #\nb\n"), ("A".to_string(), "b\n"));
+        assert_eq!(
+            split_title("\nfirst\nsecond"),
+            ("first".to_string(), "first\nsecond")
+        );
+        assert_eq!(
+            split_title("#tag line"),
+            ("#tag line".to_string(), "#tag line")
+        );
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let zids: Vec<String> =
+            ["Colour and colours.\n", "Nothing.\n", "colour\n"]
+                .iter()
+                .enumerate()
+                .map(|(i, content)| {
+                    testing::note(dir, &format!("{}.md", i), "z", content)
+                })
+                .collect();
+        let mut kasten = testing::kasten(dir);
+        let finder = Finder::new(&Query {
+            pattern: r"\b(c|C)olour".to_string(),
+            regex: true,
+            match_case: true,
+        })
+        .unwrap();
+        // Unsaved edits, as of a note open in an editor, are left alone.
+        kasten
+            .resolve(&zids[1])
+            .unwrap()
+            .borrow_mut()
+            .set_content("Unsaved colour.\n")
+            .unwrap();
+        let found = kasten.find_all(&finder, "${1}olor").unwrap();
+        assert_eq!(found.len(), 2);
+        let first = found.iter().find(|m| m.zid == zids[0]).unwrap();
+        assert_eq!(first.lines[0].replaced, "Color and colors.");
+
+        let replaced = kasten.replace_all(&finder, "${1}olor");
+        assert!(replaced.error.is_none());
+        let replacement = replaced.replacement;
+        assert_eq!(replacement.edits.len(), 2);
+        let content = |kasten: &Kasten, zid: &str| {
+            kasten.resolve(zid).unwrap().borrow().content().to_string()
+        };
+        assert_eq!(content(&kasten, &zids[0]), "Color and colors.\n");
+        assert_eq!(content(&kasten, &zids[2]), "color\n");
+        let saved = fs::read_to_string(dir.join("1.md")).unwrap();
+        assert!(saved.ends_with("Nothing.\n"));
+
+        let mut z = kasten.resolve(&zids[2]).unwrap().borrow().clone();
+        z.set_content("edited\n").unwrap();
+        kasten.save(&mut z).unwrap();
+        assert_eq!(kasten.undo_replace(&replacement).unwrap(), 1);
+        assert_eq!(content(&kasten, &zids[0]), "Colour and colours.\n");
+        assert_eq!(content(&kasten, &zids[2]), "edited\n");
+
+        // Zettels saved before one failing to save are kept to undo.
+        let mut z = kasten.resolve(&zids[2]).unwrap().borrow().clone();
+        z.set_content("colour\n").unwrap();
+        kasten.save(&mut z).unwrap();
+        fs::create_dir_all(dir.join(".2.md/in-the-way")).unwrap();
+        let replaced = kasten.replace_all(&finder, "${1}olor");
+        assert!(matches!(replaced.error, Some(Error::Io { .. })));
+        let edits = replaced.replacement.edits;
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].zid, zids[0]);
+        assert_eq!(
+            edits[0].after,
+            fs::read_to_string(dir.join("0.md")).unwrap()
+        );
+        assert_eq!(content(&kasten, &zids[0]), "Color and colors.\n");
+    }
+
+    #[test]
+    fn test_anchor_links() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let b_zid =
+            testing::note(dir, "b.md", "Bee", "# Why\n\nBecause ^because\n");
+        let content = format!("See [[bee#why]] and [b]({}^because).\n", b_zid);
+        testing::note(dir, "a.md", "A", &content);
+        let kasten = testing::kasten(dir);
+
+        let target = kasten.resolve_anchor("Bee#Why").unwrap();
+        let z = target.zettel.borrow();
+        assert_eq!(z.zid(), b_zid);
+        let range = target.range.unwrap();
+        assert_eq!(&z.content()[range], "# Why\n\nBecause ^because\n");
+        let link = format!("{}^because", b_zid);
+        let range = kasten.resolve_anchor(&link).unwrap().range.unwrap();
+        assert_eq!(&z.content()[range], "Because ^because");
+        assert!(kasten.resolve_anchor("bee").unwrap().range.is_none());
+        assert!(kasten.resolve_anchor("Bee#Nope").is_err());
+
+        let sources = kasten.iter_backlinks(&z);
+        assert_eq!(sources.len(), 1);
+        let contexts = sources[0].borrow().link_contexts_to(&z);
+        let anchors: Vec<_> = contexts
+            .iter()
+            .map(|c| c.anchor.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(anchors, vec!["#why", "^because"]);
+    }
+
+    #[test]
+    fn test_hash_in_title() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let c = testing::note(
+            dir,
+            "c.md",
+            "C#",
+            "# Intro
+
+Hi.
+",
+        );
+        let f = testing::note(
+            dir, "f.md", "F# notes", "Notes.
+",
+        );
+        let content = "[[C#]], [[C##Intro]] and [[F# notes]].
+";
+        testing::note(dir, "a.md", "A", content);
+        let kasten = testing::kasten(dir);
+
+        assert_eq!(kasten.resolve_link("c#").unwrap().borrow().zid(), c);
+        let target = kasten.resolve_anchor("C##Intro").unwrap();
+        assert_eq!(target.zettel.borrow().zid(), c);
+        assert!(target.range.is_some());
+        let target = kasten.resolve_anchor("F# notes").unwrap();
+        assert_eq!(target.zettel.borrow().zid(), f);
+        assert!(target.range.is_none());
+
+        for zid in [&c, &f] {
+            let z = kasten.resolve(zid).unwrap().borrow().clone();
+            let sources = kasten.iter_backlinks(&z);
+            assert_eq!(sources.len(), 1);
+            let contexts = sources[0].borrow().link_contexts_to(&z);
+            assert!(!contexts.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_attachment_report() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let mut kasten = testing::kasten(dir);
+        let attachments = kasten.attachments();
+        let used = attachments.add(b"used", "png").unwrap();
+        let orphan = attachments.add(b"orphan", "png").unwrap();
+
+        let z = kasten.create("pics").unwrap();
+        let zid = z.borrow().zid().to_string();
+        let mut z = z.borrow().clone();
+        let content = format!(
+            "{}\n![gone](@/attachments/gone.png) [other](pics)\n",
+            attachments.markdown(&used, "used")
+        );
+        z.set_content(&content).unwrap();
+        kasten.save(&mut z).unwrap();
+
+        let report = kasten.attachment_report().unwrap();
+        assert_eq!(report.attachments.len(), 2);
+        let orphaned: Vec<_> = report.orphaned().map(|a| &a.path).collect();
+        assert_eq!(orphaned, vec![&orphan]);
+        let used = report.attachments.iter().find(|a| a.path == used);
+        assert_eq!(used.unwrap().used_by, vec![zid.clone()]);
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing[0].zid, zid);
+        assert_eq!(report.missing[0].link, "@/attachments/gone.png");
+    }
+
+    #[test]
+    fn test_move_zettel() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::write(dir.join("notes/cat.png"), "cat").unwrap();
+        fs::write(dir.join("notes/dog.png"), "dog").unwrap();
+        let a = testing::note(
+            dir,
+            "notes/a.md",
+            "A",
+            "# Top\n![cat](cat.png) ![dog](dog.png) [up](@/notes/a.md#Top)\n",
+        );
+        let b = testing::note(
+            dir,
+            "notes/b.md",
+            "B",
+            "![dog](dog.png) [a](@/notes/a.md) [[A]]\n",
+        );
+        let mut kasten = testing::kasten(dir);
+        let zettel = kasten.resolve(&a).unwrap().borrow().clone();
+
+        assert!(kasten.move_zettel(&zettel, "@/../a.md").is_err());
+        assert!(kasten.move_zettel(&zettel, "@/notes/a.txt").is_err());
+        assert!(kasten.move_zettel(&zettel, &b).is_err());
+        assert!(dir.join("notes/a.md").is_file());
+
+        let relinked = kasten.move_zettel(&zettel, "@/ideas/a.md").unwrap();
+        assert_eq!(relinked, vec![b.clone()]);
+        let moved = kasten.resolve("@/ideas/a.md").unwrap();
+        let moved = moved.borrow();
+        assert_eq!(
+            moved.content(),
+            "# Top\n![cat](cat.png) ![dog](@/notes/dog.png) [up](@/ideas/a.md#Top)\n"
+        );
+        assert!(!dir.join("notes/a.md").exists());
+        assert!(!dir.join("notes/cat.png").exists());
+        assert!(dir.join("ideas/cat.png").is_file());
+        assert!(dir.join("notes/dog.png").is_file());
+        assert!(kasten.resolve(&a).is_err());
+        let b = kasten.resolve(&b).unwrap();
+        assert_eq!(
+            b.borrow().content(),
+            "![dog](dog.png) [a](@/ideas/a.md) [[A]]\n"
+        );
+        assert!(kasten.attachment_report().unwrap().missing.is_empty());
+    }
+
+    #[test]
+    fn test_move_zettel_versioned() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::write(dir.join("notes/cat.png"), "cat").unwrap();
+        let a = testing::note(dir, "notes/a.md", "A", "![cat](cat.png)\n");
+        let config: Config =
+            format!("[repo]\npath = {:?}\ngit = true\n", dir.to_str().unwrap())
+                .parse()
+                .unwrap();
+        let mut kasten = Kasten::new(Rc::new(RefCell::new(config))).unwrap();
+        let zettel = kasten.resolve(&a).unwrap().borrow().clone();
+        kasten.move_zettel(&zettel, "@/ideas/a.md").unwrap();
+
+        let versions = kasten.versions().unwrap().unwrap();
+        assert!(!versions.is_tracked(&dir.join("notes/a.md")));
+        assert!(versions.is_tracked(&dir.join("ideas/a.md")));
+        assert!(versions.is_tracked(&dir.join("ideas/cat.png")));
+    }
+
+    #[test]
+    fn test_tasks() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::write(
+            dir.join("a.md"),
+            "+++\ntitle = \"a\"\ntags = [\"work\"]\n+++\n- [ ] later\n- [ ] soon @2022-01-02\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b.md"),
+            "+++\ntitle = \"b\"\ntags = [\"home\", \"work\"]\n+++\n- [ ] first due:2022-01-01\n",
+        )
+        .unwrap();
+        let kasten = testing::kasten(dir);
+
+        let tasks: Vec<_> =
+            kasten.tasks().into_iter().map(|t| t.text).collect();
+        assert_eq!(
+            tasks,
+            vec!["first due:2022-01-01", "soon @2022-01-02", "later"]
+        );
+        assert_eq!(kasten.tags(), vec!["home", "work"]);
+    }
+
+    #[test]
+    fn test_resolve_file() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::create_dir_all(dir.join("attachments")).unwrap();
+        fs::write(dir.join("notes/near.png"), "").unwrap();
+        fs::write(dir.join("attachments/a b.png"), "").unwrap();
+        let repo = dir.to_str().unwrap();
+        let note = dir.join("notes/n.md");
+
+        let near = resolve_file(repo, &note, "near.png");
+        assert_eq!(near, Some(dir.join("notes/near.png")));
+        let near = resolve_file(repo, &note, "@/notes/near.png");
+        assert_eq!(near, Some(dir.join("notes/near.png")));
+        let attached = resolve_file(repo, &note, "<a%20b.png>");
+        assert_eq!(attached, Some(dir.join("attachments/a b.png")));
+        assert_eq!(resolve_file(repo, &note, "missing.png"), None);
+        assert_eq!(resolve_file(repo, &note, "https://a.b/c.png"), None);
+    }
+}
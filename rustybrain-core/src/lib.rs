@@ -1,8 +1,21 @@
+pub mod attachment;
 pub mod config;
+pub mod error;
+pub mod find;
+pub mod git;
+pub mod highlight;
+pub mod history;
+pub mod journal;
 pub mod kasten;
 pub mod md;
+pub mod merge;
+pub mod snapshot;
+pub mod state;
+pub mod task;
 pub mod zettel;
 
+pub use error::{Error, Result};
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -11,3 +24,36 @@ mod tests {
         assert_eq!(result, 4);
     }
 }
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+    use tempfile::TempDir;
+
+    use crate::config::Config;
+    use crate::kasten::Kasten;
+
+    /// Empty directory of a test's own, removed once dropped.
+    pub fn temp_dir() -> TempDir {
+        tempfile::Builder::new()
+            .prefix("rustybrain-test-")
+            .tempdir()
+            .unwrap()
+    }
+
+    /// Kasten of the repo at `dir`.
+    pub fn kasten(dir: &Path) -> Kasten {
+        let mut config = Config::default();
+        config.set_repo_path(dir.to_str().unwrap());
+        Kasten::new(Rc::new(RefCell::new(config))).unwrap()
+    }
+
+    /// Write a zettel titled `title` to `name` of the repo at `dir`, returns
+    /// its id.
+    pub fn note(dir: &Path, name: &str, title: &str, content: &str) -> String {
+        let header = format!("+++\ntitle = \"{}\"\n+++\n", title);
+        fs::write(dir.join(name), header + content).unwrap();
+        format!("@/{}", name)
+    }
+}
@@ -0,0 +1,247 @@
+/// How a line of a diff is changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Same,
+    Added,
+    Removed,
+}
+
+/// Line of a diff, with its line ending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub kind: Kind,
+    pub text: String,
+}
+
+/// Result of a three-way merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merged {
+    /// Merged text, conflicting blocks are kept with both sides between
+    /// `<<<<<<<`, `=======` and `>>>>>>>` markers.
+    pub text: String,
+    pub conflicts: usize,
+}
+
+fn lines(s: &str) -> Vec<&str> {
+    s.split_inclusive('\n').collect()
+}
+
+/// For each line of `a`, the line of `b` it is matched to by the longest
+/// common subsequence. Found by halving `a` (Hirschberg), so memory is
+/// linear in the lines.
+fn matches(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; a.len()];
+    match_into(a, b, 0, 0, &mut matched);
+    matched
+}
+
+/// Match lines of `a` to `b`, which start at `ao` and `bo` of the whole
+/// texts, into `out`.
+fn match_into(
+    a: &[&str],
+    b: &[&str],
+    ao: usize,
+    bo: usize,
+    out: &mut [Option<usize>],
+) {
+    // Lines alike at both ends are matched as they are.
+    let pre = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    for i in 0..pre {
+        out[ao + i] = Some(bo + i);
+    }
+    let (a, b, ao, bo) = (&a[pre..], &b[pre..], ao + pre, bo + pre);
+    let suf = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (n, m) = (a.len() - suf, b.len() - suf);
+    for i in 0..suf {
+        out[ao + n + i] = Some(bo + m + i);
+    }
+    let (a, b) = (&a[..n], &b[..m]);
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    if a.len() == 1 {
+        out[ao] = b.iter().position(|l| *l == a[0]).map(|j| bo + j);
+        return;
+    }
+    // Split `b` where the halves of `a` share the most lines with it.
+    let mid = a.len() / 2;
+    let head = lcs_lengths(a[..mid].iter(), b.iter());
+    let tail = lcs_lengths(a[mid..].iter().rev(), b.iter().rev());
+    let k = (0..=m)
+        .max_by_key(|&j| (head[j] + tail[m - j], usize::MAX - j))
+        .unwrap_or(0);
+    match_into(&a[..mid], &b[..k], ao, bo, out);
+    match_into(&a[mid..], &b[k..], ao + mid, bo + k, out);
+}
+
+/// Length of the longest common subsequence of `a` and each prefix of `b`,
+/// by the length of the prefix.
+fn lcs_lengths<'a, 'b>(
+    a: impl Iterator<Item = &'a &'b str>,
+    b: impl Iterator<Item = &'a &'b str> + Clone,
+) -> Vec<usize>
+where
+    'b: 'a,
+{
+    let m = b.clone().count();
+    let mut prev = vec![0; m + 1];
+    let mut cur = vec![0; m + 1];
+    for x in a {
+        for (j, y) in b.clone().enumerate() {
+            cur[j + 1] = if x == y {
+                prev[j] + 1
+            } else {
+                prev[j + 1].max(cur[j])
+            };
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev
+}
+
+/// Line diff from `old` to `new`, removed lines come before the added ones
+/// replacing them.
+pub fn diff(old: &str, new: &str) -> Vec<Line> {
+    let (a, b) = (lines(old), lines(new));
+    let line = |kind, text: &str| Line {
+        kind,
+        text: text.to_string(),
+    };
+    let mut out = vec![];
+    let mut j = 0;
+    for (i, m) in matches(&a, &b).into_iter().enumerate() {
+        match m {
+            Some(m) => {
+                out.extend(b[j..m].iter().map(|t| line(Kind::Added, t)));
+                out.push(line(Kind::Same, a[i]));
+                j = m + 1;
+            }
+            None => out.push(line(Kind::Removed, a[i])),
+        }
+    }
+    out.extend(b[j..].iter().map(|t| line(Kind::Added, t)));
+    out
+}
+
+/// Merge changes of `ours` and `theirs`, both made from `base`.
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> Merged {
+    let (base, ours, theirs) = (lines(base), lines(ours), lines(theirs));
+    let (mo, mt) = (matches(&base, &ours), matches(&base, &theirs));
+    let mut text = String::new();
+    let mut conflicts = 0;
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // Next base line kept by both sides.
+        let stable = (i..base.len()).find_map(|b| Some((b, mo[b]?, mt[b]?)));
+        let (bi, oi, ti) =
+            stable.unwrap_or((base.len(), ours.len(), theirs.len()));
+        let (b, o, t) = (&base[i..bi], &ours[j..oi], &theirs[k..ti]);
+        if o == b || o == t {
+            text.extend(t.iter().copied());
+        } else if t == b {
+            text.extend(o.iter().copied());
+        } else {
+            conflicts += 1;
+            text.push_str("<<<<<<< mine\n");
+            push_block(&mut text, o);
+            text.push_str("=======\n");
+            push_block(&mut text, t);
+            text.push_str(">>>>>>> disk\n");
+        }
+        match stable {
+            Some(_) => {
+                text.push_str(base[bi]);
+                i = bi + 1;
+                j = oi + 1;
+                k = ti + 1;
+            }
+            None => break,
+        }
+    }
+    Merged { text, conflicts }
+}
+
+/// Append lines, ending the last one if it's not, to keep markers on their
+/// own lines.
+fn push_block(text: &mut String, block: &[&str]) {
+    text.extend(block.iter().copied());
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, lines, merge3, Kind};
+
+    #[test]
+    fn test_diff_long() {
+        let old: String = (0..500).map(|i| format!("{}\n", i % 7)).collect();
+        let new: String = (0..400).map(|i| format!("{}\n", i % 5)).collect();
+        let d = diff(&old, &new);
+        let side = |kind| -> String {
+            d.iter()
+                .filter(|l| l.kind == Kind::Same || l.kind == kind)
+                .map(|l| l.text.as_str())
+                .collect()
+        };
+        assert_eq!(side(Kind::Removed), old);
+        assert_eq!(side(Kind::Added), new);
+        // As long as the longest common subsequence.
+        let (a, b): (Vec<&str>, Vec<&str>) = (lines(&old), lines(&new));
+        let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                lcs[i + 1][j + 1] = if x == y {
+                    lcs[i][j] + 1
+                } else {
+                    lcs[i][j + 1].max(lcs[i + 1][j])
+                };
+            }
+        }
+        let same = d.iter().filter(|l| l.kind == Kind::Same).count();
+        assert_eq!(same, lcs[a.len()][b.len()]);
+    }
+
+    #[test]
+    fn test_diff() {
+        let d = diff("a\nb\nc\n", "a\nx\nc\nd\n");
+        let kinds: Vec<Kind> = d.iter().map(|l| l.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Kind::Same,
+                Kind::Removed,
+                Kind::Added,
+                Kind::Same,
+                Kind::Added
+            ]
+        );
+        assert_eq!(d[1].text, "b\n");
+        assert_eq!(d[2].text, "x\n");
+    }
+
+    #[test]
+    fn test_merge3() {
+        let base = "a\nb\nc\nd\n";
+        let m = merge3(base, "a\nB\nc\nd\n", "a\nb\nc\nD\n");
+        assert_eq!(m.conflicts, 0);
+        assert_eq!(m.text, "a\nB\nc\nD\n");
+
+        let m = merge3(base, "a\nmine\nc\nd\n", "a\ndisk\nc\nd\n");
+        assert_eq!(m.conflicts, 1);
+        assert_eq!(
+            m.text,
+            "a\n<<<<<<< mine\nmine\n=======\ndisk\n>>>>>>> disk\nc\nd\n"
+        );
+
+        let m = merge3(base, "a\nb\nc\nd\ne", "a\nb\nc\nd\ne");
+        assert_eq!(m.conflicts, 0);
+        assert_eq!(m.text, "a\nb\nc\nd\ne");
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::error::{Error, Result};
+use crate::journal::file_name;
+use crate::state::STATE_DIR;
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const MONTH: i64 = 30 * DAY;
+
+/// Compression level of zstd, snapshots are small and written often.
+const LEVEL: i32 = 3;
+
+/// Saved version of a zettel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch.
+    pub secs: i64,
+    path: PathBuf,
+}
+
+/// Compressed snapshots of zettels taken on save, kept in
+/// `.rustybrain/history/` of a repo. The last one of each minute is kept
+/// for an hour, as autosave takes many, then of each hour for a day, and
+/// of each day for a month.
+pub struct Snapshots {
+    dir: PathBuf,
+}
+
+impl Snapshot {
+    pub fn time(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.secs, 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+}
+
+impl Snapshots {
+    pub fn new(repo_path: &str) -> Self {
+        Snapshots {
+            dir: Path::new(repo_path).join(STATE_DIR).join("history"),
+        }
+    }
+
+    /// Keep `text` as a version of zettel `zid` at `secs`, unless it's the
+    /// same as the latest one, and drop the versions out of retention.
+    pub fn take(&self, zid: &str, text: &str, secs: i64) -> Result<()> {
+        let snapshots = self.list(zid)?;
+        if let Some(latest) = snapshots.first() {
+            if self.read(latest)? == text {
+                return Ok(());
+            }
+        }
+        let dir = self.dir_of(zid);
+        fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        let path = dir.join(format!("{}.zst", secs));
+        let data = zstd::encode_all(text.as_bytes(), LEVEL)
+            .map_err(|e| Error::io(&path, e))?;
+        let tmp = path.with_extension("zst.tmp");
+        fs::write(&tmp, data).map_err(|e| Error::io(&tmp, e))?;
+        fs::rename(&tmp, &path).map_err(|e| Error::io(&path, e))?;
+        self.prune(zid, secs)
+    }
+
+    /// Versions of zettel `zid`, the newest first.
+    pub fn list(&self, zid: &str) -> Result<Vec<Snapshot>> {
+        let dir = self.dir_of(zid);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Error::io(&dir, e)),
+        };
+        let mut snapshots = vec![];
+        for entry in entries {
+            let path = entry.map_err(|e| Error::io(&dir, e))?.path();
+            if path.extension().map_or(true, |ext| ext != "zst") {
+                continue;
+            }
+            let secs = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse().ok());
+            if let Some(secs) = secs {
+                snapshots.push(Snapshot { secs, path });
+            }
+        }
+        snapshots.sort_by(|a, b| b.secs.cmp(&a.secs));
+        Ok(snapshots)
+    }
+
+    /// Keep the versions of zettel `from` as those of `to`, once it's moved,
+    /// along with any left of a zettel of id `to` before.
+    pub fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let snapshots = self.list(from)?;
+        if snapshots.is_empty() {
+            return Ok(());
+        }
+        let dir = self.dir_of(to);
+        fs::create_dir_all(&dir).map_err(|e| Error::io(&dir, e))?;
+        for s in snapshots {
+            let path = dir.join(s.path.file_name().unwrap_or_default());
+            fs::rename(&s.path, &path).map_err(|e| Error::io(&path, e))?;
+        }
+        let from = self.dir_of(from);
+        fs::remove_dir_all(&from).map_err(|e| Error::io(&from, e))
+    }
+
+    /// Directory of the versions of zettel `zid`, named by a hash of the
+    /// id, as zettels of ids alike must not share one.
+    fn dir_of(&self, zid: &str) -> PathBuf {
+        self.dir.join(file_name(zid))
+    }
+
+    pub fn read(&self, snapshot: &Snapshot) -> Result<String> {
+        let path = &snapshot.path;
+        let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+        let text = zstd::decode_all(data.as_slice())
+            .map_err(|e| Error::io(path, e))?;
+        String::from_utf8(text).map_err(|e| {
+            Error::io(path, io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    fn prune(&self, zid: &str, now: i64) -> Result<()> {
+        let snapshots = self.list(zid)?;
+        let secs: Vec<i64> = snapshots.iter().map(|s| s.secs).collect();
+        let kept = retained(&secs, now);
+        for (s, keep) in snapshots.iter().zip(kept) {
+            if !keep {
+                fs::remove_file(&s.path).map_err(|e| Error::io(&s.path, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which of `secs`, the newest first, are kept at `now`.
+fn retained(secs: &[i64], now: i64) -> Vec<bool> {
+    let mut last = None;
+    secs.iter()
+        .map(|&t| {
+            let age = now - t;
+            let slot = if age < HOUR {
+                (0, t / MINUTE)
+            } else if age < DAY {
+                (1, t / HOUR)
+            } else if age < MONTH {
+                (2, t / DAY)
+            } else {
+                return false;
+            };
+            // The newest of a slot is kept.
+            let keep = last != Some(slot);
+            last = Some(slot);
+            keep
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{retained, Snapshots, DAY, HOUR, MONTH};
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_retained() {
+        let now = 100 * MONTH;
+        let secs = [
+            now - 10,
+            now - 20,
+            now - 2 * HOUR - 10,
+            now - 2 * HOUR - 20,
+            now - 4 * HOUR,
+            now - 2 * DAY - 10,
+            now - 2 * DAY - 20,
+            now - 2 * MONTH,
+        ];
+        assert_eq!(
+            retained(&secs, now),
+            vec![true, false, true, false, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn test_take_and_read() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let snapshots = Snapshots::new(dir.to_str().unwrap());
+        let zid = "@/notes/a.md";
+        assert!(snapshots.list(zid).unwrap().is_empty());
+
+        let now = 100 * MONTH;
+        snapshots.take(zid, "one", now - 2 * MONTH).unwrap();
+        snapshots.take(zid, "two", now - 10).unwrap();
+        snapshots.take(zid, "two", now - 5).unwrap();
+        snapshots.take(zid, "three", now).unwrap();
+
+        let list = snapshots.list(zid).unwrap();
+        let secs: Vec<i64> = list.iter().map(|s| s.secs).collect();
+        assert_eq!(secs, vec![now, now - 10]);
+        assert_eq!(snapshots.read(&list[0]).unwrap(), "three");
+        assert_eq!(snapshots.read(&list[1]).unwrap(), "two");
+
+        // Taken in the same minute, the newer replaces it.
+        snapshots.take(zid, "four", now + 5).unwrap();
+        let list = snapshots.list(zid).unwrap();
+        let secs: Vec<i64> = list.iter().map(|s| s.secs).collect();
+        assert_eq!(secs, vec![now + 5, now - 10]);
+    }
+
+    #[test]
+    fn test_zids_alike() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let snapshots = Snapshots::new(dir.to_str().unwrap());
+        snapshots.take("@/a-b.md", "dash", 10).unwrap();
+        snapshots.take("@/a_b.md", "underscore", 10).unwrap();
+
+        let list = snapshots.list("@/a-b.md").unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(snapshots.read(&list[0]).unwrap(), "dash");
+        assert_eq!(snapshots.list("@/a_b.md").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Directory inside a repo for the app's own files.
+pub const STATE_DIR: &str = ".rustybrain";
+
+/// How many recently opened zettels are remembered.
+const RECENT_MAX: usize = 20;
+
+/// What the app remembers about a repo between sessions, kept in
+/// `.rustybrain/state.toml` of the repo.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct State {
+    show_list: bool,
+    show_back: bool,
+    show_versions: bool,
+    show_outline: bool,
+
+    /// Ids of recently opened zettels, the last opened first.
+    recent: Vec<String>,
+
+    /// Ids of zettels in tabs, by panes from left to right.
+    layout: Vec<Vec<String>>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            show_list: false,
+            show_back: true,
+            show_versions: false,
+            show_outline: false,
+            recent: vec![],
+            layout: vec![],
+        }
+    }
+}
+
+impl State {
+    pub fn load(repo_path: &str) -> Result<Self> {
+        let path = Self::path(repo_path);
+        let s = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(State::default())
+            }
+            Err(err) => return Err(Error::io(&path, err)),
+        };
+        toml::from_str(&s).map_err(|e| {
+            Error::io(&path, io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    pub fn save(&self, repo_path: &str) -> Result<()> {
+        let path = Self::path(repo_path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        let s = toml::to_string(self).map_err(|e| {
+            Error::io(&path, io::Error::new(io::ErrorKind::InvalidData, e))
+        })?;
+        fs::write(&path, s).map_err(|e| Error::io(&path, e))
+    }
+
+    fn path(repo_path: &str) -> PathBuf {
+        Path::new(repo_path).join(STATE_DIR).join("state.toml")
+    }
+
+    pub fn show_list(&self) -> bool {
+        self.show_list
+    }
+
+    pub fn show_back(&self) -> bool {
+        self.show_back
+    }
+
+    pub fn show_versions(&self) -> bool {
+        self.show_versions
+    }
+
+    pub fn show_outline(&self) -> bool {
+        self.show_outline
+    }
+
+    pub fn set_show_list(&mut self, show: bool) {
+        self.show_list = show;
+    }
+
+    pub fn set_show_back(&mut self, show: bool) {
+        self.show_back = show;
+    }
+
+    pub fn set_show_versions(&mut self, show: bool) {
+        self.show_versions = show;
+    }
+
+    pub fn set_show_outline(&mut self, show: bool) {
+        self.show_outline = show;
+    }
+
+    /// Zettel opened last time.
+    pub fn last(&self) -> Option<&str> {
+        self.recent.first().map(|s| s.as_str())
+    }
+
+    pub fn recent(&self) -> &[String] {
+        &self.recent
+    }
+
+    /// Remember zettel `zid` is opened.
+    pub fn visit(&mut self, zid: &str) {
+        self.recent.retain(|z| z != zid);
+        self.recent.insert(0, zid.to_string());
+        self.recent.truncate(RECENT_MAX);
+    }
+
+    pub fn layout(&self) -> &[Vec<String>] {
+        &self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Vec<Vec<String>>) {
+        self.layout = layout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::State;
+
+    #[test]
+    fn test_visit() {
+        let mut state = State::default();
+        assert!(state.last().is_none());
+        state.visit("@/a.md");
+        state.visit("@/b.md");
+        state.visit("@/a.md");
+        assert_eq!(state.last(), Some("@/a.md"));
+        assert_eq!(state.recent(), ["@/a.md", "@/b.md"]);
+
+        let s = toml::to_string(&state).unwrap();
+        let loaded: State = toml::from_str(&s).unwrap();
+        assert_eq!(loaded.recent(), state.recent());
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut state = State::default();
+        assert!(state.layout().is_empty());
+        state.set_layout(vec![
+            vec!["@/a.md".to_string(), "@/b.md".to_string()],
+            vec!["@/c.md".to_string()],
+        ]);
+
+        let s = toml::to_string(&state).unwrap();
+        let loaded: State = toml::from_str(&s).unwrap();
+        assert_eq!(loaded.layout(), state.layout());
+        assert_eq!(loaded.layout()[1], ["@/c.md"]);
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::NaiveDate;
+
+use crate::zettel::Zettel;
+
+/// Task list item of a zettel, like `- [ ] Call Bob due:2022-03-01`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub zid: String,
+    /// Title of the zettel.
+    pub title: String,
+    /// Line of the checkbox in the content of the zettel, from 0.
+    pub line: usize,
+    /// Text after the checkbox, trimmed.
+    pub text: String,
+    pub done: bool,
+    /// Date of `due:2022-03-01` or `@2022-03-01` in the text.
+    pub due: Option<NaiveDate>,
+    /// Tags of the zettel.
+    pub tags: Vec<String>,
+}
+
+impl Task {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Whether the task is due on or before `date`.
+    pub fn due_by(&self, date: NaiveDate) -> bool {
+        self.due.map_or(false, |due| due <= date)
+    }
+}
+
+/// Tasks of `zettel`, in the order they appear.
+pub fn tasks(zettel: &Zettel) -> Vec<Task> {
+    let content = zettel.content();
+    let mut tasks: Vec<Task> = zettel
+        .walk_iter()
+        .filter(|n| n.kind() == "task_list_item_marker")
+        .map(|marker| {
+            let done = content[marker.byte_range()].contains(['x', 'X']);
+            let end = marker
+                .parent()
+                .map_or(marker.end_byte(), |p| p.end_byte())
+                .max(marker.end_byte());
+            let text = content[marker.end_byte()..end].trim().to_string();
+            Task {
+                zid: zettel.zid().to_string(),
+                title: zettel.title().to_string(),
+                line: marker.start_position().row,
+                due: parse_due(&text),
+                text,
+                done,
+                tags: zettel.tags().to_vec(),
+            }
+        })
+        .collect();
+    tasks.sort_by_key(|t| t.line);
+    tasks
+}
+
+/// Date of the first `due:` or `@` word of `text` that is a date.
+fn parse_due(text: &str) -> Option<NaiveDate> {
+    text.split_whitespace().find_map(|word| {
+        let date = word
+            .strip_prefix("due:")
+            .or_else(|| word.strip_prefix('@'))?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    })
+}
+
+/// Checkbox `marker` ticked, or unticked if it is already.
+pub fn toggle_marker(marker: &str) -> String {
+    if marker.contains(['x', 'X']) {
+        "[ ]".to_string()
+    } else {
+        "[x]".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{parse_due, tasks, toggle_marker};
+    use crate::testing::temp_dir;
+    use crate::zettel::Zettel;
+
+    #[test]
+    fn test_tasks() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("t.md");
+        std::fs::write(
+            &path,
+            "+++\ntitle = \"t\"\ntags = [\"work\"]\n+++\n# Todo\n\n- [ ] Call Bob due:2022-03-01\n- [x] Mail @2022-02-01\n- plain\n",
+        )
+        .unwrap();
+        let z = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        let tasks = tasks(&z);
+        assert_eq!(tasks.len(), 2);
+
+        let t = &tasks[0];
+        assert_eq!(t.line, 2);
+        assert_eq!(t.text, "Call Bob due:2022-03-01");
+        assert!(!t.done);
+        assert_eq!(t.due, NaiveDate::from_ymd_opt(2022, 3, 1));
+        assert!(t.has_tag("Work"));
+        assert!(t.due_by(NaiveDate::from_ymd_opt(2022, 3, 1).unwrap()));
+        assert!(!t.due_by(NaiveDate::from_ymd_opt(2022, 2, 28).unwrap()));
+
+        assert_eq!(tasks[1].line, 3);
+        assert!(tasks[1].done);
+        assert_eq!(tasks[1].due, NaiveDate::from_ymd_opt(2022, 2, 1));
+    }
+
+    #[test]
+    fn test_parse_due() {
+        assert_eq!(parse_due("no date"), None);
+        assert_eq!(parse_due("email@example.com due:soon"), None);
+        assert_eq!(
+            parse_due("x @2022-1-5 due:2022-02-03"),
+            NaiveDate::from_ymd_opt(2022, 1, 5)
+        );
+        assert_eq!(toggle_marker("[ ]"), "[x]");
+        assert_eq!(toggle_marker("[X]"), "[ ]");
+    }
+}
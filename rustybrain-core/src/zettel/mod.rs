@@ -1,11 +1,17 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
 use std::fs;
 use std::fs::rename;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io;
 use std::io::prelude::*;
 use std::io::Cursor;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::slice::Iter;
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use chrono::Local;
 use serde::{Deserialize, Serialize};
@@ -14,6 +20,8 @@ use tree_sitter::Node;
 use tree_sitter::Tree;
 use tree_sitter::TreeCursor;
 
+use crate::error::{Error, Result};
+
 #[derive(Debug, Clone)]
 pub struct Zettel {
     id: String,
@@ -23,15 +31,152 @@ pub struct Zettel {
 
     tree: Option<Tree>,
 
+    /// File as it was loaded or saved last, to catch changes by others.
+    disk: Disk,
+
     #[allow(dead_code)]
     link_to: Vec<String>,
 }
 
+/// File as it was loaded or saved last, only what tells whether it's
+/// changed, the text is read back from the kept versions on conflict.
+#[derive(Debug, Clone, Default)]
+struct Disk {
+    mtime: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Disk {
+    fn new(path: &Path, text: &str) -> Self {
+        let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+        Disk {
+            mtime,
+            len: text.len() as u64,
+            hash: hash(text),
+        }
+    }
+}
+
+fn hash(text: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    text.hash(&mut h);
+    h.finish()
+}
+
+/// Block around a link, to show why a zettel links to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkContext {
+    pub text: String,
+    /// Byte range of the link inside `text`.
+    pub link: Range<usize>,
+    /// Section or block cited by the link.
+    pub anchor: Option<Anchor>,
+}
+
+/// Part of a zettel a link points to, written after `#` or `^` of the link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    /// Section of the heading with the text.
+    Heading(String),
+    /// Block ending with `^id`, by the id.
+    Block(String),
+}
+
+impl Anchor {
+    /// Split a link like `Note#Heading` or `Note^id` into the zettel it
+    /// points to and the anchor, at the last `#`.
+    pub fn split(link: &str) -> (&str, Option<Anchor>) {
+        if let Some((note, heading)) = link.rsplit_once('#') {
+            let heading = heading.trim();
+            if !heading.is_empty() {
+                return (note, Some(Anchor::Heading(heading.to_string())));
+            }
+            return (note, None);
+        }
+        if let Some((note, id)) = link.rsplit_once('^') {
+            if is_block_id(id) {
+                return (note, Some(Anchor::Block(id.to_string())));
+            }
+        }
+        (link, None)
+    }
+
+    /// Split `link` as `split` does if `is_note` holds for its zettel part,
+    /// or else take the whole link as the zettel, for titles like `C#`.
+    /// None if neither is one.
+    pub fn split_by<F>(link: &str, is_note: F) -> Option<(&str, Option<Anchor>)>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let (note, anchor) = Self::split(link);
+        if is_note(note) {
+            Some((note, anchor))
+        } else if note != link && is_note(link) {
+            Some((link, None))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anchor::Heading(heading) => write!(f, "#{}", heading),
+            Anchor::Block(id) => write!(f, "^{}", id),
+        }
+    }
+}
+
+fn is_block_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Link of a zettel, by where it is and what it points to.
+struct LinkRef {
+    /// Byte range of the link in the content.
+    range: Range<usize>,
+    /// Destination, or the text of a wiki link like `[[Note#Heading]]`.
+    target: String,
+    /// Byte range of `target` in the content.
+    target_range: Range<usize>,
+    /// Whether it's a wiki link, pointing to a zettel by its title.
+    wiki: bool,
+}
+
+/// Heading of a zettel, with the section it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// 1 for `#`, up to 6.
+    pub level: usize,
+    /// Text of the heading without its markers.
+    pub text: String,
+    /// Line of the heading in the content, from 0.
+    pub line: usize,
+    /// Byte range of the heading in the content.
+    pub range: Range<usize>,
+    /// Byte range of the heading and what follows, up to the next heading
+    /// of the same or a higher level.
+    pub section: Range<usize>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ZettelHeader {
     title: String,
     date: Option<Datetime>,
 
+    /// Other names the zettel is mentioned by.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+
+    /// Topics to filter zettels and their tasks by.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
     #[allow(dead_code)]
     #[serde(skip)]
     raw: String,
@@ -43,19 +188,21 @@ impl ZettelHeader {
         Self {
             title: title.to_string(),
             date: Some(Datetime::from_str(&today).unwrap()),
+            aliases: vec![],
+            tags: vec![],
             raw: "".to_string(),
         }
     }
 
-    pub fn from_cursor(
-        cursor: &mut Cursor<Vec<u8>>,
-    ) -> Result<Self, anyhow::Error> {
-        let raw = Self::read(cursor)?;
-        let s = toml::from_str(&raw)?;
-        Ok(s)
+    /// Parse the header, the error position is counted from the first line
+    /// of the file, which is the opening `+++`.
+    pub fn from_cursor(cursor: &mut Cursor<Vec<u8>>) -> Result<Self> {
+        let raw =
+            Self::read(cursor).map_err(|e| Error::io(Path::new(""), e))?;
+        toml::from_str(&raw).map_err(|e| Error::header(&raw, 1, &e))
     }
 
-    fn read(cursor: &mut Cursor<Vec<u8>>) -> Result<String, anyhow::Error> {
+    fn read(cursor: &mut Cursor<Vec<u8>>) -> io::Result<String> {
         let mut line_buf: String = String::new();
         let mut header: String = String::new();
         cursor.read_line(&mut line_buf)?;
@@ -67,7 +214,7 @@ impl ZettelHeader {
                 if line_buf.trim_start_matches('+').trim().is_empty() {
                     return Ok(header);
                 }
-                std::fmt::Write::write_str(&mut header, &line_buf)?;
+                header.push_str(&line_buf);
             }
         }
         Ok(header)
@@ -75,17 +222,10 @@ impl ZettelHeader {
 }
 
 impl Zettel {
-    pub fn from_md(
-        repo_path: &str,
-        path: &Path,
-    ) -> Result<Self, anyhow::Error> {
-        let mut file = File::open(path)?;
-        let mut buf = vec![];
-        file.read_to_end(&mut buf)?;
-        let mut cursor = Cursor::new(buf);
-        let header = ZettelHeader::from_cursor(&mut cursor)?;
-        let mut content: String = String::new();
-        cursor.read_to_string(&mut content)?;
+    pub fn from_md(repo_path: &str, path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let disk = Disk::new(path, &text);
+        let (header, content) = Self::parse(&text).map_err(|e| e.at(path))?;
         let id = Self::in_repo_path(path, repo_path)?;
         let tree = crate::md::parse(&content, None)?;
         let mut z = Zettel {
@@ -94,54 +234,143 @@ impl Zettel {
             header,
             content,
             tree,
+            disk,
             link_to: vec![],
         };
         z.parse_links_to();
         Ok(z)
     }
 
-    pub fn create(
-        repo_path: &str,
-        path: &Path,
-        title: &str,
-    ) -> Result<Self, anyhow::Error> {
+    /// Split the text of a file into the header and the content.
+    fn parse(text: &str) -> Result<(ZettelHeader, String)> {
+        let mut cursor = Cursor::new(text.as_bytes().to_vec());
+        let header = ZettelHeader::from_cursor(&mut cursor)?;
+        let mut content: String = String::new();
+        cursor
+            .read_to_string(&mut content)
+            .map_err(|e| Error::io(Path::new(""), e))?;
+        Ok((header, content))
+    }
+
+    pub fn create(repo_path: &str, path: &Path, title: &str) -> Result<Self> {
         Self::create_and_insert(path, title)?;
         Self::from_md(repo_path, path)
     }
 
-    fn create_and_insert(
-        path: &Path,
-        title: &str,
-    ) -> Result<(), anyhow::Error> {
-        let mut file = File::create(path)?;
+    /// Write the header of a new zettel, refused if the file exists.
+    fn create_and_insert(path: &Path, title: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| Error::io(path, e))?;
         let header = ZettelHeader::new(title);
-        Self::write_header(&mut file, &header)?;
+        Self::write_header(&mut file, &header)
+            .map_err(|e| Error::io(path, e))?;
         Ok(())
     }
 
-    pub fn save(&self) -> Result<(), anyhow::Error> {
+    /// Write the zettel, refused with `Error::Conflict` if the file is
+    /// changed by others since it was loaded.
+    pub fn save(&mut self) -> Result<()> {
+        if self.changed_on_disk()? {
+            return Err(Error::Conflict {
+                path: self.path.clone(),
+            });
+        }
+        let text = self.text()?;
         let tp = self.tmp();
+        let io_err = |e| Error::io(&tp, e);
         if tp.exists() {
-            fs::remove_file(&tp)?;
+            fs::remove_file(&tp).map_err(io_err)?;
         }
-        let mut tmp = File::create(&tp)?;
-        Self::write_header(&mut tmp, &self.header)?;
-        tmp.write_all(self.content.as_bytes())?;
-        rename(&tp, self.path())?;
+        let mut tmp = File::create(&tp).map_err(io_err)?;
+        tmp.write_all(text.as_bytes()).map_err(io_err)?;
+        rename(&tp, self.path()).map_err(|e| Error::io(self.path(), e))?;
+        self.disk = Disk::new(&self.path, &text);
         Ok(())
     }
 
-    fn write_header(
-        file: &mut File,
-        header: &ZettelHeader,
-    ) -> Result<(), anyhow::Error> {
-        let hs = toml::to_string(&header)?;
+    /// Whether the file is not the one loaded or saved last. A removed file
+    /// is not a change, saving brings it back.
+    pub fn changed_on_disk(&self) -> Result<bool> {
+        let meta = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(Error::io(&self.path, e)),
+        };
+        if meta.modified().ok() == self.disk.mtime
+            && meta.len() == self.disk.len
+        {
+            return Ok(false);
+        }
+        Ok(hash(&self.disk_text()?) != self.disk.hash)
+    }
+
+    /// Whether `text` is the file as it was loaded or saved last.
+    pub fn is_base(&self, text: &str) -> bool {
+        hash(text) == self.disk.hash
+    }
+
+    /// Text of the file now.
+    pub fn disk_text(&self) -> Result<String> {
+        fs::read_to_string(&self.path).map_err(|e| Error::io(&self.path, e))
+    }
+
+    /// Take the file now as the loaded one, e.g. after merging its changes.
+    pub fn rebase(&mut self) -> Result<()> {
+        self.disk = Disk::new(&self.path, &self.disk_text()?);
+        Ok(())
+    }
+
+    /// Text to be saved, the header and the content.
+    pub fn text(&self) -> Result<String> {
+        let hs = toml::to_string(&self.header).map_err(|e| {
+            Error::io(&self.path, io::Error::new(io::ErrorKind::InvalidData, e))
+        })?;
+        Ok(format!("+++\n{}+++\n{}", hs, self.content))
+    }
+
+    /// Replace the header and the content with those of `text`.
+    pub fn set_text(&mut self, text: &str) -> Result<()> {
+        let (header, content) =
+            Self::parse(text).map_err(|e| e.at(&self.path))?;
+        self.header = header;
+        self.set_content(&content)
+    }
+
+    fn write_header(file: &mut File, header: &ZettelHeader) -> io::Result<()> {
+        let hs = toml::to_string(&header)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
         file.write_all(b"+++\n")?;
         file.write_all(hs.as_bytes())?;
         file.write_all(b"+++\n")?;
         Ok(())
     }
 
+    /// Move the file of the zettel to `path` of repo `repo_path`, its id
+    /// follows. Refused with `Error::Conflict` if the file is changed by
+    /// others since it was loaded.
+    pub fn move_to(&mut self, repo_path: &str, path: &Path) -> Result<()> {
+        if self.changed_on_disk()? {
+            return Err(Error::Conflict {
+                path: self.path.clone(),
+            });
+        }
+        let id = Self::in_repo_path(path, repo_path)?;
+        if path.exists() {
+            let e = io::Error::new(io::ErrorKind::AlreadyExists, "is taken");
+            return Err(Error::io(path, e));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        rename(&self.path, path).map_err(|e| Error::io(path, e))?;
+        self.id = id;
+        self.path = path.to_path_buf();
+        Ok(())
+    }
+
     fn tmp(&self) -> PathBuf {
         let dir = self.path.parent().unwrap();
         let f = self.path.file_name().unwrap().to_str().unwrap();
@@ -187,21 +416,37 @@ impl Zettel {
         self.path.as_path()
     }
 
-    fn in_repo_path(
-        path: &Path,
-        repo_path: &str,
-    ) -> Result<String, anyhow::Error> {
-        let p = path.strip_prefix(repo_path)?;
+    fn in_repo_path(path: &Path, repo_path: &str) -> Result<String> {
+        let invalid = |msg: &str| {
+            let e = io::Error::new(io::ErrorKind::InvalidInput, msg);
+            Error::io(path, e)
+        };
+        let p = path
+            .strip_prefix(repo_path)
+            .map_err(|_| invalid("not inside the repo"))?;
         if let Some(p) = p.to_str() {
             return Ok(format!("@/{}", p));
         }
-        Err(anyhow::anyhow!("empty path"))
+        Err(invalid("path is not valid unicode"))
     }
 
     pub fn title(&self) -> &str {
         &self.header.title
     }
 
+    pub fn aliases(&self) -> &[String] {
+        &self.header.aliases
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.header.tags
+    }
+
+    /// Creation date of the header, like `2022-01-31`.
+    pub fn date(&self) -> Option<String> {
+        self.header.date.as_ref().map(|d| d.to_string())
+    }
+
     pub fn content(&self) -> &str {
         &self.content
     }
@@ -210,7 +455,16 @@ impl Zettel {
         self.header.title = title.to_string();
     }
 
-    pub fn set_content(&mut self, content: &str) -> Result<(), anyhow::Error> {
+    /// Add the tags the zettel doesn't have yet.
+    pub fn add_tags(&mut self, tags: &[String]) {
+        for tag in tags {
+            if !self.header.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                self.header.tags.push(tag.clone());
+            }
+        }
+    }
+
+    pub fn set_content(&mut self, content: &str) -> Result<()> {
         self.tree = crate::md::parse(content, None)?;
         self.content = content.to_string();
         self.parse_links_to();
@@ -218,25 +472,328 @@ impl Zettel {
     }
 
     fn parse_links_to(&mut self) {
-        let mut link_to: Vec<String> = vec![];
-        for node in self.walk_iter() {
-            if node.kind() == "text" {
-                if let Some(n) = node.parent() {
-                    if n.kind() == "link_destination" {
-                        let range = node.byte_range();
-                        let link_text = &self.content.as_bytes()[range];
-                        link_to.push(
-                            String::from_utf8_lossy(link_text).to_string(),
-                        );
+        self.link_to = self.link_refs().into_iter().map(|l| l.target).collect();
+    }
+
+    /// Links and images of the content, with a destination, and wiki links,
+    /// in the order they appear.
+    fn link_refs(&self) -> Vec<LinkRef> {
+        let bytes = self.content.as_bytes();
+        let mut links: Vec<LinkRef> = self
+            .walk_iter()
+            .filter(|n| matches!(n.kind(), "link" | "image"))
+            .filter_map(|node| {
+                let mut cursor = node.walk();
+                let dest = node
+                    .children(&mut cursor)
+                    .find(|c| c.kind() == "link_destination");
+                let (target_range, wiki) = match dest {
+                    Some(dest) => (dest.byte_range(), false),
+                    // The outer brackets of `[[Note]]` are left as text.
+                    None if node.kind() == "link"
+                        && node.start_byte() > 0
+                        && bytes[node.start_byte() - 1] == b'['
+                        && bytes.get(node.end_byte()) == Some(&b']') =>
+                    {
+                        let text = &self.content[node.byte_range()];
+                        let inner = text.trim_start_matches('[').trim_start();
+                        let start = node.end_byte() - inner.len();
+                        let inner = inner.trim_end_matches(']').trim_end();
+                        (start..start + inner.len(), true)
                     }
+                    None => return None,
+                };
+                Some(LinkRef {
+                    range: node.byte_range(),
+                    target: self.content[target_range.clone()].to_string(),
+                    target_range,
+                    wiki,
+                })
+            })
+            .filter(|l| !l.target.is_empty())
+            .collect();
+        links.sort_by_key(|l| l.range.start);
+        links
+    }
+
+    pub fn link_to_iter(&self) -> Iter<'_, String> {
+        self.link_to.iter()
+    }
+
+    /// Blocks holding links to `zid`, in the order they appear.
+    pub fn link_contexts(&self, zid: &str) -> Vec<LinkContext> {
+        self.contexts_where(|note| note == zid)
+    }
+
+    /// Blocks holding links to `target`, by its id, title or an alias, in
+    /// the order they appear.
+    pub fn link_contexts_to(&self, target: &Zettel) -> Vec<LinkContext> {
+        self.contexts_where(|note| target.is_named(note))
+    }
+
+    /// Whether the zettel part of a link names this zettel, by its id,
+    /// title or an alias.
+    fn is_named(&self, note: &str) -> bool {
+        let name = note.trim().to_lowercase();
+        note == self.zid()
+            || self.title().to_lowercase() == name
+            || self.aliases().iter().any(|a| a.to_lowercase() == name)
+    }
+
+    /// Point the links to `from` to `to` instead, keeping their anchors,
+    /// returns how many are rewritten. Wiki links get the title of `to`,
+    /// others its id.
+    pub fn relink(&mut self, from: &Zettel, to: &Zettel) -> Result<usize> {
+        self.rewrite_links(|l| {
+            let (_, anchor) =
+                Anchor::split_by(&l.target, |n| from.is_named(n))?;
+            let name = if l.wiki { to.title() } else { to.zid() };
+            Some(match anchor {
+                Some(anchor) => format!("{}{}", name, anchor),
+                None => name.to_string(),
+            })
+        })
+    }
+
+    /// Point links and images, not wiki links, elsewhere: `to` maps the
+    /// destination of one to its new one, none to keep it. Returns how many
+    /// are rewritten.
+    pub fn retarget<F>(&mut self, to: F) -> Result<usize>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.rewrite_links(|l| if l.wiki { None } else { to(&l.target) })
+    }
+
+    fn rewrite_links<F>(&mut self, to: F) -> Result<usize>
+    where
+        F: Fn(&LinkRef) -> Option<String>,
+    {
+        let links: Vec<(Range<usize>, String)> = self
+            .link_refs()
+            .into_iter()
+            .filter_map(|l| to(&l).map(|t| (l.target_range, t)))
+            .collect();
+        if links.is_empty() {
+            return Ok(0);
+        }
+        let mut content = self.content.clone();
+        for (range, target) in links.iter().rev() {
+            content.replace_range(range.clone(), target);
+        }
+        self.set_content(&content)?;
+        Ok(links.len())
+    }
+
+    /// Append the content of `source` as a section titled by it, take its
+    /// tags, and point the links to it to this zettel.
+    pub fn merge(&mut self, source: &Zettel) -> Result<()> {
+        let this = self.clone();
+        self.relink(source, &this)?;
+        let content = format!(
+            "{}\n\n# {}\n\n{}\n",
+            self.content.trim_end(),
+            source.title(),
+            source.content().trim()
+        );
+        self.set_content(content.trim_start())?;
+        self.add_tags(source.tags());
+        Ok(())
+    }
+
+    fn contexts_where<F>(&self, points_to: F) -> Vec<LinkContext>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.link_refs()
+            .into_iter()
+            .filter_map(|l| {
+                let (_, anchor) = Anchor::split_by(&l.target, &points_to)?;
+                let mut context = self.context(l.range);
+                context.anchor = anchor;
+                Some(context)
+            })
+            .collect()
+    }
+
+    /// Byte range of the section or block of `anchor` in the content.
+    pub fn anchor_range(&self, anchor: &Anchor) -> Option<Range<usize>> {
+        match anchor {
+            Anchor::Heading(name) => {
+                let name = name.to_lowercase();
+                self.headings()
+                    .into_iter()
+                    .find(|h| h.text.to_lowercase() == name)
+                    .map(|h| h.section)
+            }
+            Anchor::Block(id) => self
+                .walk_iter()
+                .filter(|n| n.kind() == "paragraph")
+                .find(|n| {
+                    let text = self.content[n.byte_range()].trim_end();
+                    text.strip_suffix(id.as_str())
+                        .and_then(|t| t.strip_suffix('^'))
+                        .map_or(false, |t| {
+                            t.is_empty() || t.ends_with(char::is_whitespace)
+                        })
+                })
+                .map(|n| n.byte_range()),
+        }
+    }
+
+    /// Headings of the content, in the order they appear.
+    pub fn headings(&self) -> Vec<Heading> {
+        let mut headings: Vec<Heading> = self
+            .walk_iter()
+            .filter(|n| matches!(n.kind(), "atx_heading" | "setext_heading"))
+            .filter_map(|node| {
+                let mut cursor = node.walk();
+                let children: Vec<Node> = node.children(&mut cursor).collect();
+                let level = children.iter().find_map(|c| {
+                    let kind = c.kind();
+                    let n = kind
+                        .strip_prefix("atx_h")
+                        .or_else(|| kind.strip_prefix("setext_h"))?;
+                    n.chars().next()?.to_digit(10)
+                })?;
+                let text = children
+                    .iter()
+                    .find(|c| c.kind() == "heading_content")
+                    .map(|c| {
+                        self.content[c.byte_range()]
+                            .trim()
+                            .trim_end_matches('#')
+                            .trim()
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                Some(Heading {
+                    level: level as usize,
+                    text,
+                    line: node.start_position().row,
+                    range: node.byte_range(),
+                    section: node.start_byte()..self.content.len(),
+                })
+            })
+            .collect();
+        headings.sort_by_key(|h| h.range.start);
+        for i in 0..headings.len() {
+            let level = headings[i].level;
+            if let Some(next) =
+                headings[i + 1..].iter().find(|h| h.level <= level)
+            {
+                headings[i].section.end = next.range.start;
+            }
+        }
+        headings
+    }
+
+    /// Text of the first paragraph, for previews.
+    pub fn first_paragraph(&self) -> Option<&str> {
+        self.walk_iter()
+            .filter(|n| n.kind() == "paragraph")
+            .min_by_key(|n| n.start_byte())
+            .map(|n| self.content[n.byte_range()].trim())
+    }
+
+    /// The block around `range` of the content.
+    pub fn context(&self, range: Range<usize>) -> LinkContext {
+        let tree = match self.tree.as_ref() {
+            Some(tree) => tree,
+            None => {
+                return LinkContext {
+                    text: self.content[range.clone()].to_string(),
+                    link: 0..range.len(),
+                    anchor: None,
                 }
             }
+        };
+        let root = tree.root_node();
+        let mut block = root
+            .descendant_for_byte_range(range.start, range.end)
+            .unwrap_or(root);
+        while let Some(p) = block.parent() {
+            if matches!(
+                block.kind(),
+                "paragraph"
+                    | "atx_heading"
+                    | "setext_heading"
+                    | "table_data_row"
+            ) {
+                break;
+            }
+            block = p;
+        }
+        let (start, end) = if block.kind() == "document" {
+            (range.start, range.end)
+        } else {
+            (block.start_byte(), block.end_byte())
+        };
+        LinkContext {
+            text: self.content[start..end].to_string(),
+            link: range.start - start..range.end - start,
+            anchor: None,
         }
-        self.link_to = link_to
     }
 
-    pub fn link_to_iter(&self) -> Iter<'_, String> {
-        self.link_to.iter()
+    /// Byte ranges of `name` in the content, ignoring case, that are whole
+    /// words and not inside links or code.
+    pub fn mentions(&self, name: &str) -> Vec<Range<usize>> {
+        let name = name.to_lowercase();
+        if name.is_empty() {
+            return vec![];
+        }
+        let skipped: Vec<Range<usize>> = self
+            .walk_iter()
+            .filter(|n| {
+                matches!(
+                    n.kind(),
+                    "link" | "image" | "code_span" | "fenced_code_block"
+                )
+            })
+            .map(|n| n.byte_range())
+            .collect();
+        // Lowercasing may change the length of a char, offsets in it are
+        // mapped back to the content where a char of it starts.
+        let mut lower = String::with_capacity(self.content.len());
+        let mut starts = vec![];
+        for (i, c) in self.content.char_indices() {
+            starts.resize(lower.len(), None);
+            starts.push(Some(i));
+            lower.extend(c.to_lowercase());
+        }
+        starts.resize(lower.len(), None);
+        starts.push(Some(self.content.len()));
+        let content = &self.content;
+        let is_word =
+            |c: Option<char>| c.map_or(false, |c| c.is_alphanumeric());
+        lower
+            .match_indices(&name)
+            .filter_map(|(i, _)| Some(starts[i]?..starts[i + name.len()]?))
+            .filter(|r| {
+                !is_word(content[..r.start].chars().last())
+                    && !is_word(content[r.end..].chars().next())
+            })
+            .filter(|r| {
+                !skipped.iter().any(|s| s.start < r.end && r.start < s.end)
+            })
+            .collect()
+    }
+
+    /// Turn `range` of the content into a link to `zid`.
+    pub fn link_range(&mut self, range: Range<usize>, zid: &str) -> Result<()> {
+        let text = self
+            .content
+            .get(range.clone())
+            .ok_or_else(|| Error::Link(zid.to_string()))?;
+        let content = format!(
+            "{}[{}]({}){}",
+            &self.content[..range.start],
+            text,
+            zid,
+            &self.content[range.end..]
+        );
+        self.set_content(&content)
     }
 }
 
@@ -282,3 +839,165 @@ impl<'a> Iterator for WalkIter<'a> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Anchor, Zettel};
+    use crate::error::Error;
+    use crate::testing::temp_dir;
+
+    #[test]
+    fn test_link_contexts() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("a.md");
+        std::fs::write(
+            &path,
+            "+++\ntitle = \"a\"\n+++\n# A\n\nSee [b](@/b.md) here.\n\n- [c](@/c.md)\n",
+        )
+        .unwrap();
+        let z = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        let contexts = z.link_contexts("@/b.md");
+        assert_eq!(contexts.len(), 1);
+        let c = &contexts[0];
+        assert_eq!(c.text, "See [b](@/b.md) here.");
+        assert_eq!(&c.text[c.link.clone()], "[b](@/b.md)");
+        assert!(z.link_contexts("@/d.md").is_empty());
+        assert_eq!(z.first_paragraph(), Some("See [b](@/b.md) here."));
+    }
+
+    #[test]
+    fn test_anchors() {
+        assert_eq!(
+            Anchor::split("Note#Some Heading"),
+            ("Note", Some(Anchor::Heading("Some Heading".to_string())))
+        );
+        assert_eq!(
+            Anchor::split("@/b.md^ab-1"),
+            ("@/b.md", Some(Anchor::Block("ab-1".to_string())))
+        );
+        assert_eq!(Anchor::split("x^2 + y"), ("x^2 + y", None));
+        assert_eq!(Anchor::split("Note#"), ("Note", None));
+        assert_eq!(
+            Anchor::split("C##Intro"),
+            ("C#", Some(Anchor::Heading("Intro".to_string())))
+        );
+        let is_c = |note: &str| note == "C#";
+        assert_eq!(Anchor::split_by("C#", is_c), Some(("C#", None)));
+        assert_eq!(Anchor::split_by("F# notes", is_c), None);
+
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("r.md");
+        std::fs::write(
+            &path,
+            "+++\ntitle = \"r\"\n+++\n# Intro\n\nFirst ^one\n\n# Next\n\n- item ^two\n\nSee [[b#Why]] and [b](@/b.md^x) and [c](@/c.md).\n",
+        )
+        .unwrap();
+        let z = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        let content = z.content();
+        let range = |a: Anchor| z.anchor_range(&a).map(|r| &content[r]);
+        assert_eq!(
+            range(Anchor::Heading("intro".to_string())),
+            Some("# Intro\n\nFirst ^one\n\n")
+        );
+        assert_eq!(range(Anchor::Block("one".to_string())), Some("First ^one"));
+        assert_eq!(range(Anchor::Block("two".to_string())), Some("item ^two"));
+        assert_eq!(range(Anchor::Block("three".to_string())), None);
+
+        let links: Vec<_> = z.link_to_iter().collect();
+        assert_eq!(links, vec!["b#Why", "@/b.md^x", "@/c.md"]);
+        let contexts = z.link_contexts("@/b.md");
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].anchor, Some(Anchor::Block("x".to_string())));
+    }
+
+    #[test]
+    fn test_headings() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("h.md");
+        std::fs::write(
+            &path,
+            "+++\ntitle = \"h\"\n+++\n# One #\n\ntext\n\n## Two\n\nmore\n\nThree\n=====\n\nend\n",
+        )
+        .unwrap();
+        let z = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        let headings = z.headings();
+        let summary: Vec<_> = headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(1, "One", 0), (2, "Two", 4), (1, "Three", 8)]
+        );
+        let content = z.content();
+        assert_eq!(
+            &content[headings[0].section.clone()],
+            "# One #\n\ntext\n\n## Two\n\nmore\n\n"
+        );
+        assert_eq!(&content[headings[1].section.clone()], "## Two\n\nmore\n\n");
+        assert_eq!(
+            &content[headings[2].section.clone()],
+            "Three\n=====\n\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_mentions() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("m.md");
+        std::fs::write(
+            &path,
+            "+++\ntitle = \"m\"\n+++\nRust, [rust](@/r.md), rusty `rust`\nand RUST.\n",
+        )
+        .unwrap();
+        let mut z = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        let mentions = z.mentions("rust");
+        assert_eq!(mentions, vec![0..4, 39..43]);
+        assert_eq!(
+            z.context(39..43).text,
+            "Rust, [rust](@/r.md), rusty `rust`\nand RUST."
+        );
+
+        z.link_range(0..4, "@/r.md").unwrap();
+        assert!(z.content().starts_with("[Rust](@/r.md), "));
+        assert_eq!(z.mentions("rust").len(), 1);
+
+        // Case is folded beyond ASCII, İ lowercases to two chars.
+        z.set_content("İ Élan, ÉLAN and Привет привет.\n").unwrap();
+        assert_eq!(z.mentions("élan"), vec![3..8, 10..15]);
+        assert_eq!(z.mentions("привет"), vec![20..32, 33..45]);
+        assert!(z.mentions("i").is_empty());
+    }
+
+    #[test]
+    fn test_save_conflict() {
+        let tmp = temp_dir();
+        let dir = tmp.path();
+        let path = dir.join("c.md");
+        std::fs::write(&path, "+++\ntitle = \"c\"\n+++\nOne\n").unwrap();
+        let mut z = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        z.set_content("One\nTwo\n").unwrap();
+        z.save().unwrap();
+        z.save().unwrap();
+        assert!(!z.changed_on_disk().unwrap());
+
+        let theirs = "+++\ntitle = \"c\"\n+++\nOne\nTwo\nThree\n";
+        std::fs::write(&path, theirs).unwrap();
+        assert!(z.changed_on_disk().unwrap());
+        assert!(matches!(z.save(), Err(Error::Conflict { .. })));
+        assert!(z.is_base("+++\ntitle = \"c\"\n+++\nOne\nTwo\n"));
+        assert!(!z.is_base(theirs));
+        assert_eq!(z.disk_text().unwrap(), theirs);
+
+        z.rebase().unwrap();
+        z.set_text("+++\ntitle = \"C\"\n+++\nMerged\n").unwrap();
+        z.save().unwrap();
+        assert_eq!(z.title(), "C");
+        let loaded = Zettel::from_md(dir.to_str().unwrap(), &path).unwrap();
+        assert_eq!(loaded.content(), "Merged\n");
+    }
+}
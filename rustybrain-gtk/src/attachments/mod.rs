@@ -0,0 +1,296 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use gdk::{Key, ModifierType};
+use gtk::{prelude::*, ApplicationWindow, Dialog, EventControllerKey};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::attachment::{Attachment, MissingFile};
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::Result;
+
+use crate::AppModel;
+
+pub struct Model {
+    app_win: Option<ApplicationWindow>,
+    kasten: Rc<RefCell<Kasten>>,
+    /// Attachments no note links to.
+    orphaned: Vec<Attachment>,
+    /// Links to missing files, with the titles of their notes.
+    missing: Vec<(MissingFile, String)>,
+    show: bool,
+
+    /// The lists should be filled from the report again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    Show,
+    Hide,
+    /// Ask to remove the attachments no note links to.
+    DeleteOrphans,
+    /// Remove the attachments no note links to, except the files, linked
+    /// from open notes.
+    DeleteExcept(Vec<PathBuf>),
+    /// Open the note of a missing file.
+    OpenMissing(usize),
+}
+
+pub struct AttachmentsDialog {
+    dialog: Dialog,
+    orphaned_title: gtk::Label,
+    orphaned: gtk::ListBox,
+    missing_title: gtk::Label,
+    missing: gtk::ListBox,
+    delete_btn: gtk::Button,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = AttachmentsDialog;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            app_win: None,
+            kasten: parent_model.kasten.clone(),
+            orphaned: vec![],
+            missing: vec![],
+            show: false,
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        let result = match msg {
+            Msg::Init(w) => {
+                self.app_win = Some(w);
+                Ok(())
+            }
+            Msg::Show => self.show(),
+            Msg::Hide => {
+                self.show = false;
+                Ok(())
+            }
+            Msg::DeleteOrphans => {
+                let n = self.orphaned.len();
+                send!(parent_sender, super::Msg::AskDeleteOrphans(n));
+                Ok(())
+            }
+            Msg::DeleteExcept(files) => self.delete_orphans(&files),
+            Msg::OpenMissing(i) => {
+                if let Some((m, _)) = self.missing.get(i) {
+                    send!(parent_sender, super::Msg::Visit(m.zid.clone()));
+                    self.show = false;
+                }
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Check attachments failed!".to_string(),
+                    Rc::new(e)
+                )
+            );
+        }
+    }
+}
+
+impl Model {
+    fn show(&mut self) -> Result<()> {
+        let kasten = self.kasten.borrow();
+        let report = kasten.attachment_report()?;
+        self.orphaned = report.orphaned().cloned().collect();
+        self.missing = report
+            .missing
+            .into_iter()
+            .map(|m| {
+                let title = kasten
+                    .resolve(&m.zid)
+                    .map(|z| z.borrow().title().to_string())
+                    .unwrap_or_else(|_| m.zid.clone());
+                (m, title)
+            })
+            .collect();
+        self.show = true;
+        self.reset = true;
+        Ok(())
+    }
+
+    /// Remove the orphaned attachments, but `kept`. Those removed before
+    /// a failure are gone from the list too.
+    fn delete_orphans(&mut self, kept: &[PathBuf]) -> Result<()> {
+        let attachments = self.kasten.borrow().attachments();
+        self.reset = true;
+        let mut removed = Ok(());
+        self.orphaned.retain(|a| {
+            if removed.is_err() || kept.contains(&a.path) {
+                return true;
+            }
+            removed = attachments.remove(&a.path);
+            removed.is_err()
+        });
+        removed
+    }
+}
+
+impl Widgets<Model, AppModel> for AttachmentsDialog {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Attachments")
+            .default_width(700)
+            .default_height(500)
+            .build();
+        let orphaned_title = gtk::Label::builder()
+            .wrap(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let orphaned = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        let orphaned_window = gtk::ScrolledWindow::builder()
+            .vexpand(true)
+            .child(&orphaned)
+            .build();
+        let missing_title = gtk::Label::builder()
+            .wrap(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let missing = gtk::ListBox::builder().build();
+        let s = sender.clone();
+        missing.connect_row_activated(move |_, row| {
+            send!(s, Msg::OpenMissing(row.index() as usize))
+        });
+        let missing_window = gtk::ScrolledWindow::builder()
+            .vexpand(true)
+            .child(&missing)
+            .build();
+
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let close_btn = gtk::Button::builder().label("Close").build();
+        let delete_btn =
+            gtk::Button::builder().label("Delete unused files").build();
+        buttons.append(&close_btn);
+        buttons.append(&delete_btn);
+
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        box_.append(&orphaned_title);
+        box_.append(&orphaned_window);
+        box_.append(&missing_title);
+        box_.append(&missing_window);
+        box_.append(&buttons);
+        dialog.set_child(Some(&box_));
+
+        let s = sender.clone();
+        close_btn.connect_clicked(move |_| send!(s, Msg::Hide));
+        let s = sender.clone();
+        delete_btn.connect_clicked(move |_| send!(s, Msg::DeleteOrphans));
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        AttachmentsDialog {
+            dialog,
+            orphaned_title,
+            orphaned,
+            missing_title,
+            missing,
+            delete_btn,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        self.delete_btn.set_sensitive(!model.orphaned.is_empty());
+        if model.reset {
+            self.fill(model);
+        }
+        if model.show {
+            self.dialog.show();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
+
+impl AttachmentsDialog {
+    fn fill(&self, model: &Model) {
+        self.orphaned_title.set_text(if model.orphaned.is_empty() {
+            "Every attached file is linked by a note."
+        } else {
+            "Attached files no note links to:"
+        });
+        Self::clear(&self.orphaned);
+        for a in model.orphaned.iter() {
+            self.orphaned.append(&Self::row(&a.link));
+        }
+
+        self.missing_title.set_text(if model.missing.is_empty() {
+            "No note links to a missing file."
+        } else {
+            "Links to missing files, pick one to open its note:"
+        });
+        Self::clear(&self.missing);
+        for (m, title) in model.missing.iter() {
+            self.missing
+                .append(&Self::row(&format!("{}: {}", title, m.link)));
+        }
+    }
+
+    fn clear(list: &gtk::ListBox) {
+        while let Some(c) = list.last_child() {
+            list.remove(&c);
+        }
+    }
+
+    fn row(text: &str) -> gtk::ListBoxRow {
+        let label = gtk::Label::builder()
+            .label(text)
+            .halign(gtk::Align::Start)
+            .ellipsize(gtk::pango::EllipsizeMode::Middle)
+            .margin_top(4)
+            .margin_bottom(4)
+            .build();
+        gtk::ListBoxRow::builder().child(&label).build()
+    }
+}
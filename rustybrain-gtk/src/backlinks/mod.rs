@@ -1,28 +1,56 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use gtk::glib;
 use gtk::prelude::*;
 use gtk::ListBox;
 use gtk::ScrolledWindow;
+use relm4::send;
 use relm4::ComponentUpdate;
 use relm4::Widgets;
-use rustybrain_core::kasten::Kasten;
-use rustybrain_core::zettel::Zettel;
+use rustybrain_core::kasten::{Kasten, Mention};
+use rustybrain_core::zettel::{LinkContext, Zettel};
 
 use crate::AppModel;
 
+/// Order of the source zettels.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// Newest first.
+    Date,
+    Title,
+}
+
+/// Zettel linking to the current one, with the blocks holding the links.
+struct Group {
+    zettel: Rc<RefCell<Zettel>>,
+    contexts: Vec<LinkContext>,
+}
+
 pub struct Model {
     kasten: Rc<RefCell<Kasten>>,
     zettel: Option<Rc<RefCell<Zettel>>>,
+    sort: Sort,
+    groups: Vec<Group>,
+    mentions: Vec<Rc<Mention>>,
 }
 
 pub enum Msg {
     ChangeZettel(Rc<RefCell<Zettel>>),
+    Clear,
+    Sort(Sort),
+    /// Open the source zettel of the n-th group.
+    Open(usize),
+    /// Rewrite the n-th unlinked mention into a link.
+    LinkMention(usize),
+    /// Zettels are changed, find mentions again.
+    Refresh,
 }
 
 pub struct Backlinks {
     window: ScrolledWindow,
     layout: ListBox,
+    mentions: ListBox,
 }
 
 impl relm4::Model for Model {
@@ -38,6 +66,9 @@ impl ComponentUpdate<AppModel> for Model {
         Model {
             kasten: parent_model.kasten.clone(),
             zettel: None,
+            sort: Sort::Date,
+            groups: vec![],
+            mentions: vec![],
         }
     }
 
@@ -46,10 +77,74 @@ impl ComponentUpdate<AppModel> for Model {
         msg: Self::Msg,
         _components: &Self::Components,
         _sender: relm4::Sender<Self::Msg>,
-        _parent_sender: relm4::Sender<super::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
     ) {
         match msg {
             Msg::ChangeZettel(z) => self.zettel = Some(z),
+            Msg::Clear => self.zettel = None,
+            Msg::Sort(sort) => self.sort = sort,
+            Msg::Open(i) => {
+                if let Some(g) = self.groups.get(i) {
+                    send!(
+                        parent_sender,
+                        super::Msg::ChangeZettel(g.zettel.clone())
+                    );
+                }
+                return;
+            }
+            Msg::LinkMention(i) => {
+                if let (Some(m), Some(z)) = (self.mentions.get(i), &self.zettel)
+                {
+                    let zid = z.borrow().zid().to_string();
+                    send!(
+                        parent_sender,
+                        super::Msg::LinkMention(m.clone(), zid)
+                    );
+                }
+                return;
+            }
+            Msg::Refresh => {}
+        }
+        self.collect(&parent_sender);
+    }
+}
+
+impl Model {
+    fn collect(&mut self, parent_sender: &relm4::Sender<super::Msg>) {
+        self.groups.clear();
+        self.mentions.clear();
+        let z = match self.zettel.as_ref() {
+            Some(z) => z.borrow(),
+            None => return,
+        };
+        match self.kasten.borrow().unlinked_mentions(&z) {
+            Ok(mentions) => {
+                self.mentions = mentions.into_iter().map(Rc::new).collect()
+            }
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Find unlinked mentions failed!".to_string(),
+                    Rc::new(e)
+                )
+            ),
+        }
+        for source in self.kasten.borrow().iter_backlinks(&z) {
+            let contexts = source.borrow().link_contexts_to(&z);
+            self.groups.push(Group {
+                zettel: source,
+                contexts,
+            });
+        }
+        match self.sort {
+            Sort::Date => self.groups.sort_by(|a, b| {
+                b.zettel.borrow().date().cmp(&a.zettel.borrow().date())
+            }),
+            Sort::Title => self.groups.sort_by(|a, b| {
+                let a = a.zettel.borrow().title().to_lowercase();
+                let b = b.zettel.borrow().title().to_lowercase();
+                a.cmp(&b)
+            }),
         }
     }
 }
@@ -60,31 +155,138 @@ impl Widgets<Model, super::AppModel> for Backlinks {
     fn init_view(
         _model: &Model,
         _components: &(),
-        _sender: relm4::Sender<Msg>,
+        sender: relm4::Sender<Msg>,
     ) -> Self {
+        let sort = gtk::DropDown::from_strings(&["By date", "By title"]);
+        let s = sender.clone();
+        sort.connect_selected_notify(move |dd| {
+            let sort = match dd.selected() {
+                1 => Sort::Title,
+                _ => Sort::Date,
+            };
+            send!(s, Msg::Sort(sort));
+        });
         let layout = ListBox::builder().build();
+        layout.connect_row_activated(move |_, row| {
+            send!(sender, Msg::Open(row.index() as usize))
+        });
+        let mentions_label = gtk::Label::builder()
+            .label("Unlinked mentions")
+            .halign(gtk::Align::Start)
+            .css_classes(vec!["heading".to_string()])
+            .build();
+        let mentions = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        box_.append(&sort);
+        box_.append(&layout);
+        box_.append(&mentions_label);
+        box_.append(&mentions);
         let window = ScrolledWindow::builder()
             .width_request(200)
-            .child(&layout)
+            .child(&box_)
             .build();
-        Backlinks { window, layout }
+        Backlinks {
+            window,
+            layout,
+            mentions,
+        }
     }
 
     fn root_widget(&self) -> Self::Root {
         self.window.clone()
     }
 
-    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+    fn view(&mut self, model: &Model, sender: relm4::Sender<Msg>) {
         while let Some(c) = self.layout.last_child() {
             self.layout.remove(&c);
         }
-        if let Some(z) = model.zettel.as_ref() {
-            for item in model.kasten.borrow().iter_backlinks(&z.borrow()) {
-                let z = &item.borrow();
-                let label = gtk::Label::builder().label(z.title()).build();
-                let row = gtk::ListBoxRow::builder().child(&label).build();
-                self.layout.append(&row);
+        while let Some(c) = self.mentions.last_child() {
+            self.mentions.remove(&c);
+        }
+        for (i, m) in model.mentions.iter().enumerate() {
+            let box_ = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(4)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            let head = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .build();
+            let title = gtk::Label::builder()
+                .label(m.zettel.borrow().title())
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .css_classes(vec!["heading".to_string()])
+                .build();
+            let link_btn = gtk::Button::builder().label("Link it").build();
+            let s = sender.clone();
+            link_btn.connect_clicked(move |_| send!(s, Msg::LinkMention(i)));
+            head.append(&title);
+            head.append(&link_btn);
+            let snippet = gtk::Label::builder()
+                .use_markup(true)
+                .label(&Self::snippet(&m.context))
+                .halign(gtk::Align::Start)
+                .wrap(true)
+                .max_width_chars(30)
+                .build();
+            box_.append(&head);
+            box_.append(&snippet);
+            self.mentions
+                .append(&gtk::ListBoxRow::builder().child(&box_).build());
+        }
+        for g in model.groups.iter() {
+            let box_ = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(4)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            let title = gtk::Label::builder()
+                .label(g.zettel.borrow().title())
+                .halign(gtk::Align::Start)
+                .css_classes(vec!["heading".to_string()])
+                .build();
+            box_.append(&title);
+            for c in g.contexts.iter() {
+                if let Some(anchor) = c.anchor.as_ref() {
+                    let cited = gtk::Label::builder()
+                        .label(&anchor.to_string())
+                        .halign(gtk::Align::Start)
+                        .css_classes(vec!["dim-label".to_string()])
+                        .build();
+                    box_.append(&cited);
+                }
+                let snippet = gtk::Label::builder()
+                    .use_markup(true)
+                    .label(&Self::snippet(c))
+                    .halign(gtk::Align::Start)
+                    .wrap(true)
+                    .max_width_chars(30)
+                    .build();
+                box_.append(&snippet);
             }
+            let row = gtk::ListBoxRow::builder().child(&box_).build();
+            self.layout.append(&row);
         }
     }
 }
+
+impl Backlinks {
+    /// Markup of the context, with the link highlighted.
+    fn snippet(c: &LinkContext) -> String {
+        let text = &c.text;
+        format!(
+            "{}<b><span underline=\"single\">{}</span></b>{}",
+            glib::markup_escape_text(&text[..c.link.start]),
+            glib::markup_escape_text(&text[c.link.clone()]),
+            glib::markup_escape_text(&text[c.link.end..]),
+        )
+    }
+}
@@ -0,0 +1,327 @@
+use gdk::{Key, ModifierType};
+use gtk::glib::translate::{FromGlib, IntoGlib};
+use rustybrain_core::config::Config;
+use rustybrain_core::Error;
+
+use crate::Msg;
+
+/// One key press, the key is lowercased and only default modifiers are kept.
+type Chord = (u32, ModifierType);
+
+/// Action the user can bind keys to, or pick from the palette.
+pub struct Command {
+    id: &'static str,
+    title: &'static str,
+    msg: Msg,
+}
+
+struct Binding {
+    keys: Vec<Chord>,
+    command: usize,
+
+    /// Bindings of higher layers override lower ones.
+    layer: usize,
+}
+
+/// What a key press means to the registry.
+pub enum Press {
+    /// A binding is completed.
+    Fire(Msg),
+    /// Part of a sequence, waiting for more keys.
+    Pending,
+    /// Not bound to anything.
+    Unbound,
+}
+
+#[derive(Default)]
+pub struct Registry {
+    commands: Vec<Command>,
+    bindings: Vec<Binding>,
+
+    /// Chords pressed so far of an unfinished sequence.
+    pending: Vec<Chord>,
+}
+
+/// Bindings of commands not in `[shortcut]`.
+const DEFAULT_KEYS: [(&str, &str); 18] = [
+    ("<Alt>Left", "back"),
+    ("<Alt>Right", "forward"),
+    ("<Control><Shift>p", "palette"),
+    ("<Control>n", "new-note"),
+    ("<Control>d", "daily-note"),
+    ("<Control>s", "save"),
+    ("<Control>f", "find-in-note"),
+    ("<Control><Shift>r", "replace-in-notes"),
+    ("F2", "edit-title"),
+    ("<Control><Shift>l", "toggle-list"),
+    ("<Control><Shift>b", "toggle-backlinks"),
+    ("<Control><Shift>h", "toggle-versions"),
+    ("<Control><Shift>o", "toggle-outline"),
+    ("<Alt>Down", "next-note"),
+    ("<Alt>Up", "previous-note"),
+    ("<Control>t", "new-tab"),
+    ("<Control>w", "close-tab"),
+    // Not `<Control>backslash`, text views take it to unselect.
+    ("<Control><Shift>t", "split"),
+];
+
+impl Command {
+    fn new(id: &'static str, title: &'static str, msg: Msg) -> Self {
+        Command { id, title, msg }
+    }
+
+    pub fn id(&self) -> &'static str {
+        self.id
+    }
+
+    pub fn title(&self) -> &'static str {
+        self.title
+    }
+
+    pub fn msg(&self) -> Msg {
+        self.msg.clone()
+    }
+}
+
+impl Registry {
+    /// Build bindings from config, bad or conflicting ones are skipped and
+    /// returned as errors.
+    pub fn new(config: &Config) -> (Self, Vec<Error>) {
+        let mut registry = Self::with_defaults();
+        let mut errors = vec![];
+
+        let shortcut = config.shortcut();
+        let shortcuts = [
+            ("find", shortcut.find()),
+            ("insert", shortcut.insert()),
+            ("quit", shortcut.quit()),
+            ("preferences", shortcut.preferences()),
+        ];
+        for (id, key) in shortcuts {
+            if let Err(message) = registry.bind(key, id, 1) {
+                errors.push(config.invalid("shortcut", id, &message));
+            }
+        }
+
+        for (key, id) in config.keys() {
+            if let Err(message) = registry.bind(key, id, 2) {
+                errors.push(config.invalid("keys", key, &message));
+            }
+        }
+        (registry, errors)
+    }
+
+    /// Check keys of `[shortcut]` as `new` binds them, by command id, and
+    /// returns the message of the first bad or conflicting one.
+    pub fn check_shortcuts(shortcuts: &[(&str, &str)]) -> Result<(), String> {
+        let mut registry = Self::with_defaults();
+        for (id, key) in shortcuts {
+            registry.bind(key, id, 1)?;
+        }
+        Ok(())
+    }
+
+    /// All commands, with only the default bindings.
+    fn with_defaults() -> Self {
+        let mut registry = Registry {
+            commands: vec![
+                Command::new("palette", "Command palette", Msg::StartPalette),
+                Command::new("new-note", "New note", Msg::StartNewZettel),
+                Command::new("daily-note", "Open daily note", Msg::OpenDaily),
+                Command::new("find", "Find note", Msg::StartSearch),
+                Command::new("insert", "Insert link", Msg::StartInsert),
+                Command::new("find-in-note", "Find in note", Msg::StartFind),
+                Command::new(
+                    "replace-in-notes",
+                    "Replace in all notes",
+                    Msg::StartReplace,
+                ),
+                Command::new(
+                    "extract-note",
+                    "Extract selection into new note",
+                    Msg::ExtractZettel,
+                ),
+                Command::new(
+                    "merge-note",
+                    "Merge note into this one",
+                    Msg::StartMerge,
+                ),
+                Command::new("move-note", "Move note", Msg::StartMove),
+                Command::new("save", "Save note", Msg::Save),
+                Command::new("edit-title", "Edit title", Msg::EditTitle),
+                Command::new(
+                    "toggle-list",
+                    "Toggle note list",
+                    Msg::ToggleList,
+                ),
+                Command::new(
+                    "toggle-backlinks",
+                    "Toggle backlinks",
+                    Msg::ToggleBacklinks,
+                ),
+                Command::new(
+                    "toggle-versions",
+                    "Toggle versions",
+                    Msg::ToggleVersions,
+                ),
+                Command::new(
+                    "toggle-outline",
+                    "Toggle outline",
+                    Msg::ToggleOutline,
+                ),
+                Command::new("fold", "Fold section", Msg::ToggleFold(None)),
+                Command::new("new-tab", "Open note in new tab", Msg::NewTab),
+                Command::new("close-tab", "Close tab", Msg::CloseTab),
+                Command::new("split", "Open note in new pane", Msg::SplitPane),
+                Command::new("back", "Go back", Msg::Back),
+                Command::new("forward", "Go forward", Msg::Forward),
+                Command::new("next-note", "Next note", Msg::StepList(1)),
+                Command::new(
+                    "previous-note",
+                    "Previous note",
+                    Msg::StepList(-1),
+                ),
+                Command::new(
+                    "version-history",
+                    "Version history",
+                    Msg::StartVersionHistory,
+                ),
+                Command::new("tasks", "Show tasks", Msg::StartTasks),
+                Command::new(
+                    "attachments",
+                    "Check attachments",
+                    Msg::StartAttachments,
+                ),
+                Command::new("export", "Export notes", Msg::StartExport),
+                Command::new("reindex", "Reindex slip-box", Msg::Reindex),
+                Command::new(
+                    "preferences",
+                    "Preferences",
+                    Msg::StartPreferences,
+                ),
+                Command::new("quit", "Quit", Msg::Quit),
+            ],
+            bindings: vec![],
+            pending: vec![],
+        };
+        for (key, id) in DEFAULT_KEYS {
+            // Defaults are known to be valid and free of conflicts.
+            let _ = registry.bind(key, id, 0);
+        }
+        registry
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Labels of the keys bound to command `id`, e.g. `Ctrl+X Ctrl+S`.
+    pub fn bindings_of(&self, id: &str) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|b| self.commands[b.command].id == id)
+            .map(|b| {
+                b.keys
+                    .iter()
+                    .map(|(k, m)| {
+                        gtk::accelerator_get_label(*k, *m).to_string()
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    /// Feed a key press, sequences are matched across calls.
+    pub fn press(&mut self, key: Key, mods: ModifierType) -> Press {
+        let k = key.into_glib();
+        let modifiers = Key::Shift_L.into_glib()..=Key::Hyper_R.into_glib();
+        if modifiers.contains(&k) || k == Key::ISO_Level3_Shift.into_glib() {
+            return Press::Unbound;
+        }
+        let chord = normalize(key, mods);
+
+        let waiting = !self.pending.is_empty();
+        self.pending.push(chord);
+        let mut prefix = false;
+        for b in self.bindings.iter() {
+            if b.keys == self.pending {
+                self.pending.clear();
+                return Press::Fire(self.commands[b.command].msg());
+            }
+            prefix |= b.keys.starts_with(&self.pending);
+        }
+        if prefix {
+            return Press::Pending;
+        }
+        self.pending.clear();
+        if waiting {
+            // Swallow the key breaking a sequence, like Emacs does.
+            Press::Pending
+        } else {
+            Press::Unbound
+        }
+    }
+
+    /// Bind `key` to command `id`, replacing conflicting bindings of lower
+    /// layers.
+    fn bind(
+        &mut self,
+        key: &str,
+        id: &str,
+        layer: usize,
+    ) -> Result<(), String> {
+        let command = self
+            .commands
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| format!("`{}` is not a command", id))?;
+        let keys = parse_keys(key)
+            .ok_or_else(|| format!("`{}` is not a valid key sequence", key))?;
+
+        let conflicts = |b: &Binding| {
+            b.keys.starts_with(&keys) || keys.starts_with(&b.keys)
+        };
+        if let Some(b) = self
+            .bindings
+            .iter()
+            .find(|b| b.layer == layer && conflicts(b))
+        {
+            return Err(format!(
+                "`{}` conflicts with the binding of `{}`",
+                key, self.commands[b.command].id
+            ));
+        }
+        self.bindings.retain(|b| !conflicts(b));
+        self.bindings.push(Binding {
+            keys,
+            command,
+            layer,
+        });
+        Ok(())
+    }
+}
+
+/// Parse chords separated by spaces, e.g. `<Control>x <Control>s`.
+fn parse_keys(s: &str) -> Option<Vec<Chord>> {
+    let keys = s
+        .split_whitespace()
+        .map(|chord| {
+            let (k, m) = gtk::accelerator_parse(chord)?;
+            if k == 0 {
+                return None;
+            }
+            Some(normalize(unsafe { Key::from_glib(k) }, m))
+        })
+        .collect::<Option<Vec<Chord>>>()?;
+    if keys.is_empty() {
+        None
+    } else {
+        Some(keys)
+    }
+}
+
+fn normalize(key: Key, mods: ModifierType) -> Chord {
+    let mods = mods & gtk::accelerator_get_default_mod_mask();
+    (key.to_lower().into_glib(), mods)
+}
@@ -0,0 +1,53 @@
+use gtk::{traits::TextBufferExt, TextMark};
+
+use super::Blocking;
+
+/// Inline code like `` `code` ``.
+pub struct CodeSpan {
+    left: TextMark,
+    right: TextMark,
+    /// Number of backticks around the code.
+    ticks: i32,
+}
+
+impl Blocking for CodeSpan {
+    fn from_node(
+        node: &rustybrain_core::md::Node,
+        buffer: &gtk::TextBuffer,
+    ) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        let ticks = match node.named_child(0) {
+            Some(text) => text.start_byte() - node.start_byte(),
+            None => (node.end_byte() - node.start_byte()) / 2,
+        };
+        CodeSpan {
+            left,
+            right,
+            ticks: ticks as i32,
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        buffer.apply_tag_by_name(
+            "code",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_in(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        self.show_endpoint_n(buffer, self.ticks);
+    }
+
+    fn cursor_out(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        self.hide_endpoint_n(buffer, self.ticks);
+    }
+}
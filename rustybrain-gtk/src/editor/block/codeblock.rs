@@ -2,6 +2,7 @@ use gtk::prelude::*;
 use gtk::TextIter;
 use gtk::TextMark;
 use gtk::TextView;
+use rustybrain_core::highlight;
 
 use super::Blocking;
 
@@ -20,7 +21,13 @@ impl Blocking for Codeblock {
         let mut content = None;
         let child = Self::node_child_by_kind(node, "code_fence_content");
         if let Some(cnt_node) = child {
-            content = Some(CodeblockContent::from_node(&cnt_node, buffer));
+            let mut c = CodeblockContent::from_node(&cnt_node, buffer);
+            if let Some(info) = Self::node_child_by_kind(node, "info_string") {
+                let start = buffer.iter_at_offset(info.start_byte() as i32);
+                let end = buffer.iter_at_offset(info.end_byte() as i32);
+                c.info = buffer.text(&start, &end, true).to_string();
+            }
+            content = Some(c);
         }
 
         let (left, right) = Self::node_endpoint(node, buffer);
@@ -117,6 +124,8 @@ impl Codeblock {
 struct CodeblockContent {
     right: TextMark,
     left: TextMark,
+    /// Info string of the fence, naming the language.
+    info: String,
 }
 
 impl Blocking for CodeblockContent {
@@ -125,7 +134,11 @@ impl Blocking for CodeblockContent {
         buffer: &gtk::TextBuffer,
     ) -> Self {
         let (left, right) = Self::node_endpoint(node, buffer);
-        CodeblockContent { left, right }
+        CodeblockContent {
+            left,
+            right,
+            info: String::new(),
+        }
     }
 
     fn left(&self) -> &gtk::TextMark {
@@ -140,5 +153,31 @@ impl Blocking for CodeblockContent {
         let start = self.start(buffer);
         let end = self.end(buffer);
         buffer.apply_tag_by_name("code-block", &start, &end);
+        self.highlight(buffer);
+    }
+}
+
+impl CodeblockContent {
+    /// Tag the code by the highlighter of its language.
+    fn highlight(&self, buffer: &gtk::TextBuffer) {
+        let start = self.start(buffer);
+        let code = buffer.text(&start, &self.end(buffer), true);
+        // An unknown language or broken query leaves the code plain.
+        let spans = highlight::highlight(&self.info, &code).unwrap_or_default();
+        // Spans come in order, walk the chars along with them.
+        let (mut pos, mut offset) = (0, start.offset());
+        let mut char_offset = |byte: usize| {
+            if byte < pos {
+                return start.offset() + code[..byte].chars().count() as i32;
+            }
+            offset += code[pos..byte].chars().count() as i32;
+            pos = byte;
+            offset
+        };
+        for span in spans {
+            let s = buffer.iter_at_offset(char_offset(span.range.start));
+            let e = buffer.iter_at_offset(char_offset(span.range.end));
+            buffer.apply_tag_by_name(&format!("code-{}", span.kind), &s, &e);
+        }
     }
 }
@@ -79,3 +79,53 @@ impl Blocking for StrongEmphasis {
         self.hide_endpoint_n(buffer, 2);
     }
 }
+
+/// Struck text like `~~gone~~`, or `~gone~`.
+pub struct Strikethrough {
+    left: TextMark,
+    right: TextMark,
+    /// Number of tildes around the text.
+    tildes: i32,
+}
+
+impl Blocking for Strikethrough {
+    fn from_node(
+        node: &rustybrain_core::md::Node,
+        buffer: &gtk::TextBuffer,
+    ) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        let tildes = match node.named_child(0) {
+            Some(text) => text.start_byte() - node.start_byte(),
+            None => (node.end_byte() - node.start_byte()) / 2,
+        };
+        Self {
+            left,
+            right,
+            tildes: tildes as i32,
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        buffer.apply_tag_by_name(
+            "strikethrough",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_in(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        self.show_endpoint_n(buffer, self.tildes);
+    }
+
+    fn cursor_out(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        self.hide_endpoint_n(buffer, self.tildes);
+    }
+}
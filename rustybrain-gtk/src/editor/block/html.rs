@@ -0,0 +1,49 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+
+use super::Blocking;
+
+/// Block of raw HTML, dimmed while the cursor is away as it is not
+/// rendered.
+pub struct HtmlBlock {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for HtmlBlock {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        HtmlBlock { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        self.cursor_out(view, buffer);
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.remove_tag_by_name(
+            "html",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "html",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+}
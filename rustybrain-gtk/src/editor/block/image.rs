@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use gtk::gio;
+use gtk::prelude::*;
+use gtk::Picture;
+use gtk::TextBuffer;
+use gtk::TextMark;
+use gtk::TextTag;
+use gtk::TextView;
+use rustybrain_core::kasten;
+use rustybrain_core::md::Node;
+
+use super::Blocking;
+
+/// Space between a picture and the text around it.
+const PADDING: i32 = 6;
+
+/// Image like `![alt](path)`, its picture shown below the line of it, and
+/// the source hidden while the cursor is away.
+///
+/// The picture floats over the view as an overlay, in space kept below the
+/// line by a tag, instead of sitting at a `TextChildAnchor`: an anchor is a
+/// char of the buffer, which would shift the offsets of the parsed tree and
+/// go into the undo history of the note. It's put in place with `place`
+/// when the buffer or the size of the view changes.
+pub struct Image {
+    left: TextMark,
+    right: TextMark,
+    /// Destination of the image, as written.
+    dest: String,
+
+    picture: Option<Picture>,
+    /// Space kept below the line of the image for the picture.
+    space: TextTag,
+}
+
+/// Pictures of the images of a note by their files, decoded again only once
+/// a file is modified, not on every edit of the note.
+#[derive(Default)]
+pub struct Pictures {
+    loaded: HashMap<PathBuf, (Option<SystemTime>, Option<gdk::Texture>)>,
+}
+
+impl Pictures {
+    /// Picture of the file at `path`, none if it can't be decoded.
+    fn get(&mut self, path: &Path) -> Option<gdk::Texture> {
+        let mtime = fs::metadata(path).and_then(|m| m.modified()).ok();
+        match self.loaded.get(path) {
+            Some((t, texture)) if *t == mtime => texture.clone(),
+            _ => {
+                let file = gio::File::for_path(path);
+                let texture = gdk::Texture::from_file(&file).ok();
+                self.loaded
+                    .insert(path.to_path_buf(), (mtime, texture.clone()));
+                texture
+            }
+        }
+    }
+}
+
+impl Blocking for Image {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        let dest = Self::node_child_by_kind(node, "link_destination")
+            .map(|n| {
+                let start = buffer.iter_at_offset(n.start_byte() as i32);
+                let end = buffer.iter_at_offset(n.end_byte() as i32);
+                buffer.text(&start, &end, true).to_string()
+            })
+            .unwrap_or_default();
+        Image {
+            left,
+            right,
+            dest,
+            picture: None,
+            space: TextTag::new(None),
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        let picture = match self.picture.as_ref() {
+            Some(p) => p,
+            None => return,
+        };
+        buffer.tag_table().add(&self.space);
+        let mut line_start = self.start(buffer);
+        line_start.set_line_offset(0);
+        let mut line_end = self.end(buffer);
+        if !line_end.ends_line() {
+            line_end.forward_to_line_end();
+        }
+        buffer.apply_tag(&self.space, &line_start, &line_end);
+        view.add_overlay(picture, 0, 0);
+    }
+
+    fn umount(&self, view: &TextView, buffer: &TextBuffer) {
+        if let Some(picture) = self.picture.as_ref() {
+            view.remove(picture);
+            buffer.tag_table().remove(&self.space);
+        }
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        if self.picture.is_some() {
+            buffer.remove_tag_by_name(
+                "hidden",
+                &self.start(buffer),
+                &self.end(buffer),
+            );
+        }
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        if self.picture.is_some() {
+            buffer.apply_tag_by_name(
+                "hidden",
+                &self.start(buffer),
+                &self.end(buffer),
+            );
+        }
+    }
+}
+
+impl Image {
+    /// Take the picture of the image in the note at `note` of repo
+    /// `repo_path` from `pictures`, left as source if the file is not found.
+    pub fn load(
+        &mut self,
+        repo_path: &str,
+        note: &Path,
+        pictures: &mut Pictures,
+    ) {
+        let texture = kasten::resolve_file(repo_path, note, &self.dest)
+            .and_then(|path| pictures.get(&path));
+        if let Some(texture) = texture {
+            let picture = Picture::for_paintable(&texture);
+            picture.set_can_shrink(true);
+            picture.set_keep_aspect_ratio(true);
+            self.picture = Some(picture);
+        }
+    }
+
+    /// Scale the picture to the width of `view`, never up, and put it below
+    /// the line of the image.
+    pub fn place(&self, view: &TextView, buffer: &TextBuffer) {
+        if let Some(picture) = self.picture.as_ref() {
+            place(view, buffer, &self.right, picture, &self.space);
+        }
+    }
+
+    pub fn show(&self, shown: bool) {
+        if let Some(picture) = self.picture.as_ref() {
+            picture.set_visible(shown);
+        }
+    }
+}
+
+fn place(
+    view: &TextView,
+    buffer: &TextBuffer,
+    mark: &TextMark,
+    picture: &Picture,
+    space: &TextTag,
+) {
+    let (w, h) = match picture.paintable() {
+        Some(p) if p.intrinsic_width() > 0 => {
+            (p.intrinsic_width(), p.intrinsic_height())
+        }
+        _ => return,
+    };
+    let max_width = view.width() - view.left_margin() - view.right_margin();
+    let width = w.min(max_width).max(1);
+    let height = h * width / w;
+    if picture.width_request() != width || picture.height_request() != height {
+        picture.set_size_request(width, height);
+    }
+    let below = height + 2 * PADDING;
+    if space.pixels_below_lines() != below {
+        space.set_pixels_below_lines(below);
+    }
+    // Space is kept below the last wrapped line of the paragraph.
+    let mut iter = buffer.iter_at_mark(mark);
+    if !iter.ends_line() {
+        iter.forward_to_line_end();
+    }
+    let (y, line_height) = view.line_yrange(&iter);
+    view.move_overlay(
+        picture,
+        view.left_margin(),
+        y + line_height - below + PADDING,
+    );
+}
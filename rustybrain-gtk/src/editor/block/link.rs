@@ -91,6 +91,25 @@ impl Blocking for Link {
     }
 }
 
+impl Link {
+    /// Where the link points to, the destination, or the text of a wiki link
+    /// like `[[Note]]`.
+    pub fn target(&self, buffer: &gtk::TextBuffer) -> Option<String> {
+        let text = match &self.dest {
+            Some(dest) => {
+                buffer.text(&dest.start(buffer), &dest.end(buffer), true)
+            }
+            None => buffer.text(&self.start(buffer), &self.end(buffer), true),
+        };
+        let target = text.trim_matches(|c| c == '[' || c == ']').trim();
+        if target.is_empty() {
+            None
+        } else {
+            Some(target.to_string())
+        }
+    }
+}
+
 impl Blocking for LinkText {
     fn from_node(
         node: &rustybrain_core::md::Node,
@@ -0,0 +1,284 @@
+use std::cell::Cell;
+
+use gtk::prelude::*;
+use gtk::CheckButton;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+use rustybrain_core::task;
+
+use super::Blocking;
+
+/// Item of an ordered, unordered or task list.
+pub struct ListItem {
+    left: TextMark,
+    right: TextMark,
+
+    marker: Option<ListMarker>,
+    task: Option<TaskMarker>,
+}
+
+impl Blocking for ListItem {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        let marker = Self::node_child_by_kind(node, "list_marker")
+            .map(|n| ListMarker::from_node(&n, buffer));
+        let task = Self::node_child_by_kind(node, "paragraph").and_then(|p| {
+            let n = Self::node_child_by_kind(&p, "task_list_item_marker")?;
+            let mut task = TaskMarker::from_node(&n, buffer);
+            task.set_paragraph(&p, buffer);
+            Some(task)
+        });
+        ListItem {
+            left,
+            right,
+            marker,
+            task,
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        if let Some(marker) = self.marker.as_ref() {
+            marker.mount(view, buffer);
+        }
+        if let Some(task) = self.task.as_ref() {
+            task.mount(view, buffer);
+        }
+    }
+
+    fn umount(&self, view: &TextView, buffer: &TextBuffer) {
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+        if let Some(marker) = self.marker.as_ref() {
+            marker.umount(view, buffer);
+        }
+        if let Some(task) = self.task.as_ref() {
+            task.umount(view, buffer);
+        }
+    }
+
+    // The bullet of a task item is hidden behind its checkbox, other items
+    // keep their bullet or number.
+    fn cursor_in(&self, view: &TextView, buffer: &TextBuffer) {
+        if let (Some(marker), Some(task)) = (&self.marker, &self.task) {
+            marker.cursor_in(view, buffer);
+            task.cursor_in(view, buffer);
+        }
+    }
+
+    fn cursor_out(&self, view: &TextView, buffer: &TextBuffer) {
+        if let (Some(marker), Some(task)) = (&self.marker, &self.task) {
+            marker.cursor_out(view, buffer);
+            task.cursor_out(view, buffer);
+        }
+    }
+}
+
+impl ListItem {
+    pub fn place(&self, view: &TextView, buffer: &TextBuffer) {
+        if let Some(task) = self.task.as_ref() {
+            task.place(view, buffer);
+        }
+    }
+
+    pub fn show(&self, shown: bool) {
+        if let Some(task) = self.task.as_ref() {
+            task.shown.set(shown);
+            task.sync_check();
+        }
+    }
+}
+
+/// Bullet or number of a list item, like `-` or `1.`.
+struct ListMarker {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for ListMarker {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        ListMarker { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "list-marker",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        let (start, end) = self.with_space(buffer);
+        buffer.remove_tag_by_name("hidden", &start, &end);
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        let (start, end) = self.with_space(buffer);
+        buffer.apply_tag_by_name("hidden", &start, &end);
+    }
+}
+
+impl ListMarker {
+    fn with_space(&self, buffer: &TextBuffer) -> (TextIter, TextIter) {
+        let mut end = self.end(buffer);
+        end.forward_char();
+        (self.start(buffer), end)
+    }
+}
+
+/// Checkbox of a task list item, `[ ]` or `[x]`. A check button floats
+/// over the source while the cursor is away, ticking it edits the source.
+struct TaskMarker {
+    left: TextMark,
+    right: TextMark,
+    /// End of the paragraph of the task.
+    end: Option<TextMark>,
+    checked: bool,
+
+    check: CheckButton,
+    /// The buffer is the one shown in the view.
+    shown: Cell<bool>,
+    /// The cursor is in the item, its source is shown instead.
+    editing: Cell<bool>,
+}
+
+impl Blocking for TaskMarker {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        let (start, end) =
+            (buffer.iter_at_mark(&left), buffer.iter_at_mark(&right));
+        let checked = buffer.text(&start, &end, true).contains(['x', 'X']);
+        let check = CheckButton::builder()
+            .active(checked)
+            .focusable(false)
+            .build();
+        TaskMarker {
+            left,
+            right,
+            end: None,
+            checked,
+            check,
+            shown: Cell::new(true),
+            editing: Cell::new(false),
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        let marker_end = self.end(buffer);
+        buffer.apply_tag_by_name(
+            "task-marker",
+            &self.start(buffer),
+            &marker_end,
+        );
+        buffer.apply_tag_by_name("task-box", &self.start(buffer), &marker_end);
+        view.add_overlay(&self.check, 0, 0);
+        let (buf, left, right) =
+            (buffer.clone(), self.left.clone(), self.right.clone());
+        self.check.connect_toggled(move |_| {
+            let mut start = buf.iter_at_mark(&left);
+            toggle_marker(&buf, &mut start, &mut buf.iter_at_mark(&right));
+        });
+        if let (true, Some(end)) = (self.checked, self.end.as_ref()) {
+            buffer.apply_tag_by_name(
+                "task-done",
+                &marker_end,
+                &buffer.iter_at_mark(end),
+            );
+        }
+    }
+
+    fn umount(&self, view: &TextView, buffer: &TextBuffer) {
+        view.remove(&self.check);
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+        if let Some(end) = self.end.as_ref() {
+            buffer.delete_mark(end);
+        }
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.remove_tag_by_name(
+            "task-box",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+        self.editing.set(true);
+        self.sync_check();
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "task-box",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+        self.editing.set(false);
+        self.sync_check();
+    }
+}
+
+impl TaskMarker {
+    /// Put the check button over the source, centered on its line.
+    fn place(&self, view: &TextView, buffer: &TextBuffer) {
+        let rect = view.iter_location(&self.start(buffer));
+        let (height, ..) = self.check.measure(gtk::Orientation::Vertical, -1);
+        view.move_overlay(
+            &self.check,
+            rect.x(),
+            rect.y() + (rect.height() - height) / 2,
+        );
+    }
+
+    fn sync_check(&self) {
+        self.check
+            .set_visible(self.shown.get() && !self.editing.get());
+    }
+
+    fn set_paragraph(&mut self, node: &Node, buffer: &TextBuffer) {
+        let end = TextMark::builder().left_gravity(false).build();
+        buffer.add_mark(&end, &buffer.iter_at_offset(node.end_byte() as i32));
+        self.end = Some(end);
+    }
+}
+
+/// Tick or untick the checkbox of the source between `start` and `end`, as
+/// one undoable edit.
+fn toggle_marker(
+    buffer: &TextBuffer,
+    start: &mut TextIter,
+    end: &mut TextIter,
+) {
+    let marker = task::toggle_marker(&buffer.text(start, end, true));
+    buffer.begin_user_action();
+    buffer.delete(start, end);
+    buffer.insert(start, &marker);
+    buffer.end_user_action();
+}
@@ -1,8 +1,17 @@
 mod anonymous;
+mod code;
 mod codeblock;
 mod emphasis;
 mod headline;
+mod html;
+mod image;
 mod link;
+mod list;
+mod quote;
+mod rule;
+mod table;
+
+use std::path::Path;
 
 use gtk::prelude::*;
 use gtk::TextBuffer;
@@ -14,10 +23,19 @@ use rustybrain_core::md::Node;
 use anonymous::Anonymous;
 use headline::Headline;
 
+use self::code::CodeSpan;
 use self::codeblock::Codeblock;
 use self::emphasis::Emphasis;
+use self::emphasis::Strikethrough;
 use self::emphasis::StrongEmphasis;
+use self::html::HtmlBlock;
+use self::image::Image;
+pub use self::image::Pictures;
 use self::link::Link;
+use self::list::ListItem;
+use self::quote::Blockquote;
+use self::rule::ThematicBreak;
+use self::table::Table;
 
 pub trait Blocking {
     fn node_endpoint(node: &Node, buffer: &TextBuffer) -> (TextMark, TextMark) {
@@ -155,6 +173,14 @@ pub enum Block {
     Link(Link),
     Emphasis(Emphasis),
     StrongEmphasis(StrongEmphasis),
+    CodeSpan(CodeSpan),
+    Strikethrough(Strikethrough),
+    Blockquote(Blockquote),
+    ListItem(ListItem),
+    ThematicBreak(ThematicBreak),
+    Table(Table),
+    HtmlBlock(HtmlBlock),
+    Image(Image),
     Anonymous(Anonymous),
 }
 
@@ -169,6 +195,15 @@ impl Blocking for Block {
                 }
             }
         }
+        for n in 1..3 {
+            if node.kind() == format!("setext_h{}_underline", n) {
+                if let Some(p) = node.parent().as_ref() {
+                    let mut headline = Headline::from_node(p, buffer);
+                    headline.set_number(n, node, buffer);
+                    return Self::Headline(headline);
+                }
+            }
+        }
         if node.kind() == "fenced_code_block" {
             return Self::Codeblock(Codeblock::from_node(node, buffer));
         }
@@ -183,6 +218,30 @@ impl Blocking for Block {
                 node, buffer,
             ));
         }
+        if node.kind() == "code_span" {
+            return Self::CodeSpan(CodeSpan::from_node(node, buffer));
+        }
+        if node.kind() == "strikethrough" {
+            return Self::Strikethrough(Strikethrough::from_node(node, buffer));
+        }
+        if node.kind() == "block_quote" {
+            return Self::Blockquote(Blockquote::from_node(node, buffer));
+        }
+        if node.kind() == "list_item" || node.kind() == "task_list_item" {
+            return Self::ListItem(ListItem::from_node(node, buffer));
+        }
+        if node.kind() == "thematic_break" {
+            return Self::ThematicBreak(ThematicBreak::from_node(node, buffer));
+        }
+        if node.kind() == "table" {
+            return Self::Table(Table::from_node(node, buffer));
+        }
+        if node.kind() == "html_block" {
+            return Self::HtmlBlock(HtmlBlock::from_node(node, buffer));
+        }
+        if node.kind() == "image" {
+            return Self::Image(Image::from_node(node, buffer));
+        }
 
         Self::Anonymous(Anonymous::from_node(node, buffer))
     }
@@ -195,6 +254,14 @@ impl Blocking for Block {
             Block::Link(l) => l.start(buffer),
             Block::Emphasis(e) => e.start(buffer),
             Block::StrongEmphasis(s) => s.start(buffer),
+            Block::CodeSpan(c) => c.start(buffer),
+            Block::Strikethrough(s) => s.start(buffer),
+            Block::Blockquote(q) => q.start(buffer),
+            Block::ListItem(i) => i.start(buffer),
+            Block::ThematicBreak(r) => r.start(buffer),
+            Block::Table(t) => t.start(buffer),
+            Block::HtmlBlock(h) => h.start(buffer),
+            Block::Image(i) => i.start(buffer),
         }
     }
 
@@ -206,6 +273,14 @@ impl Blocking for Block {
             Block::Link(l) => l.end(buffer),
             Block::Emphasis(e) => e.end(buffer),
             Block::StrongEmphasis(s) => s.end(buffer),
+            Block::CodeSpan(c) => c.end(buffer),
+            Block::Strikethrough(s) => s.end(buffer),
+            Block::Blockquote(q) => q.end(buffer),
+            Block::ListItem(i) => i.end(buffer),
+            Block::ThematicBreak(r) => r.end(buffer),
+            Block::Table(t) => t.end(buffer),
+            Block::HtmlBlock(h) => h.end(buffer),
+            Block::Image(i) => i.end(buffer),
         }
     }
 
@@ -217,6 +292,14 @@ impl Blocking for Block {
             Block::Link(l) => l.left(),
             Block::Emphasis(e) => e.left(),
             Block::StrongEmphasis(s) => s.left(),
+            Block::CodeSpan(c) => c.left(),
+            Block::Strikethrough(s) => s.left(),
+            Block::Blockquote(q) => q.left(),
+            Block::ListItem(i) => i.left(),
+            Block::ThematicBreak(r) => r.left(),
+            Block::Table(t) => t.left(),
+            Block::HtmlBlock(h) => h.left(),
+            Block::Image(i) => i.left(),
         }
     }
 
@@ -228,6 +311,14 @@ impl Blocking for Block {
             Block::Link(l) => l.right(),
             Block::Emphasis(e) => e.right(),
             Block::StrongEmphasis(s) => s.right(),
+            Block::CodeSpan(c) => c.right(),
+            Block::Strikethrough(s) => s.right(),
+            Block::Blockquote(q) => q.right(),
+            Block::ListItem(i) => i.right(),
+            Block::ThematicBreak(r) => r.right(),
+            Block::Table(t) => t.right(),
+            Block::HtmlBlock(h) => h.right(),
+            Block::Image(i) => i.right(),
         }
     }
 
@@ -239,6 +330,14 @@ impl Blocking for Block {
             Block::Link(l) => l.mount(view, buffer),
             Block::Emphasis(e) => e.mount(view, buffer),
             Block::StrongEmphasis(s) => s.mount(view, buffer),
+            Block::CodeSpan(c) => c.mount(view, buffer),
+            Block::Strikethrough(s) => s.mount(view, buffer),
+            Block::Blockquote(q) => q.mount(view, buffer),
+            Block::ListItem(i) => i.mount(view, buffer),
+            Block::ThematicBreak(r) => r.mount(view, buffer),
+            Block::Table(t) => t.mount(view, buffer),
+            Block::HtmlBlock(h) => h.mount(view, buffer),
+            Block::Image(i) => i.mount(view, buffer),
         }
     }
 
@@ -250,6 +349,14 @@ impl Blocking for Block {
             Block::Link(l) => l.umount(view, buffer),
             Block::Emphasis(e) => e.umount(view, buffer),
             Block::StrongEmphasis(s) => s.umount(view, buffer),
+            Block::CodeSpan(c) => c.umount(view, buffer),
+            Block::Strikethrough(s) => s.umount(view, buffer),
+            Block::Blockquote(q) => q.umount(view, buffer),
+            Block::ListItem(i) => i.umount(view, buffer),
+            Block::ThematicBreak(r) => r.umount(view, buffer),
+            Block::Table(t) => t.umount(view, buffer),
+            Block::HtmlBlock(h) => h.umount(view, buffer),
+            Block::Image(i) => i.umount(view, buffer),
         }
     }
 
@@ -261,6 +368,14 @@ impl Blocking for Block {
             Block::Link(l) => l.cursor_in(view, buffer),
             Block::Emphasis(e) => e.cursor_in(view, buffer),
             Block::StrongEmphasis(s) => s.cursor_in(view, buffer),
+            Block::CodeSpan(c) => c.cursor_in(view, buffer),
+            Block::Strikethrough(s) => s.cursor_in(view, buffer),
+            Block::Blockquote(q) => q.cursor_in(view, buffer),
+            Block::ListItem(i) => i.cursor_in(view, buffer),
+            Block::ThematicBreak(r) => r.cursor_in(view, buffer),
+            Block::Table(t) => t.cursor_in(view, buffer),
+            Block::HtmlBlock(h) => h.cursor_in(view, buffer),
+            Block::Image(i) => i.cursor_in(view, buffer),
         }
     }
 
@@ -272,6 +387,14 @@ impl Blocking for Block {
             Block::Link(l) => l.cursor_out(view, buffer),
             Block::Emphasis(e) => e.cursor_out(view, buffer),
             Block::StrongEmphasis(s) => s.cursor_out(view, buffer),
+            Block::CodeSpan(c) => c.cursor_out(view, buffer),
+            Block::Strikethrough(s) => s.cursor_out(view, buffer),
+            Block::Blockquote(q) => q.cursor_out(view, buffer),
+            Block::ListItem(i) => i.cursor_out(view, buffer),
+            Block::ThematicBreak(r) => r.cursor_out(view, buffer),
+            Block::Table(t) => t.cursor_out(view, buffer),
+            Block::HtmlBlock(h) => h.cursor_out(view, buffer),
+            Block::Image(i) => i.cursor_out(view, buffer),
         }
     }
 }
@@ -280,4 +403,45 @@ impl Block {
     pub fn is_anonymous(&self) -> bool {
         matches!(self, Block::Anonymous(_))
     }
+
+    /// Load the picture of the block if it is an image, in the note at
+    /// `note` of repo `repo_path`.
+    pub fn load_image(
+        &mut self,
+        repo_path: &str,
+        note: &Path,
+        pictures: &mut Pictures,
+    ) {
+        if let Block::Image(i) = self {
+            i.load(repo_path, note, pictures);
+        }
+    }
+
+    /// Put the widget floating over the view for the block in place, the
+    /// picture of an image or the checkbox of a task.
+    pub fn place(&self, view: &TextView, buffer: &TextBuffer) {
+        match self {
+            Block::Image(i) => i.place(view, buffer),
+            Block::ListItem(i) => i.place(view, buffer),
+            _ => {}
+        }
+    }
+
+    /// Show or hide the widget floating over the view for the block, hidden
+    /// while another buffer is shown in the view.
+    pub fn show(&self, shown: bool) {
+        match self {
+            Block::Image(i) => i.show(shown),
+            Block::ListItem(i) => i.show(shown),
+            _ => {}
+        }
+    }
+
+    /// Target of the block if it is a link.
+    pub fn link_target(&self, buffer: &TextBuffer) -> Option<String> {
+        match self {
+            Block::Link(l) => l.target(buffer),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,81 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+
+use super::Blocking;
+
+/// Block quote, its lines start with `>`.
+pub struct Blockquote {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for Blockquote {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        Blockquote { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "blockquote",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        for (start, end) in self.markers(buffer) {
+            buffer.remove_tag_by_name("hidden", &start, &end);
+        }
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        for (start, end) in self.markers(buffer) {
+            buffer.apply_tag_by_name("hidden", &start, &end);
+        }
+    }
+}
+
+impl Blockquote {
+    /// The `>` markers at the start of each line, with a space after them,
+    /// nested quotes included.
+    fn markers(&self, buffer: &TextBuffer) -> Vec<(TextIter, TextIter)> {
+        let end = self.end(buffer);
+        let mut line = self.start(buffer);
+        line.set_line_offset(0);
+        let mut markers = vec![];
+        while line.offset() < end.offset() {
+            let mut marker_end = line;
+            // Lazy lines of a paragraph have no marker to hide, only
+            // indentation.
+            let mut quoted = false;
+            while !marker_end.ends_line() {
+                match marker_end.char() {
+                    '>' => quoted = true,
+                    ' ' => {}
+                    _ => break,
+                }
+                marker_end.forward_char();
+            }
+            if quoted {
+                markers.push((line, marker_end));
+            }
+            if !line.forward_line() {
+                break;
+            }
+        }
+        markers
+    }
+}
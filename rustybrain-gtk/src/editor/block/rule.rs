@@ -0,0 +1,48 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+
+use super::Blocking;
+
+/// Thematic break like `---`, drawn as a line while the cursor is away.
+pub struct ThematicBreak {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for ThematicBreak {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        ThematicBreak { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        self.cursor_out(view, buffer);
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.remove_tag_by_name(
+            "thematic-break",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "thematic-break",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+
+use super::Blocking;
+
+/// Pipe table, its delimiter row is hidden while the cursor is away.
+pub struct Table {
+    left: TextMark,
+    right: TextMark,
+
+    header: Option<Row>,
+    delimiter: Option<Row>,
+}
+
+impl Blocking for Table {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        let header = Self::node_child_by_kind(node, "table_header_row")
+            .map(|n| Row::from_node(&n, buffer));
+        let delimiter = Self::node_child_by_kind(node, "table_delimiter_row")
+            .map(|n| Row::from_node(&n, buffer));
+        Table {
+            left,
+            right,
+            header,
+            delimiter,
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "table",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+        if let Some(header) = self.header.as_ref() {
+            buffer.apply_tag_by_name(
+                "table-header",
+                &header.start(buffer),
+                &header.end(buffer),
+            );
+        }
+    }
+
+    fn umount(&self, view: &TextView, buffer: &TextBuffer) {
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+        if let Some(header) = self.header.as_ref() {
+            header.umount(view, buffer);
+        }
+        if let Some(delimiter) = self.delimiter.as_ref() {
+            delimiter.umount(view, buffer);
+        }
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        if let Some((start, end)) = self.delimiter_line(buffer) {
+            buffer.remove_tag_by_name("hidden", &start, &end);
+        }
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        if let Some((start, end)) = self.delimiter_line(buffer) {
+            buffer.apply_tag_by_name("hidden", &start, &end);
+        }
+    }
+}
+
+impl Table {
+    /// The delimiter row with the line break before it.
+    fn delimiter_line(
+        &self,
+        buffer: &TextBuffer,
+    ) -> Option<(TextIter, TextIter)> {
+        let delimiter = self.delimiter.as_ref()?;
+        let mut start = delimiter.start(buffer);
+        start.backward_char();
+        Some((start, delimiter.end(buffer)))
+    }
+}
+
+struct Row {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for Row {
+    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer);
+        Row { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, _buffer: &TextBuffer) {}
+}
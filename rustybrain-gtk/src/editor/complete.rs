@@ -0,0 +1,203 @@
+use std::cell::RefCell;
+
+use gtk::{prelude::*, TextView};
+use relm4::send;
+use rustybrain_core::kasten;
+
+use super::Msg;
+
+/// Most zettels offered for a link being typed.
+pub const LIMIT: usize = 10;
+
+/// How a link being typed was opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opener {
+    /// `[[`, completed with the title, like `[[Title]]`.
+    Wiki,
+    /// `](`, completed with the id, like `[text](zid)`.
+    Inline,
+}
+
+/// Link being typed right before the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Typing {
+    pub opener: Opener,
+    /// Char offset of the text after the opener.
+    pub start: i32,
+    /// Text typed after the opener.
+    pub text: String,
+}
+
+/// Choice of the popover.
+pub enum Item {
+    Zettel {
+        zid: String,
+        title: String,
+    },
+    /// Create a zettel titled with the text typed.
+    Create(String),
+}
+
+impl Item {
+    /// Text completing the link, closing it.
+    pub fn completion(&self, opener: Opener) -> String {
+        let (zid, title) = match self {
+            Item::Zettel { zid, title } => (zid.as_str(), title.as_str()),
+            Item::Create(title) => ("", title.as_str()),
+        };
+        match opener {
+            Opener::Wiki => format!("{}]]", title),
+            Opener::Inline => format!("{})", zid),
+        }
+    }
+}
+
+/// Link being typed at the cursor of `buffer`, on the line of the cursor,
+/// not closed yet. None for a URL, which no zettel completes.
+pub fn typing_at(buffer: &gtk::TextBuffer) -> Option<Typing> {
+    let cursor = buffer.iter_at_offset(buffer.cursor_position());
+    let mut line_start = cursor;
+    line_start.set_line_offset(0);
+    let before = buffer.text(&line_start, &cursor, true);
+    let (opener, at) = match (before.rfind("[["), before.rfind("](")) {
+        (Some(w), Some(i)) if i > w => (Opener::Inline, i),
+        (Some(w), _) => (Opener::Wiki, w),
+        (None, Some(i)) => (Opener::Inline, i),
+        (None, None) => return None,
+    };
+    // Both openers are two bytes.
+    let text = &before[at + 2..];
+    if text.contains(['[', ']', '(', ')']) || kasten::is_external(text) {
+        return None;
+    }
+    Some(Typing {
+        opener,
+        start: cursor.offset() - text.chars().count() as i32,
+        text: text.to_string(),
+    })
+}
+
+/// Popover under the cursor of a view, offering zettels for the link being
+/// typed.
+pub struct Completion {
+    popover: gtk::Popover,
+    list: gtk::ListBox,
+    typing: RefCell<Option<Typing>>,
+    items: RefCell<Vec<Item>>,
+}
+
+impl Completion {
+    pub fn new(view: &TextView, sender: relm4::Sender<Msg>) -> Self {
+        let list = gtk::ListBox::builder().build();
+        let v = view.clone();
+        list.connect_row_activated(move |_, row| {
+            send!(sender, Msg::Complete(v.clone(), row.index() as usize))
+        });
+        let window = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(300)
+            .child(&list)
+            .build();
+        // Typing goes on in the view while it's shown.
+        let popover = gtk::Popover::builder()
+            .autohide(false)
+            .can_focus(false)
+            .has_arrow(false)
+            .position(gtk::PositionType::Bottom)
+            .child(&window)
+            .build();
+        popover.set_parent(view);
+        Completion {
+            popover,
+            list,
+            typing: RefCell::new(None),
+            items: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.popover.is_visible()
+    }
+
+    /// Offer `items` for `typing`, under the cursor of `view`.
+    pub fn show(&self, view: &TextView, typing: Typing, items: Vec<Item>) {
+        while let Some(c) = self.list.last_child() {
+            self.list.remove(&c);
+        }
+        for item in items.iter() {
+            let text = match item {
+                Item::Zettel { title, .. } => title.clone(),
+                Item::Create(title) => format!("Create note “{}”", title),
+            };
+            let label = gtk::Label::builder()
+                .label(&text)
+                .halign(gtk::Align::Start)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .max_width_chars(40)
+                .build();
+            self.list
+                .append(&gtk::ListBoxRow::builder().child(&label).build());
+        }
+        self.list.select_row(self.list.row_at_index(0).as_ref());
+
+        let buffer = view.buffer();
+        let cursor = buffer.iter_at_offset(buffer.cursor_position());
+        let rect = view.iter_location(&cursor);
+        let (x, y) = view.buffer_to_window_coords(
+            gtk::TextWindowType::Widget,
+            rect.x(),
+            rect.y(),
+        );
+        self.popover.set_pointing_to(Some(&gdk::Rectangle::new(
+            x,
+            y,
+            1,
+            rect.height(),
+        )));
+        *self.typing.borrow_mut() = Some(typing);
+        *self.items.borrow_mut() = items;
+        self.popover.popup();
+    }
+
+    pub fn hide(&self) {
+        self.typing.borrow_mut().take();
+        self.items.borrow_mut().clear();
+        if self.is_shown() {
+            self.popover.popdown();
+        }
+    }
+
+    /// Select the row `delta` rows away from the selected one.
+    pub fn step(&self, delta: i32) {
+        let n = self.items.borrow().len() as i32;
+        if n == 0 {
+            return;
+        }
+        let i = self.selected().map_or(0, |i| i as i32);
+        let row = self.list.row_at_index((i + delta).rem_euclid(n));
+        self.list.select_row(row.as_ref());
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.list.selected_row().map(|r| r.index() as usize)
+    }
+
+    /// Link being typed and the n-th item, and hide.
+    pub fn take(&self, i: usize) -> Option<(Typing, Item)> {
+        let typing = self.typing.borrow_mut().take();
+        let mut items = self.items.borrow_mut();
+        let r = match typing {
+            Some(typing) if i < items.len() => Some((typing, items.remove(i))),
+            _ => None,
+        };
+        drop(items);
+        self.hide();
+        r
+    }
+
+    /// Detach from the view, when its tab is closed.
+    pub fn unparent(&self) {
+        self.popover.unparent();
+    }
+}
@@ -1,28 +1,187 @@
 mod block;
+mod complete;
 mod style;
+mod tab;
 
 use std::cell::RefCell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Duration;
 
 use gtk::{
-    prelude::*, ActionBar, EventControllerFocus, MessageType, ScrolledWindow,
+    glib, prelude::*, ActionBar, EventControllerFocus, MessageType, TextMark,
     TextTagTable, TextView,
 };
 use relm4::{send, ComponentUpdate, Components, Widgets};
-use rustybrain_core::kasten::Kasten;
+use rustybrain_core::config::Config;
+use rustybrain_core::find::{Finder, Query};
+use rustybrain_core::journal::Swap;
+use rustybrain_core::kasten::{self, Kasten, Mention, Saved};
 use rustybrain_core::zettel::Zettel;
+use rustybrain_core::Error;
 
 use self::block::Blocking;
+use crate::outline::Outline;
 
 pub enum Msg {
     Open(Rc<RefCell<Zettel>>),
     Insert(Rc<RefCell<Zettel>>),
     OpenOnStack(Rc<RefCell<Zettel>>),
-    Changed,
+    /// Open in a new tab of the focused pane.
+    OpenInTab(Rc<RefCell<Zettel>>),
+    /// Add an empty pane next to the others, and focus it.
+    Split,
+    /// Save and close the tab of the view, or the focused tab.
+    CloseTab(Option<TextView>),
+    /// Tab of the view gets focus.
+    Focus(TextView),
+    /// Open zettels in panes of tabs, as the layout of last session.
+    Restore(Vec<Vec<Rc<RefCell<Zettel>>>>),
+    /// Open the zettel with edits left by a crashed session.
+    Recover(Rc<RefCell<Zettel>>, Swap),
+    Changed(gtk::TextBuffer),
+    /// No edit since the n-th one of the buffer for a while, journal it.
+    Settled(gtk::TextBuffer, u64),
+    /// No edit since the n-th one of the buffer for the autosave delay.
+    Autosave(gtk::TextBuffer, u64),
     Save,
-    Cursor,
+    /// Save the zettel of the buffer.
+    SaveBuffer(gtk::TextBuffer),
+    /// Replace the zettel with the text merged from a conflict, and save.
+    Merged(Rc<RefCell<Zettel>>, String),
+    /// The conflict stopping a close or an open is left unresolved, the
+    /// action is dropped.
+    Unresolved,
+    /// Save and close all zettels, then tell parent they are closed.
+    Close,
+    /// Save all zettels, then let parent quit.
+    SaveAndQuit,
+    /// Forget unsaved edits of the open zettels, then let parent quit.
+    DiscardAndQuit,
+    /// Config changed, apply fonts and colors again.
+    Restyle,
+    Cursor(gtk::TextBuffer),
     EditTitle,
     DoneEditTitle,
+    /// Follow a link, to a zettel or outside.
+    ActivateLink(String),
+    /// Attach copies of the files to the zettel of the view, linked at the
+    /// cursor.
+    Attach(TextView, Vec<PathBuf>),
+    /// Attach a pasted picture to the zettel of the view.
+    AttachPicture(TextView, gdk::Texture),
+    /// Put the cursor at the start of the line of the focused zettel, from 0.
+    GotoLine(i32),
+    /// Fold or unfold the section of the heading at the line of the focused
+    /// zettel, or of the heading above the cursor.
+    ToggleFold(Option<i32>),
+    /// Show the byte range of the focused zettel, e.g. the section a link
+    /// points to.
+    Highlight(Range<usize>),
+    /// Rewrite the mention into a link to the zettel of the id, in its
+    /// buffer if the zettel is open.
+    LinkMention(Rc<Mention>, String),
+    /// Complete the link being typed in the view with the n-th zettel
+    /// offered.
+    Complete(TextView, usize),
+    /// Move the selection of the focused zettel into a new zettel, linked
+    /// in its place.
+    Extract,
+    /// Merge the zettel into the focused one, and delete it.
+    MergeIn(Rc<RefCell<Zettel>>),
+    /// Move the focused zettel to the file of the id.
+    Move(String),
+    /// Show the find bar.
+    StartFind,
+    /// Highlight the matches of the query in the focused zettel.
+    Find(Query),
+    /// Select the next match after the cursor, or the one before it.
+    FindStep(bool),
+    CloseFind,
+    /// Zettels of the ids are changed on disk, show them again unless they
+    /// are being edited.
+    Reload(Vec<String>),
+    /// Lines of the view are laid out again, e.g. it's resized.
+    Relayout(TextView),
+    /// Tell parent the files open zettels link to, unsaved edits included,
+    /// to keep them when deleting unused attachments.
+    LinkedFiles,
+}
+
+/// Link in the buffer, by char offsets.
+pub struct LinkSpan {
+    start: i32,
+    end: i32,
+    target: String,
+}
+
+/// Links of the buffer on top of a tab, shared with event handlers of its
+/// view.
+type Links = Rc<RefCell<Vec<LinkSpan>>>;
+
+/// How long the target of a followed link stays highlighted.
+const HIGHLIGHT_TIME: Duration = Duration::from_secs(2);
+
+/// Delay after the last edit to write the buffer to the journal.
+const JOURNAL_DELAY: Duration = Duration::from_secs(1);
+
+/// Matches of `finder` in `content`, by char offsets.
+fn char_ranges(content: &str, finder: &Finder) -> Vec<Range<i32>> {
+    let (mut chars, mut last) = (0, 0);
+    finder
+        .find(content)
+        .into_iter()
+        .map(|r| {
+            chars += content[last..r.start].chars().count() as i32;
+            let start = chars;
+            chars += content[r.clone()].chars().count() as i32;
+            last = r.end;
+            start..chars
+        })
+        .collect()
+}
+
+fn link_at(links: &Links, offset: i32) -> Option<String> {
+    links
+        .borrow()
+        .iter()
+        .find(|l| l.start <= offset && offset < l.end)
+        .map(|l| l.target.clone())
+}
+
+/// Tell the user no version of a saved zettel is kept, apart from the save
+/// that went fine.
+fn report_version(saved: Saved, parent_sender: &relm4::Sender<super::Msg>) {
+    if let Some(e) = saved.version {
+        send!(
+            parent_sender,
+            super::Msg::ShowError(
+                "Note is saved, but keeping a version of it failed!"
+                    .to_string(),
+                Rc::new(e)
+            )
+        );
+    }
+}
+
+// Widgets of the blocks float over the view, which outlives the zettel.
+impl Drop for EditingZettel {
+    fn drop(&mut self) {
+        while let Some(blk) = self.blocks.pop() {
+            blk.umount(&self.view, &self.buffer);
+        }
+    }
+}
+
+/// How saving a zettel went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Saving {
+    Unmodified,
+    Saved,
+    Failed,
+    /// Refused for changes on disk, it stays open until they are merged.
+    Conflict,
 }
 
 /// Zettel that be editing.
@@ -33,14 +192,26 @@ pub struct EditingZettel {
 
     view: TextView,
     blocks: Vec<block::Block>,
+    /// Starts of the headings with their sections folded.
+    folds: Vec<TextMark>,
+    /// Repo of the zettel, where the files it links to are looked up.
+    repo_path: String,
+    /// Count of edits, to tell if a delayed action is still current.
+    edits: u64,
+    /// Pictures of the images in the note, kept across edits.
+    pictures: block::Pictures,
 
     #[allow(dead_code)]
     table: TextTagTable,
 }
 
 impl EditingZettel {
-    fn new(zettel: Rc<RefCell<Zettel>>, view: TextView) -> Self {
-        let table = style::Style::new().table();
+    fn new(
+        zettel: Rc<RefCell<Zettel>>,
+        view: TextView,
+        table: TextTagTable,
+        repo_path: String,
+    ) -> Self {
         let buffer = gtk::TextBuffer::builder()
             .enable_undo(true)
             .tag_table(&table)
@@ -58,6 +229,10 @@ impl EditingZettel {
             view,
 
             blocks: vec![],
+            folds: vec![],
+            repo_path,
+            edits: 0,
+            pictures: block::Pictures::default(),
         };
         r.on_buffer_changed();
         r
@@ -66,10 +241,11 @@ impl EditingZettel {
     fn listen_buffer_event(&self, sender: relm4::Sender<Msg>) {
         let s = sender.clone();
 
-        self.buffer.connect_changed(move |_| send!(s, Msg::Changed));
+        self.buffer
+            .connect_changed(move |b| send!(s, Msg::Changed(b.clone())));
 
-        self.buffer.connect_cursor_position_notify(move |_| {
-            send!(sender, Msg::Cursor)
+        self.buffer.connect_cursor_position_notify(move |b| {
+            send!(sender, Msg::Cursor(b.clone()))
         });
     }
 
@@ -89,12 +265,133 @@ impl EditingZettel {
             return;
         };
 
-        let zettel = &self.zettel.borrow();
-        let iter = zettel.walk_iter();
-        for node in iter {
-            let blk = block::Block::from_node(&node, &self.buffer);
-            blk.mount(&self.view, &self.buffer);
-            self.blocks.push(blk);
+        {
+            let zettel = &self.zettel.borrow();
+            for node in zettel.walk_iter() {
+                let mut blk = block::Block::from_node(&node, &self.buffer);
+                blk.load_image(
+                    &self.repo_path,
+                    zettel.path(),
+                    &mut self.pictures,
+                );
+                blk.mount(&self.view, &self.buffer);
+                self.blocks.push(blk);
+            }
+        }
+        self.apply_folds();
+        self.place_overlays();
+    }
+
+    /// Put the widgets floating over the view in place, once the buffer or
+    /// the size of the view changes.
+    fn place_overlays(&self) {
+        for blk in &self.blocks {
+            blk.place(&self.view, &self.buffer);
+        }
+    }
+
+    /// Show the widgets floating over the view only while the buffer is the
+    /// one in it.
+    fn show_overlays(&self, shown: bool) {
+        for blk in &self.blocks {
+            blk.show(shown);
+        }
+    }
+
+    /// Hide the sections of folded headings, below the headings, and forget
+    /// the folds of headings that are gone.
+    fn apply_folds(&mut self) {
+        let zettel = self.zettel.borrow();
+        let (content, headings) = (zettel.content(), zettel.headings());
+        let buffer = &self.buffer;
+        self.folds.retain(|mark| {
+            let line = buffer.iter_at_mark(mark).line() as usize;
+            let heading = match headings.iter().find(|h| h.line == line) {
+                Some(h) => h,
+                None => {
+                    buffer.delete_mark(mark);
+                    return false;
+                }
+            };
+            let offset = |byte: usize| content[..byte].chars().count() as i32;
+            let mut start = buffer.iter_at_offset(offset(heading.range.end));
+            if !start.starts_line() {
+                start.forward_line();
+            }
+            let end = buffer.iter_at_offset(offset(heading.section.end));
+            if start < end {
+                buffer.apply_tag_by_name("hidden", &start, &end);
+            }
+            true
+        });
+    }
+
+    /// Lines of the folded headings.
+    fn folded_lines(&self) -> Vec<usize> {
+        let mut lines: Vec<usize> = self
+            .folds
+            .iter()
+            .map(|m| self.buffer.iter_at_mark(m).line() as usize)
+            .collect();
+        lines.sort_unstable();
+        lines
+    }
+
+    /// Fold the section of the heading at `line`, or unfold it if it is.
+    fn toggle_fold(&mut self, line: usize) {
+        let buffer = &self.buffer;
+        match self
+            .folds
+            .iter()
+            .position(|m| buffer.iter_at_mark(m).line() as usize == line)
+        {
+            Some(i) => buffer.delete_mark(&self.folds.remove(i)),
+            None => {
+                let is_heading = self
+                    .zettel
+                    .borrow()
+                    .headings()
+                    .iter()
+                    .any(|h| h.line == line);
+                let start = match buffer.iter_at_line(line as i32) {
+                    Some(start) if is_heading => start,
+                    _ => return,
+                };
+                let mark = TextMark::builder().left_gravity(true).build();
+                buffer.add_mark(&mark, &start);
+                self.folds.push(mark);
+            }
+        }
+        self.on_buffer_changed();
+        self.on_cursor_notify();
+    }
+
+    /// Unfold the sections hiding `line`.
+    fn reveal(&mut self, line: usize) {
+        let folded = self.folded_lines();
+        let hiding: Vec<usize> = {
+            let zettel = self.zettel.borrow();
+            let content = zettel.content();
+            zettel
+                .headings()
+                .iter()
+                .filter(|h| folded.contains(&h.line) && h.line < line)
+                .filter(|h| content[..h.section.end].lines().count() > line)
+                .map(|h| h.line)
+                .collect()
+        };
+        for l in hiding {
+            self.toggle_fold(l);
+        }
+    }
+
+    /// Headings, folds and the cursor, for the outline.
+    fn outline(&self) -> Outline {
+        let cursor = self.buffer.iter_at_offset(self.buffer.cursor_position());
+        Outline {
+            headings: self.zettel.borrow().headings(),
+            folded: self.folded_lines(),
+            cursor: cursor.line() as usize,
         }
     }
 
@@ -116,6 +413,34 @@ impl EditingZettel {
         }
     }
 
+    fn link_spans(&self) -> Vec<LinkSpan> {
+        self.blocks
+            .iter()
+            .filter_map(|blk| {
+                Some(LinkSpan {
+                    target: blk.link_target(&self.buffer)?,
+                    start: blk.start(&self.buffer).offset(),
+                    end: blk.end(&self.buffer).offset(),
+                })
+            })
+            .collect()
+    }
+
+    fn swap(&self) -> Swap {
+        let (start, end) = self.buffer.bounds();
+        Swap {
+            zid: self.zettel.borrow().zid().to_string(),
+            title: self.title.text(),
+            content: self.buffer.text(&start, &end, true).to_string(),
+        }
+    }
+
+    /// Show edits of `swap` instead of the saved content.
+    fn recover(&self, swap: &Swap) {
+        self.title.set_text(&swap.title);
+        self.buffer.set_text(&swap.content);
+    }
+
     fn insert_zettel_at_cursor(&self, z: &Zettel) {
         self.buffer
             .insert_at_cursor(&format!("[{}]({})", z.title(), z.zid(),));
@@ -125,35 +450,86 @@ impl EditingZettel {
         &mut self,
         kasten: &mut Kasten,
         parent_sender: relm4::Sender<super::Msg>,
-    ) -> bool {
+    ) -> Saving {
         if !self.buffer.is_modified() {
-            return false;
+            return Saving::Unmodified;
         }
 
         // TODO set when title is changed
         let title = self.title.text();
         self.zettel.borrow_mut().set_title(&title);
 
-        if let Err(err) = kasten.save(&self.zettel.borrow()) {
+        let zid = self.zettel.borrow().zid().to_string();
+        let saved = kasten.save(&mut self.zettel.borrow_mut());
+        // Stay modified until it's saved, the edits are still to be kept.
+        match saved {
+            Err(Error::Conflict { .. }) => {
+                send!(parent_sender, super::Msg::Conflict(self.zettel.clone()));
+                return Saving::Conflict;
+            }
+            Err(err) => {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowError(
+                        "Save note failed!".to_string(),
+                        Rc::new(err)
+                    )
+                );
+                return Saving::Failed;
+            }
+            Ok(saved) => report_version(saved, &parent_sender),
+        }
+        if let Err(err) = kasten.journal().remove(&zid) {
             send!(
                 parent_sender,
-                super::Msg::ShowMsg(
-                    MessageType::Error,
-                    format!("Save note failed: {:?}", err)
+                super::Msg::ShowError(
+                    "Forget edits of the saved note failed!".to_string(),
+                    Rc::new(err)
                 )
             );
         }
         self.buffer.set_modified(false);
-        true
+        Saving::Saved
+    }
+
+    /// Show the zettel as it is now, e.g. replaced by a merge.
+    fn reload(&self) {
+        let z = self.zettel.borrow();
+        self.title.set_text(z.title());
+        self.buffer.set_text(z.content());
     }
 }
 
 pub struct Model {
+    config: Rc<RefCell<Config>>,
     kasten: Rc<RefCell<Kasten>>,
-    stack: Vec<EditingZettel>,
+    panes: Vec<tab::Pane>,
+    /// Index of the pane having focus.
+    active: usize,
+    style: style::Style,
 
-    view: gtk::TextView,
+    /// Holds the notebooks of panes.
+    panes_box: gtk::Box,
+    /// View of the tab last reported to parent as focused.
+    focused: Option<TextView>,
+    /// Layout last reported to parent.
+    layout: Vec<Vec<String>>,
+    /// Count of modified zettels last reported to parent.
+    unsaved: usize,
+    /// Outline of the focused zettel last reported to parent.
+    outline: Option<Outline>,
     editing_title: bool,
+
+    /// Find bar is shown.
+    finding: bool,
+    /// Find bar is just shown, its entry should take focus.
+    find_started: bool,
+    query: Query,
+    /// Count of matches in the focused zettel, or why the query is bad.
+    found: std::result::Result<usize, String>,
+    /// Close, open or quit stopped by a save conflict, done again once the
+    /// conflict is merged.
+    pending: Option<Msg>,
 }
 
 pub struct EditorComponents {}
@@ -179,27 +555,161 @@ impl relm4::Model for Model {
 
 pub struct Editor {
     layout: gtk::Box,
-    main_win: gtk::ScrolledWindow,
+    panes_box: gtk::Box,
     title_in: gtk::Entry,
     title_label: gtk::Label,
     title_show: gtk::Box,
     action_bar: gtk::ActionBar,
     save_btn: gtk::Button,
+    find_bar: gtk::SearchBar,
+    find_entry: gtk::SearchEntry,
+    find_count: gtk::Label,
 }
 
 impl Model {
+    /// Focused tab.
+    fn tab(&self) -> Option<&tab::Tab> {
+        let pane = self.panes.get(self.active)?;
+        pane.tabs.get(pane.current()?)
+    }
+
+    fn tab_mut(&mut self) -> Option<&mut tab::Tab> {
+        let pane = self.panes.get_mut(self.active)?;
+        let i = pane.current()?;
+        pane.tabs.get_mut(i)
+    }
+
+    fn tabs(&self) -> impl Iterator<Item = &tab::Tab> {
+        self.panes.iter().flat_map(|p| p.tabs.iter())
+    }
+
+    /// Files the links of every open zettel point to.
+    fn linked_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for ez in self.tabs().flat_map(|t| t.stack.iter()) {
+            let z = ez.zettel.borrow();
+            files.extend(z.link_to_iter().filter_map(|link| {
+                kasten::resolve_file(&ez.repo_path, z.path(), link)
+            }));
+        }
+        files
+    }
+
+    /// Pane and tab showing zettel `zid`.
+    fn find(&self, zid: &str) -> Option<(usize, usize)> {
+        self.panes.iter().enumerate().find_map(|(p, pane)| {
+            let t = pane.tabs.iter().position(|t| {
+                t.top().map_or(false, |ez| ez.zettel.borrow().zid() == zid)
+            })?;
+            Some((p, t))
+        })
+    }
+
+    fn find_view(&self, view: &TextView) -> Option<(usize, usize)> {
+        self.panes.iter().enumerate().find_map(|(p, pane)| {
+            let t = pane.tabs.iter().position(|t| t.view == *view)?;
+            Some((p, t))
+        })
+    }
+
+    /// Zettel of the buffer, on any tab.
+    fn find_buffer(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+    ) -> Option<&mut EditingZettel> {
+        self.panes
+            .iter_mut()
+            .flat_map(|p| p.tabs.iter_mut())
+            .flat_map(|t| t.stack.iter_mut())
+            .find(|ez| ez.buffer == *buffer)
+    }
+
+    fn select(&mut self, p: usize, t: usize) {
+        self.active = p;
+        self.panes[p].select(t);
+    }
+
+    fn new_pane(&mut self, sender: relm4::Sender<Msg>) -> usize {
+        let pane = tab::Pane::new(sender);
+        self.panes_box.append(&pane.notebook);
+        self.panes.push(pane);
+        self.panes.len() - 1
+    }
+
+    /// The focused pane, created if there is none.
+    fn active_pane(&mut self, sender: relm4::Sender<Msg>) -> usize {
+        if self.panes.is_empty() {
+            self.active = self.new_pane(sender);
+        }
+        self.active
+    }
+
+    fn new_tab(
+        &mut self,
+        p: usize,
+        zettel: Rc<RefCell<Zettel>>,
+        sender: relm4::Sender<Msg>,
+    ) -> usize {
+        let mut tab = tab::Tab::new(self.kasten.clone(), sender.clone());
+        let repo_path = self.kasten.borrow().repo_path();
+        let ez = EditingZettel::new(
+            zettel,
+            tab.view.clone(),
+            self.style.table(),
+            repo_path,
+        );
+        ez.listen_buffer_event(sender);
+        tab.stack.push(ez);
+        self.panes[p].push(tab)
+    }
+
+    /// Show the zettel in the focused tab, or switch to the tab having it.
+    /// Returns false if a zettel of the tab can't be closed for a conflict.
     fn open_zettel(
         &mut self,
         zettel: Rc<RefCell<Zettel>>,
         sender: relm4::Sender<Msg>,
-    ) {
-        loop {
-            if self.stack.pop().is_none() {
-                break;
+        parent_sender: relm4::Sender<super::Msg>,
+    ) -> bool {
+        let zid = zettel.borrow().zid().to_string();
+        if let Some((p, t)) = self.find(&zid) {
+            self.select(p, t);
+            return true;
+        }
+        let (kasten, table) = (self.kasten.clone(), self.style.table());
+        let repo_path = kasten.borrow().repo_path();
+        match self.tab_mut() {
+            Some(tab) => {
+                if !tab.close(&mut kasten.borrow_mut(), parent_sender) {
+                    return false;
+                }
+                let ez = EditingZettel::new(
+                    zettel,
+                    tab.view.clone(),
+                    table,
+                    repo_path,
+                );
+                ez.listen_buffer_event(sender);
+                tab.stack.push(ez);
             }
+            None => self.open_in_tab(zettel, sender),
         }
+        true
+    }
 
-        self.open_zettel_on_stack(zettel, sender);
+    fn open_in_tab(
+        &mut self,
+        zettel: Rc<RefCell<Zettel>>,
+        sender: relm4::Sender<Msg>,
+    ) {
+        let zid = zettel.borrow().zid().to_string();
+        if let Some((p, t)) = self.find(&zid) {
+            self.select(p, t);
+            return;
+        }
+        let p = self.active_pane(sender.clone());
+        let t = self.new_tab(p, zettel, sender);
+        self.select(p, t);
     }
 
     fn open_zettel_on_stack(
@@ -207,58 +717,1053 @@ impl Model {
         zettel: Rc<RefCell<Zettel>>,
         sender: relm4::Sender<Msg>,
     ) {
-        let ez = EditingZettel::new(zettel, self.view.clone());
-        ez.listen_buffer_event(sender);
-        self.stack.push(ez);
+        let table = self.style.table();
+        let repo_path = self.kasten.borrow().repo_path();
+        match self.tab_mut() {
+            Some(tab) => {
+                let ez = EditingZettel::new(
+                    zettel,
+                    tab.view.clone(),
+                    table,
+                    repo_path,
+                );
+                ez.listen_buffer_event(sender);
+                tab.stack.push(ez);
+            }
+            None => self.open_in_tab(zettel, sender),
+        }
     }
 
     fn pop_stack_and_insert(&mut self, sender: relm4::Sender<Msg>) {
-        if let Some(z) = self.stack.pop() {
-            send!(sender, Msg::Insert(z.zettel));
+        if let Some(z) = self.tab_mut().and_then(|t| t.stack.pop()) {
+            send!(sender, Msg::Insert(z.zettel.clone()));
         }
     }
 
-    fn on_buffer_changed(&mut self) {
-        if let Some(z) = self.stack.last_mut() {
-            z.buffer.set_modified(true);
-            z.on_buffer_changed();
+    /// Returns false if the tab is kept open, failing to save or for a
+    /// conflict.
+    fn close_tab(
+        &mut self,
+        view: Option<TextView>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) -> bool {
+        let found = match view {
+            Some(view) => self.find_view(&view),
+            None => self
+                .panes
+                .get(self.active)
+                .and_then(|pane| Some((self.active, pane.current()?))),
+        };
+        let (p, t) = match found {
+            Some(found) => found,
+            None => return true,
+        };
+        let kasten = self.kasten.clone();
+        if !self.panes[p].tabs[t].close(&mut kasten.borrow_mut(), parent_sender)
+        {
+            self.select(p, t);
+            return false;
+        }
+        self.panes[p].remove(t);
+        if self.panes[p].tabs.is_empty() {
+            self.remove_pane(p);
         }
+        self.focused = None;
+        true
     }
 
-    fn on_cursor_notify(&mut self) {
-        if let Some(z) = self.stack.last_mut() {
+    fn remove_pane(&mut self, p: usize) {
+        let pane = self.panes.remove(p);
+        self.panes_box.remove(&pane.notebook);
+        if self.active > p || self.active >= self.panes.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    /// Drop panes left empty, e.g. split but nothing opened in.
+    fn prune(&mut self) {
+        let mut p = 0;
+        while p < self.panes.len() {
+            if self.panes[p].tabs.is_empty() && p != self.active {
+                self.remove_pane(p);
+            } else {
+                p += 1;
+            }
+        }
+    }
+
+    fn focus(
+        &mut self,
+        view: TextView,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let (p, _) = match self.find_view(&view) {
+            Some(found) => found,
+            None => return,
+        };
+        self.active = p;
+        self.prune();
+        if self.focused.as_ref() == Some(&view) {
+            return;
+        }
+        if let Some(ez) = self.tab().and_then(|t| t.top()) {
+            send!(parent_sender, super::Msg::Focused(ez.zettel.clone()));
+        }
+        self.focused = Some(view);
+    }
+
+    fn restore(
+        &mut self,
+        layout: Vec<Vec<Rc<RefCell<Zettel>>>>,
+        sender: relm4::Sender<Msg>,
+    ) {
+        for zettels in layout.into_iter().filter(|zs| !zs.is_empty()) {
+            let p = self.new_pane(sender.clone());
+            for z in zettels {
+                self.new_tab(p, z, sender.clone());
+            }
+        }
+        self.active = 0;
+    }
+
+    /// Ids of zettels shown, by panes and tabs.
+    fn layout(&self) -> Vec<Vec<String>> {
+        self.panes
+            .iter()
+            .map(|pane| {
+                pane.tabs
+                    .iter()
+                    .filter_map(|t| t.top())
+                    .map(|ez| ez.zettel.borrow().zid().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|zids| !zids.is_empty())
+            .collect()
+    }
+
+    fn report_layout(&mut self, parent_sender: relm4::Sender<super::Msg>) {
+        let layout = self.layout();
+        if layout != self.layout {
+            self.layout = layout.clone();
+            send!(parent_sender, super::Msg::LayoutChanged(layout));
+        }
+    }
+
+    /// Tell parent the outline of the focused zettel, if it changed.
+    fn report_outline(&mut self, parent_sender: relm4::Sender<super::Msg>) {
+        let outline = self.tab().and_then(|t| t.top()).map(|ez| ez.outline());
+        if outline != self.outline {
+            self.outline = outline.clone();
+            send!(
+                parent_sender,
+                super::Msg::OutlineChanged(outline.unwrap_or_default())
+            );
+        }
+    }
+
+    fn report_unsaved(&mut self, parent_sender: relm4::Sender<super::Msg>) {
+        let unsaved = self
+            .tabs()
+            .flat_map(|t| t.stack.iter())
+            .filter(|ez| ez.buffer.is_modified())
+            .count();
+        if unsaved != self.unsaved {
+            self.unsaved = unsaved;
+            send!(parent_sender, super::Msg::Unsaved(unsaved));
+        }
+    }
+
+    fn on_buffer_changed(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+        sender: relm4::Sender<Msg>,
+    ) {
+        let autosave = self.config.borrow().editor().autosave();
+        let z = match self.find_buffer(buffer) {
+            Some(z) => z,
+            None => return,
+        };
+        z.buffer.set_modified(true);
+        z.on_buffer_changed();
+        z.edits += 1;
+
+        let (s, b, n) = (sender.clone(), buffer.clone(), z.edits);
+        glib::timeout_add_local_once(JOURNAL_DELAY, move || {
+            send!(s, Msg::Settled(b, n))
+        });
+        if autosave > 0 {
+            let (b, n) = (buffer.clone(), z.edits);
+            let delay = Duration::from_secs(autosave.into());
+            glib::timeout_add_local_once(delay, move || {
+                send!(sender, Msg::Autosave(b, n))
+            });
+        }
+    }
+
+    /// Keep edits of the buffer in the journal, if it's still unsaved after
+    /// the n-th edit.
+    fn on_settled(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+        n: u64,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let journal = self.kasten.borrow().journal();
+        let z = match self.find_buffer(buffer) {
+            Some(z) if z.edits == n && z.buffer.is_modified() => z,
+            _ => return,
+        };
+        if let Err(e) = journal.write(&z.swap()) {
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Write journal failed!".to_string(),
+                    Rc::new(e)
+                )
+            );
+        }
+    }
+
+    fn autosave(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+        n: u64,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let kasten = self.kasten.clone();
+        if let Some(z) = self.find_buffer(buffer) {
+            if z.edits == n {
+                z.save(&mut kasten.borrow_mut(), parent_sender);
+            }
+        }
+    }
+
+    fn save_buffer(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) -> Saving {
+        let kasten = self.kasten.clone();
+        match self.find_buffer(buffer) {
+            Some(z) => z.save(&mut kasten.borrow_mut(), parent_sender),
+            None => Saving::Unmodified,
+        }
+    }
+
+    fn merged(
+        &mut self,
+        zettel: Rc<RefCell<Zettel>>,
+        text: &str,
+        sender: relm4::Sender<Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        // The zettel may come from the kasten, rebuilt since it was opened:
+        // the open one is found by its id.
+        let zid = zettel.borrow().zid().to_string();
+        let open = self
+            .panes
+            .iter()
+            .flat_map(|p| p.tabs.iter())
+            .flat_map(|t| t.stack.iter())
+            .find(|ez| ez.zettel.borrow().zid() == zid);
+        let zettel = open.map_or(zettel, |ez| ez.zettel.clone());
+        let applied = {
+            let mut z = zettel.borrow_mut();
+            z.rebase().and_then(|_| z.set_text(text))
+        };
+        if let Err(e) = applied {
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Merge note failed!".to_string(),
+                    Rc::new(e)
+                )
+            );
+            return;
+        }
+        if let Some(ez) = open {
+            // Saved after the buffer change is handled.
+            ez.reload();
+            send!(sender, Msg::SaveBuffer(ez.buffer.clone()));
+            return;
+        }
+        let saved = self.kasten.borrow_mut().save(&mut zettel.borrow_mut());
+        match saved {
+            Ok(saved) => report_version(saved, &parent_sender),
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Save note failed!".to_string(),
+                    Rc::new(e)
+                )
+            ),
+        }
+    }
+
+    fn recover(
+        &mut self,
+        zettel: Rc<RefCell<Zettel>>,
+        swap: Swap,
+        sender: relm4::Sender<Msg>,
+    ) {
+        // Restored tabs are reused, on top of a stack or under it.
+        let open = self
+            .panes
+            .iter()
+            .flat_map(|p| p.tabs.iter())
+            .flat_map(|t| t.stack.iter())
+            .find(|ez| ez.zettel.borrow().zid() == swap.zid);
+        if let Some(ez) = open {
+            ez.recover(&swap);
+            return;
+        }
+        self.open_in_tab(zettel, sender);
+        if let Some(z) = self.tab().and_then(|t| t.top()) {
+            z.recover(&swap);
+        }
+    }
+
+    /// Forget swaps of the open zettels, others may be of another session
+    /// still running.
+    fn discard(&self, parent_sender: relm4::Sender<super::Msg>) {
+        let journal = self.kasten.borrow().journal();
+        let open = self
+            .panes
+            .iter()
+            .flat_map(|p| p.tabs.iter())
+            .flat_map(|t| t.stack.iter());
+        for ez in open {
+            let zid = ez.zettel.borrow().zid().to_string();
+            if let Err(e) = journal.remove(&zid) {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowError(
+                        "Discard unsaved changes failed!".to_string(),
+                        Rc::new(e)
+                    )
+                );
+            }
+        }
+    }
+
+    fn on_cursor_notify(&mut self, buffer: &gtk::TextBuffer) {
+        if let Some(z) = self.find_buffer(buffer) {
             z.on_cursor_notify();
         }
     }
 
+    fn goto_line(&mut self, line: i32) {
+        if let Some(ez) = self.tab_mut().and_then(|t| t.stack.last_mut()) {
+            ez.reveal(line.max(0) as usize);
+        }
+        let tab = match self.tab() {
+            Some(tab) => tab,
+            None => return,
+        };
+        let buffer = match tab.top() {
+            Some(ez) => &ez.buffer,
+            None => return,
+        };
+        tab.sync_view();
+        if let Some(iter) = buffer.iter_at_line(line) {
+            buffer.place_cursor(&iter);
+        }
+        tab.view
+            .scroll_to_mark(&buffer.get_insert(), 0.0, true, 0.0, 0.3);
+        tab.view.grab_focus();
+    }
+
+    /// Scroll to and highlight the byte range of the focused zettel for a
+    /// while.
+    fn highlight(&mut self, range: Range<usize>) {
+        let (start, end) = match self.tab().and_then(|t| t.top()) {
+            Some(ez) => {
+                let zettel = ez.zettel.borrow();
+                let content = zettel.content();
+                match (content.get(..range.start), content.get(range)) {
+                    (Some(before), Some(target)) => {
+                        let start = before.chars().count() as i32;
+                        (start, start + target.chars().count() as i32)
+                    }
+                    _ => return,
+                }
+            }
+            None => return,
+        };
+        let buffer = match self.tab().and_then(|t| t.top()) {
+            Some(ez) => ez.buffer.clone(),
+            None => return,
+        };
+        let line = buffer.iter_at_offset(start).line();
+        self.goto_line(line);
+        let (start, end) =
+            (buffer.iter_at_offset(start), buffer.iter_at_offset(end));
+        buffer.place_cursor(&start);
+        buffer.apply_tag_by_name("target", &start, &end);
+        glib::timeout_add_local_once(HIGHLIGHT_TIME, move || {
+            let (start, end) = buffer.bounds();
+            buffer.remove_tag_by_name("target", &start, &end);
+        });
+    }
+
+    /// Fold the heading at `line`, or the heading above the cursor.
+    fn toggle_fold(&mut self, line: Option<i32>) {
+        let ez = match self.tab_mut().and_then(|t| t.stack.last_mut()) {
+            Some(ez) => ez,
+            None => return,
+        };
+        let line = match line {
+            Some(line) => line.max(0) as usize,
+            None => {
+                let outline = ez.outline();
+                match outline
+                    .headings
+                    .iter()
+                    .rev()
+                    .find(|h| h.line <= outline.cursor)
+                {
+                    Some(h) => h.line,
+                    None => return,
+                }
+            }
+        };
+        ez.toggle_fold(line);
+    }
+
     fn insert_zettel_at_cursor(&self, zettel: &Zettel) {
-        if let Some(z) = self.stack.last() {
+        if let Some(z) = self.tab().and_then(|t| t.top()) {
             z.insert_zettel_at_cursor(zettel);
         }
     }
 
+    /// Tab showing the buffer on top.
+    fn tab_of(&self, buffer: &gtk::TextBuffer) -> Option<&tab::Tab> {
+        self.tabs()
+            .find(|t| t.top().map_or(false, |ez| ez.buffer == *buffer))
+    }
+
+    /// Offer zettels for the link being typed in the buffer, if it is.
+    fn offer_completion(&self, buffer: &gtk::TextBuffer) {
+        let tab = match self.tab_of(buffer) {
+            Some(tab) => tab,
+            None => return,
+        };
+        let typing = match complete::typing_at(buffer) {
+            Some(typing) => typing,
+            None => {
+                tab.completion.hide();
+                return;
+            }
+        };
+        let mut items: Vec<complete::Item> = self
+            .kasten
+            .borrow()
+            .complete_title(&typing.text, complete::LIMIT)
+            .iter()
+            .map(|z| {
+                let z = z.borrow();
+                complete::Item::Zettel {
+                    zid: z.zid().to_string(),
+                    title: z.title().to_string(),
+                }
+            })
+            .collect();
+        let title = typing.text.trim();
+        if items.is_empty() && !title.is_empty() {
+            items.push(complete::Item::Create(title.to_string()));
+        }
+        if items.is_empty() {
+            tab.completion.hide();
+        } else {
+            tab.completion.show(&tab.view, typing, items);
+        }
+    }
+
+    /// Stop offering zettels once the cursor leaves the link being typed.
+    fn check_completion(&self, buffer: &gtk::TextBuffer) {
+        if let Some(tab) = self.tab_of(buffer) {
+            if tab.completion.is_shown()
+                && complete::typing_at(buffer).is_none()
+            {
+                tab.completion.hide();
+            }
+        }
+    }
+
+    /// Replace the text typed after the opener of the link with the n-th
+    /// zettel offered, created first if it's a new one.
+    fn complete(
+        &self,
+        view: &TextView,
+        i: usize,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let tab = match self.find_view(view) {
+            Some((p, t)) => &self.panes[p].tabs[t],
+            None => return,
+        };
+        let (typing, item) = match tab.completion.take(i) {
+            Some(taken) => taken,
+            None => return,
+        };
+        let item = match item {
+            complete::Item::Create(title) => {
+                let created = self.kasten.borrow_mut().create(&title);
+                match created {
+                    Ok(z) => {
+                        let z = z.borrow();
+                        complete::Item::Zettel {
+                            zid: z.zid().to_string(),
+                            title: z.title().to_string(),
+                        }
+                    }
+                    Err(e) => {
+                        send!(
+                            parent_sender,
+                            super::Msg::ShowError(
+                                "Create note failed!".to_string(),
+                                Rc::new(e)
+                            )
+                        );
+                        return;
+                    }
+                }
+            }
+            item => item,
+        };
+        let buffer = view.buffer();
+        let mut start = buffer.iter_at_offset(typing.start);
+        let mut end = buffer.iter_at_offset(buffer.cursor_position());
+        buffer.begin_user_action();
+        buffer.delete(&mut start, &mut end);
+        buffer.insert(&mut start, &item.completion(typing.opener));
+        buffer.end_user_action();
+    }
+
+    /// Replace the selection of the focused zettel with a link to a new
+    /// zettel made of it.
+    fn extract(&mut self, parent_sender: relm4::Sender<super::Msg>) {
+        let ez = match self.tab().and_then(|t| t.top()) {
+            Some(ez) => ez,
+            None => return,
+        };
+        let (mut start, mut end) = match ez.buffer.selection_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let text = ez.buffer.text(&start, &end, true);
+        if text.trim().is_empty() {
+            return;
+        }
+        let extracted =
+            self.kasten.borrow_mut().extract(&ez.zettel.borrow(), &text);
+        match extracted {
+            Ok(z) => {
+                let z = z.borrow();
+                ez.buffer.begin_user_action();
+                ez.buffer.delete(&mut start, &mut end);
+                ez.buffer.insert(
+                    &mut start,
+                    &format!("[{}]({})", z.title(), z.zid()),
+                );
+                ez.buffer.end_user_action();
+                send!(parent_sender, super::Msg::ZettelsChanged);
+            }
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Extract note failed!".to_string(),
+                    Rc::new(e)
+                )
+            ),
+        }
+    }
+
+    /// Append `source` to the focused zettel and save it, then point links
+    /// to `source` to it and delete `source`.
+    fn merge_in(
+        &mut self,
+        source: Rc<RefCell<Zettel>>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let source = source.borrow().clone();
+        let warn = |text: &str| {
+            send!(
+                parent_sender,
+                super::Msg::ShowMsg(MessageType::Warning, text.to_string())
+            )
+        };
+        let into = match self.tab().and_then(|t| t.top()) {
+            Some(ez) if ez.zettel.borrow().zid() != source.zid() => {
+                ez.zettel.borrow().zid().to_string()
+            }
+            Some(_) => return warn("A note can't be merged into itself."),
+            None => return warn("Open the note to merge into first."),
+        };
+        // Edits of the source in other tabs are saved before it's read.
+        while let Some((p, t)) = self.find(source.zid()) {
+            let view = self.panes[p].tabs[t].view.clone();
+            if !self.close_tab(Some(view), parent_sender.clone()) {
+                return warn(
+                    "The note to merge is changed on disk, merge it again \
+                     once that is resolved.",
+                );
+            }
+        }
+        let source = match self.kasten.borrow().resolve(source.zid()) {
+            Ok(z) => z.borrow().clone(),
+            Err(_) => source,
+        };
+        // Closing tabs moves the others, the target is found by its id.
+        let (p, t) = match self.find(&into) {
+            Some(found) => found,
+            None => return warn("The note to merge into is closed."),
+        };
+        self.select(p, t);
+        let kasten = self.kasten.clone();
+        let ez = match self.panes[p].tabs[t].stack.last_mut() {
+            Some(ez) => ez,
+            None => return,
+        };
+        let merged = {
+            let mut z = ez.zettel.borrow().clone();
+            z.merge(&source).map(|_| z)
+        };
+        let result = merged.and_then(|merged| {
+            let (mut start, mut end) = ez.buffer.bounds();
+            ez.buffer.begin_user_action();
+            ez.buffer.delete(&mut start, &mut end);
+            ez.buffer.insert(&mut start, merged.content());
+            ez.buffer.end_user_action();
+            *ez.zettel.borrow_mut() = merged;
+            // A failed save is reported by it, the source is kept then.
+            let saving =
+                ez.save(&mut kasten.borrow_mut(), parent_sender.clone());
+            if saving != Saving::Saved {
+                return Ok(None);
+            }
+            let into = ez.zettel.borrow();
+            kasten.borrow_mut().merge(&source, &into).map(Some)
+        });
+        match result {
+            Ok(Some(_)) => send!(parent_sender, super::Msg::ZettelsChanged),
+            Ok(None) => {}
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Merge note failed!".to_string(),
+                    Rc::new(e)
+                )
+            ),
+        }
+    }
+
+    /// Move the focused zettel to the file of id `zid`, once its edits are
+    /// saved wherever it's open, and show it from there.
+    fn move_to(
+        &mut self,
+        zid: String,
+        sender: relm4::Sender<Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let from = match self.tab().and_then(|t| t.top()) {
+            Some(ez) => ez.zettel.borrow().zid().to_string(),
+            None => {
+                return send!(
+                    parent_sender,
+                    super::Msg::ShowMsg(
+                        MessageType::Warning,
+                        "Open the note to move first.".to_string()
+                    )
+                )
+            }
+        };
+        let kasten = self.kasten.clone();
+        for ez in self.editing_mut(&from) {
+            // A failed save is reported by it, the zettel stays then.
+            let saving =
+                ez.save(&mut kasten.borrow_mut(), parent_sender.clone());
+            if saving != Saving::Saved && saving != Saving::Unmodified {
+                return;
+            }
+        }
+        let zettel = match kasten.borrow().resolve(&from) {
+            Ok(z) => z.borrow().clone(),
+            Err(_) => return,
+        };
+        let relinked = kasten.borrow_mut().move_zettel(&zettel, &zid);
+        // It's moved even if keeping its version failed.
+        let moved = match kasten.borrow().resolve(&from) {
+            Ok(_) => None,
+            Err(_) => kasten.borrow().resolve(&zid).ok(),
+        };
+        if let Some(moved) = moved {
+            for ez in self.editing_mut(&from) {
+                *ez.zettel.borrow_mut() = moved.borrow().clone();
+                ez.reload();
+                // Saved as it is on disk, to be unmodified again.
+                send!(sender, Msg::SaveBuffer(ez.buffer.clone()));
+            }
+            send!(parent_sender, super::Msg::Focused(moved));
+            send!(parent_sender, super::Msg::ZettelsChanged);
+        }
+        match relinked {
+            Ok(relinked) => {
+                send!(parent_sender, super::Msg::Replaced(relinked))
+            }
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Move note failed!".to_string(),
+                    Rc::new(e)
+                )
+            ),
+        }
+    }
+
+    /// Zettels of id `zid` being edited, in any tab.
+    fn editing_mut<'a>(
+        &'a mut self,
+        zid: &'a str,
+    ) -> impl Iterator<Item = &'a mut EditingZettel> {
+        self.panes
+            .iter_mut()
+            .flat_map(|p| p.tabs.iter_mut())
+            .flat_map(|t| t.stack.iter_mut())
+            .filter(move |ez| ez.zettel.borrow().zid() == zid)
+    }
+
+    /// Tag the matches of the query in the focused zettel, and only there.
+    fn mark_found(&mut self) {
+        for tab in self.tabs() {
+            if let Some(ez) = tab.top() {
+                let (start, end) = ez.buffer.bounds();
+                ez.buffer.remove_tag_by_name("found", &start, &end);
+            }
+        }
+        self.found = Ok(0);
+        if !self.finding || self.query.pattern.is_empty() {
+            return;
+        }
+        let ez = match self.tab().and_then(|t| t.top()) {
+            Some(ez) => ez,
+            None => return,
+        };
+        let finder = match Finder::new(&self.query) {
+            Ok(finder) => finder,
+            Err(e) => {
+                self.found = Err(e.to_string());
+                return;
+            }
+        };
+        let found = char_ranges(ez.zettel.borrow().content(), &finder);
+        for r in found.iter() {
+            let start = ez.buffer.iter_at_offset(r.start);
+            let end = ez.buffer.iter_at_offset(r.end);
+            ez.buffer.apply_tag_by_name("found", &start, &end);
+        }
+        self.found = Ok(found.len());
+    }
+
+    /// Select the first match after the selection of the focused zettel,
+    /// or the last one before it, going round at the ends.
+    fn find_step(&mut self, forward: bool) {
+        let (tab, ez) = match self.tab().and_then(|t| Some((t, t.top()?))) {
+            Some(found) => found,
+            None => return,
+        };
+        let found = match Finder::new(&self.query) {
+            Ok(finder) => char_ranges(ez.zettel.borrow().content(), &finder),
+            Err(_) => return,
+        };
+        let buffer = &ez.buffer;
+        let (start, end) = buffer.selection_bounds().unwrap_or_else(|| {
+            let cursor = buffer.iter_at_offset(buffer.cursor_position());
+            (cursor, cursor)
+        });
+        let next = if forward {
+            found
+                .iter()
+                .find(|r| r.start >= end.offset())
+                .or_else(|| found.first())
+        } else {
+            found
+                .iter()
+                .rev()
+                .find(|r| r.end <= start.offset())
+                .or_else(|| found.last())
+        };
+        if let Some(r) = next {
+            let line = buffer.iter_at_offset(r.start).line();
+            let (view, buffer) = (tab.view.clone(), buffer.clone());
+            if let Some(ez) = self.tab_mut().and_then(|t| t.stack.last_mut()) {
+                ez.reveal(line as usize);
+            }
+            buffer.select_range(
+                &buffer.iter_at_offset(r.end),
+                &buffer.iter_at_offset(r.start),
+            );
+            view.scroll_to_mark(&buffer.get_insert(), 0.0, true, 0.0, 0.3);
+        }
+    }
+
+    /// Show zettels of `zids` as they are on disk now, unless they have
+    /// unsaved edits, which are merged with them when saved.
+    fn reload(&mut self, zids: &[String], sender: relm4::Sender<Msg>) {
+        let open = self
+            .panes
+            .iter()
+            .flat_map(|p| p.tabs.iter())
+            .flat_map(|t| t.stack.iter())
+            .filter(|ez| !ez.buffer.is_modified())
+            .filter(|ez| {
+                zids.iter().any(|zid| zid == ez.zettel.borrow().zid())
+            });
+        for ez in open {
+            let reloaded = {
+                let mut z = ez.zettel.borrow_mut();
+                z.rebase()
+                    .and_then(|_| z.disk_text())
+                    .and_then(|text| z.set_text(&text))
+            };
+            if reloaded.is_ok() {
+                ez.reload();
+                // Saved as it is on disk, to be unmodified again.
+                send!(sender, Msg::SaveBuffer(ez.buffer.clone()));
+            }
+        }
+    }
+
+    /// Link the mention in the buffer of its zettel, so unsaved edits are
+    /// kept, or on disk if it's not open.
+    fn link_mention(
+        &mut self,
+        m: &Mention,
+        zid: &str,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let source = m.zettel.borrow().zid().to_string();
+        let kasten = self.kasten.clone();
+        let open = self
+            .panes
+            .iter_mut()
+            .flat_map(|p| p.tabs.iter_mut())
+            .flat_map(|t| t.stack.iter_mut())
+            .find(|ez| ez.zettel.borrow().zid() == source);
+        let ez = match open {
+            Some(ez) => ez,
+            None => {
+                let linked = kasten.borrow_mut().link_mention(m, zid);
+                match linked {
+                    Ok(_) => send!(parent_sender, super::Msg::MentionLinked),
+                    Err(e) => send!(
+                        parent_sender,
+                        super::Msg::ShowError(
+                            "Link the mention failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+                return;
+            }
+        };
+
+        let (start, end, text) = {
+            let z = m.zettel.borrow();
+            let content = z.content();
+            let start = content[..m.range.start].chars().count() as i32;
+            let text = content[m.range.clone()].to_string();
+            (start, start + text.chars().count() as i32, text)
+        };
+        let buffer = ez.buffer.clone();
+        let (mut from, mut to) =
+            (buffer.iter_at_offset(start), buffer.iter_at_offset(end));
+        // The mention is found in the saved content, edits may have moved it.
+        if buffer.text(&from, &to, true).as_str() != text {
+            send!(
+                parent_sender,
+                super::Msg::ShowMsg(
+                    MessageType::Warning,
+                    "The mention is edited, save the note and try again."
+                        .to_string()
+                )
+            );
+            return;
+        }
+        buffer.delete(&mut from, &mut to);
+        buffer.insert(&mut from, &format!("[{}]({})", text, zid));
+        let saving = ez.save(&mut kasten.borrow_mut(), parent_sender.clone());
+        if saving == Saving::Saved {
+            send!(parent_sender, super::Msg::MentionLinked);
+        }
+    }
+
+    fn sync_links(&self) {
+        for tab in self.tabs() {
+            tab.sync_links();
+        }
+    }
+
+    fn activate_link(
+        &self,
+        target: &str,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        if kasten::is_external(target) {
+            gtk::show_uri(None::<&gtk::Window>, target, 0);
+            return;
+        }
+        // Anchors alone, like `#Heading`, are in the zettel itself.
+        let target = match self.tab().and_then(|t| t.top()) {
+            Some(ez) if target.starts_with(['#', '^']) => {
+                format!("{}{}", ez.zettel.borrow().zid(), target)
+            }
+            _ => target.to_string(),
+        };
+        let resolved = self.kasten.borrow().resolve_anchor(&target);
+        match resolved {
+            Ok(kasten::Target {
+                zettel,
+                range: Some(range),
+            }) => {
+                send!(parent_sender, super::Msg::ChangeZettelAt(zettel, range))
+            }
+            Ok(t) => send!(parent_sender, super::Msg::ChangeZettel(t.zettel)),
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Open link failed!".to_string(),
+                    Rc::new(e)
+                )
+            ),
+        }
+    }
+
+    /// Attach copies of the files of `paths` to the zettel shown by `view`,
+    /// and link them at its cursor.
+    fn attach(
+        &self,
+        view: &TextView,
+        paths: &[&Path],
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let buffer = match self.find_view(view) {
+            Some((p, t)) => match self.panes[p].tabs[t].top() {
+                Some(ez) => ez.buffer.clone(),
+                None => return,
+            },
+            None => return,
+        };
+        let attachments = self.kasten.borrow().attachments();
+        let mut links = vec![];
+        for path in paths {
+            match attachments.add_file(path) {
+                Ok(copy) => {
+                    let name = path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    links.push(attachments.markdown(&copy, &name));
+                }
+                Err(e) => send!(
+                    parent_sender,
+                    super::Msg::ShowError(
+                        "Attach file failed!".to_string(),
+                        Rc::new(e)
+                    )
+                ),
+            }
+        }
+        if !links.is_empty() {
+            buffer.insert_at_cursor(&links.join("\n"));
+        }
+    }
+
+    /// Attach a pasted picture as a PNG file.
+    fn attach_picture(
+        &self,
+        view: &TextView,
+        texture: &gdk::Texture,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let tmp = std::env::temp_dir()
+            .join(format!("rustybrain-paste-{}.png", std::process::id()));
+        if let Err(e) = texture.save_to_png(&tmp) {
+            let e = std::io::Error::new(std::io::ErrorKind::Other, e.message);
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Attach picture failed!".to_string(),
+                    Rc::new(Error::io(&tmp, e))
+                )
+            );
+            return;
+        }
+        self.attach(view, &[tmp.as_path()], parent_sender);
+        let _ = std::fs::remove_file(&tmp);
+    }
+
     fn save(&mut self, parent_sender: relm4::Sender<super::Msg>) -> bool {
-        match self.stack.last_mut() {
-            Some(z) => z.save(&mut self.kasten.borrow_mut(), parent_sender),
+        let kasten = self.kasten.clone();
+        match self.tab_mut().and_then(|t| t.stack.last_mut()) {
+            Some(z) => {
+                z.save(&mut kasten.borrow_mut(), parent_sender) == Saving::Saved
+            }
             None => false,
         }
     }
+
+    /// Save and close every tab, stopping at the first one kept open, failing
+    /// to save or for a conflict. Returns whether all are closed.
+    fn close(&mut self, parent_sender: relm4::Sender<super::Msg>) -> bool {
+        loop {
+            let first = self.panes.iter().find_map(|p| p.tabs.first());
+            let view = match first {
+                Some(tab) => tab.view.clone(),
+                None => break,
+            };
+            if !self.close_tab(Some(view), parent_sender.clone()) {
+                return false;
+            }
+        }
+        while let Some(pane) = self.panes.pop() {
+            self.panes_box.remove(&pane.notebook);
+        }
+        self.active = 0;
+        self.focused = None;
+        self.editing_title = false;
+        true
+    }
 }
 
 impl ComponentUpdate<super::AppModel> for Model {
     fn init_model(parent_model: &super::AppModel) -> Self {
-        let view = gtk::TextView::builder()
-            .vexpand(true)
+        let style = style::Style::new(parent_model.config.borrow().editor());
+        let panes_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .homogeneous(true)
+            .spacing(6)
             .hexpand(true)
-            .pixels_inside_wrap(10)
-            .wrap_mode(gtk::WrapMode::Char)
+            .vexpand(true)
             .build();
 
         Model {
+            config: parent_model.config.clone(),
             kasten: parent_model.kasten.clone(),
-            stack: vec![],
+            panes: vec![],
+            active: 0,
+            style,
             editing_title: false,
-            view,
+            panes_box,
+            focused: None,
+            layout: vec![],
+            unsaved: 0,
+            outline: None,
+            finding: false,
+            find_started: false,
+            query: Query::default(),
+            found: Ok(0),
+            pending: None,
         }
     }
 
@@ -269,14 +1774,45 @@ impl ComponentUpdate<super::AppModel> for Model {
         sender: relm4::Sender<Self::Msg>,
         parent_sender: relm4::Sender<super::Msg>,
     ) {
+        // Closing all is switching slip-box, the layout is kept for it.
+        let mut report = true;
+        self.find_started = false;
         match msg {
-            Msg::Changed => self.on_buffer_changed(),
-            Msg::Cursor => self.on_cursor_notify(),
+            Msg::Changed(b) => {
+                self.on_buffer_changed(&b, sender);
+                self.offer_completion(&b);
+            }
+            Msg::Settled(b, n) => self.on_settled(&b, n, parent_sender.clone()),
+            Msg::Autosave(b, n) => self.autosave(&b, n, parent_sender.clone()),
+            Msg::Recover(z, swap) => {
+                self.editing_title = false;
+                self.recover(z, swap, sender);
+            }
+            Msg::Cursor(b) => {
+                self.on_cursor_notify(&b);
+                self.check_completion(&b);
+            }
             Msg::Open(z) => {
                 self.editing_title = false;
-                self.open_zettel(z, sender)
+                if !self.open_zettel(z.clone(), sender, parent_sender.clone()) {
+                    self.pending = Some(Msg::Open(z));
+                }
             }
-
+            Msg::OpenInTab(z) => {
+                self.editing_title = false;
+                self.open_in_tab(z, sender);
+            }
+            Msg::Split => {
+                self.active = self.new_pane(sender);
+                self.prune();
+            }
+            Msg::CloseTab(view) => {
+                if !self.close_tab(view.clone(), parent_sender.clone()) {
+                    self.pending = Some(Msg::CloseTab(view));
+                }
+            }
+            Msg::Focus(view) => self.focus(view, parent_sender.clone()),
+            Msg::Restore(layout) => self.restore(layout, sender),
             Msg::OpenOnStack(z) => {
                 self.editing_title = false;
                 self.open_zettel_on_stack(z, sender);
@@ -285,14 +1821,104 @@ impl ComponentUpdate<super::AppModel> for Model {
                 self.insert_zettel_at_cursor(&z.borrow());
             }
             Msg::Save => {
-                if self.save(parent_sender) || self.stack.len() > 1 {
+                let stacked = self.tab().map_or(false, |t| t.stack.len() > 1);
+                if self.save(parent_sender.clone()) || stacked {
                     self.pop_stack_and_insert(sender);
                 }
             }
+            Msg::SaveBuffer(b) => {
+                let saving = self.save_buffer(&b, parent_sender.clone());
+                if saving == Saving::Saved {
+                    if let Some(msg) = self.pending.take() {
+                        send!(sender, msg);
+                    }
+                }
+            }
+            Msg::Merged(z, text) => {
+                self.merged(z, &text, sender, parent_sender.clone())
+            }
+            Msg::Unresolved => self.pending = None,
+            Msg::Close => {
+                if self.close(parent_sender.clone()) {
+                    report = false;
+                    send!(parent_sender, super::Msg::EditorClosed);
+                } else {
+                    self.pending = Some(Msg::Close);
+                }
+            }
+            Msg::DiscardAndQuit => {
+                self.discard(parent_sender.clone());
+                send!(parent_sender, super::Msg::Exit);
+                return;
+            }
+            Msg::SaveAndQuit => {
+                if self.close(parent_sender.clone()) {
+                    send!(parent_sender, super::Msg::Exit);
+                    return;
+                }
+                self.pending = Some(Msg::SaveAndQuit);
+            }
+            Msg::Restyle => {
+                self.style.restyle(self.config.borrow().editor());
+            }
             Msg::EditTitle => self.editing_title = true,
             Msg::DoneEditTitle => {
                 self.editing_title = false;
             }
+            Msg::ActivateLink(target) => {
+                self.activate_link(&target, parent_sender.clone())
+            }
+            Msg::GotoLine(line) => self.goto_line(line),
+            Msg::ToggleFold(line) => self.toggle_fold(line),
+            Msg::Highlight(range) => self.highlight(range),
+            Msg::LinkMention(m, zid) => {
+                self.link_mention(&m, &zid, parent_sender.clone())
+            }
+            Msg::Attach(view, paths) => {
+                let paths: Vec<&Path> =
+                    paths.iter().map(|p| p.as_path()).collect();
+                self.attach(&view, &paths, parent_sender.clone())
+            }
+            Msg::AttachPicture(view, texture) => {
+                self.attach_picture(&view, &texture, parent_sender.clone())
+            }
+            Msg::Complete(view, i) => {
+                self.complete(&view, i, parent_sender.clone())
+            }
+            Msg::Extract => self.extract(parent_sender.clone()),
+            Msg::MergeIn(z) => self.merge_in(z, parent_sender.clone()),
+            Msg::Move(zid) => self.move_to(zid, sender, parent_sender.clone()),
+            Msg::StartFind => {
+                self.finding = true;
+                self.find_started = true;
+            }
+            Msg::Find(query) => self.query = query,
+            Msg::FindStep(forward) => self.find_step(forward),
+            Msg::CloseFind => self.finding = false,
+            Msg::Reload(zids) => self.reload(&zids, sender),
+            Msg::LinkedFiles => {
+                let files = self.linked_files();
+                send!(parent_sender, super::Msg::DeleteOrphansExcept(files));
+                return;
+            }
+            Msg::Relayout(view) => {
+                let top = self
+                    .find_view(&view)
+                    .and_then(|(p, t)| self.panes[p].tabs[t].top());
+                if let Some(ez) = top {
+                    ez.place_overlays();
+                }
+                return;
+            }
+        }
+        self.sync_links();
+        self.mark_found();
+        self.report_unsaved(parent_sender.clone());
+        self.report_outline(parent_sender.clone());
+        if report {
+            self.report_layout(parent_sender);
+        } else {
+            self.layout.clear();
         }
     }
 }
@@ -328,16 +1954,6 @@ impl Widgets<Model, super::AppModel> for Editor {
         focus_ctrl.connect_leave(move |_| send!(s, Msg::DoneEditTitle));
         entry.add_controller(&focus_ctrl);
 
-        let window = ScrolledWindow::builder()
-            .hexpand(true)
-            .vexpand(true)
-            .margin_start(10)
-            .margin_end(10)
-            .margin_top(10)
-            .margin_bottom(10)
-            .child(&model.view)
-            .build();
-
         let title_show = gtk::Box::builder()
             .orientation(gtk::Orientation::Horizontal)
             .hexpand(true)
@@ -351,17 +1967,23 @@ impl Widgets<Model, super::AppModel> for Editor {
 
         let action_bar = ActionBar::builder().build();
         let save_btn = gtk::Button::builder().label("Save").build();
-        save_btn.connect_clicked(move |_| send!(sender, Msg::Save));
+        let s = sender.clone();
+        save_btn.connect_clicked(move |_| send!(s, Msg::Save));
         action_bar.pack_end(&save_btn);
 
+        let (find_bar, find_entry, find_count) = Self::find_bar(sender);
+
         Editor {
             layout: box_,
             title_in: entry,
             title_label: label,
             title_show,
-            main_win: window,
+            panes_box: model.panes_box.clone(),
             action_bar,
             save_btn,
+            find_bar,
+            find_entry,
+            find_count,
         }
     }
 
@@ -375,13 +1997,40 @@ impl Widgets<Model, super::AppModel> for Editor {
             self.layout.append(&self.title_show);
         }
         self.layout.append(&self.action_bar);
-        self.layout.append(&self.main_win);
+        self.layout.append(&self.find_bar);
+        self.layout.append(&self.panes_box);
+
+        if self.find_bar.is_search_mode() != model.finding {
+            self.find_bar.set_search_mode(model.finding);
+        }
+        if model.find_started {
+            self.find_entry.grab_focus();
+        }
+        match &model.found {
+            Ok(_) if model.query.pattern.is_empty() => {
+                self.find_count.set_text("")
+            }
+            Ok(0) => self.find_count.set_text("No matches"),
+            Ok(1) => self.find_count.set_text("1 match"),
+            Ok(n) => self.find_count.set_text(&format!("{} matches", n)),
+            Err(e) => self.find_count.set_text(e),
+        }
 
-        if let Some(ez) = model.stack.last() {
-            model.view.set_buffer(Some(&ez.buffer));
+        for tab in model.tabs() {
+            tab.sync_view();
+        }
+        let tab = match model.tab() {
+            Some(tab) => tab,
+            None => {
+                self.title_label.set_text("");
+                self.save_btn.set_sensitive(false);
+                return;
+            }
+        };
+        if let Some(ez) = tab.top() {
             self.title_in.set_buffer(&ez.title);
 
-            if ez.buffer.is_modified() || model.stack.len() > 1 {
+            if ez.buffer.is_modified() || tab.stack.len() > 1 {
                 self.save_btn.set_sensitive(true);
             } else {
                 self.save_btn.set_sensitive(false);
@@ -394,6 +2043,86 @@ impl Widgets<Model, super::AppModel> for Editor {
                 self.title_in.set_placeholder_text(None)
             }
         }
-        model.view.grab_focus();
+        // Typing goes on in the find bar while it's shown.
+        if !model.finding {
+            tab.view.grab_focus();
+        }
+    }
+}
+
+impl Editor {
+    /// Bar to find text in the focused zettel, with its entry and the label
+    /// counting matches.
+    fn find_bar(
+        sender: relm4::Sender<Msg>,
+    ) -> (gtk::SearchBar, gtk::SearchEntry, gtk::Label) {
+        let entry = gtk::SearchEntry::builder()
+            .hexpand(true)
+            .placeholder_text("Find in note")
+            .build();
+        let regex_btn = gtk::ToggleButton::builder()
+            .label(".*")
+            .tooltip_text("Regular expression")
+            .build();
+        let case_btn = gtk::ToggleButton::builder()
+            .label("Aa")
+            .tooltip_text("Match case")
+            .build();
+        let prev_btn = gtk::Button::builder()
+            .icon_name("go-up-symbolic")
+            .tooltip_text("Previous match")
+            .build();
+        let next_btn = gtk::Button::builder()
+            .icon_name("go-down-symbolic")
+            .tooltip_text("Next match")
+            .build();
+        let count = gtk::Label::builder().width_chars(12).build();
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        box_.append(&entry);
+        box_.append(&regex_btn);
+        box_.append(&case_btn);
+        box_.append(&prev_btn);
+        box_.append(&next_btn);
+        box_.append(&count);
+        let bar = gtk::SearchBar::builder()
+            .child(&box_)
+            .show_close_button(true)
+            .build();
+        bar.connect_entry(&entry);
+
+        let query: Rc<dyn Fn() -> Query> = {
+            let (e, r, c) =
+                (entry.clone(), regex_btn.clone(), case_btn.clone());
+            Rc::new(move || Query {
+                pattern: e.text().to_string(),
+                regex: r.is_active(),
+                match_case: c.is_active(),
+            })
+        };
+        let (s, q) = (sender.clone(), query.clone());
+        entry.connect_search_changed(move |_| send!(s, Msg::Find(q())));
+        let (s, q) = (sender.clone(), query.clone());
+        regex_btn.connect_toggled(move |_| send!(s, Msg::Find(q())));
+        let s = sender.clone();
+        case_btn.connect_toggled(move |_| send!(s, Msg::Find(query())));
+        let s = sender.clone();
+        entry.connect_activate(move |_| send!(s, Msg::FindStep(true)));
+        let s = sender.clone();
+        entry.connect_next_match(move |_| send!(s, Msg::FindStep(true)));
+        let s = sender.clone();
+        next_btn.connect_clicked(move |_| send!(s, Msg::FindStep(true)));
+        let s = sender.clone();
+        entry.connect_previous_match(move |_| send!(s, Msg::FindStep(false)));
+        let s = sender.clone();
+        prev_btn.connect_clicked(move |_| send!(s, Msg::FindStep(false)));
+        bar.connect_search_mode_enabled_notify(move |bar| {
+            if !bar.is_search_mode() {
+                send!(sender, Msg::CloseFind);
+            }
+        });
+        (bar, entry, count)
     }
 }
@@ -1,18 +1,29 @@
+use std::collections::HashMap;
+
+use gtk::glib::translate::IntoGlib;
 use gtk::pango::FontDescription;
+use gtk::prelude::*;
 use gtk::TextTag;
 use gtk::TextTagTable;
+use rustybrain_core::config::{Editor, Theme};
+use rustybrain_core::highlight::KINDS;
 
 pub struct Style {
     font: String,
     font_size: i32,
+    theme: Theme,
+    /// Colours of highlighted code set in the config, by kind.
+    code: HashMap<&'static str, String>,
     table: TextTagTable,
 }
 
 impl Style {
-    pub fn new() -> Self {
+    pub fn new(config: &Editor) -> Self {
         let mut style = Style {
-            font: "Victor Mono".to_string(),
-            font_size: 14,
+            font: config.font().to_string(),
+            font_size: config.font_size(),
+            theme: config.theme(),
+            code: code_colors(config),
             table: TextTagTable::new(),
         };
         style.fill();
@@ -23,6 +34,15 @@ impl Style {
         self.table.clone()
     }
 
+    /// Update tags of the table in place, so buffers using it are restyled.
+    pub fn restyle(&mut self, config: &Editor) {
+        self.font = config.font().to_string();
+        self.font_size = config.font_size();
+        self.theme = config.theme();
+        self.code = code_colors(config);
+        self.fill();
+    }
+
     fn fill(&mut self) {
         self.fill_paragraph();
         self.fill_headline();
@@ -32,13 +52,36 @@ impl Style {
         self.fill_bold();
         self.fill_italic();
         self.fill_strikethrough();
+        self.fill_blockquote();
+        self.fill_list();
+        self.fill_thematic_break();
+        self.fill_table();
+        self.fill_html();
+        self.fill_target();
+        self.fill_found();
         self.fill_hidden();
     }
 
+    /// Tag named `name` of the table, added if missing.
+    fn tag(&self, name: &str) -> TextTag {
+        if let Some(tag) = self.table.lookup(name) {
+            return tag;
+        }
+        let tag = TextTag::builder().name(name).build();
+        self.table.add(&tag);
+        tag
+    }
+
+    fn color(&self, light: &str, dark: &str) -> String {
+        match self.theme {
+            Theme::Light => light.to_string(),
+            Theme::Dark => dark.to_string(),
+        }
+    }
+
     fn fill_paragraph(&mut self) {
         let fd = self.font_desc();
-        let tag = TextTag::builder().name("p").font_desc(&fd).build();
-        self.table.add(&tag);
+        self.tag("p").set_font_desc(Some(&fd));
     }
 
     fn font_desc(&self) -> FontDescription {
@@ -54,53 +97,136 @@ impl Style {
             let mut fd = FontDescription::from_string(&font);
             fd.set_style(gtk::pango::Style::Oblique);
             let name = format!("h{}", hn);
-            let tag = TextTag::builder().name(&name).font_desc(&fd).build();
-            self.table.add(&tag);
+            self.tag(&name).set_font_desc(Some(&fd));
             hn += 1;
         }
     }
 
     fn fill_link(&mut self) {
         let fd = self.font_desc();
-        let tag = TextTag::builder()
-            .name("link")
-            .foreground("blue")
-            .underline(gtk::pango::Underline::SingleLine)
-            .font_desc(&fd)
-            .build();
-        self.table.add(&tag);
+        let tag = self.tag("link");
+        tag.set_foreground(Some(&self.color("blue", "#8AB4F8")));
+        tag.set_underline(gtk::pango::Underline::SingleLine);
+        tag.set_font_desc(Some(&fd));
     }
 
-    fn fill_code(&mut self) {}
+    /// Tags of inline code, and of highlighted code in code blocks,
+    /// `code-keyword` and so on.
+    fn fill_code(&mut self) {
+        let tag = self.tag("code");
+        tag.set_family(Some("Monospace"));
+        tag.set_background(Some(&self.color("#E7ECF0", "#2B2F33")));
+        for kind in KINDS {
+            let (light, dark) = match kind {
+                "keyword" => ("#A626A4", "#C678DD"),
+                "string" => ("#50A14F", "#98C379"),
+                "comment" => ("#A0A1A7", "#7F848E"),
+                "number" | "constant" => ("#986801", "#D19A66"),
+                "function" => ("#4078F2", "#61AFEF"),
+                "type" => ("#C18401", "#E5C07B"),
+                "property" => ("#E45649", "#E06C75"),
+                "attribute" => ("#0184BC", "#56B6C2"),
+                _ => ("#383A42", "#ABB2BF"),
+            };
+            let color = match self.code.get(kind) {
+                Some(color) => color.clone(),
+                None => self.color(light, dark),
+            };
+            let tag = self.tag(&format!("code-{}", kind));
+            tag.set_foreground(Some(&color));
+            if kind == "comment" {
+                tag.set_style(gtk::pango::Style::Italic);
+            }
+        }
+    }
 
     fn fill_code_block(&mut self) {
         let fd = self.font_desc();
-        let tag = TextTag::builder()
-            .name("code-block")
-            .paragraph_background("#E7ECF0")
-            .font_desc(&fd)
-            .build();
-        self.table.add(&tag);
+        let tag = self.tag("code-block");
+        tag.set_paragraph_background(Some(&self.color("#E7ECF0", "#2B2F33")));
+        tag.set_font_desc(Some(&fd));
     }
 
     fn fill_bold(&mut self) {
         let mut fd = self.font_desc();
         fd.set_weight(gtk::pango::Weight::Bold);
-        let tag = TextTag::builder().name("bold").font_desc(&fd).build();
-        self.table.add(&tag);
+        self.tag("bold").set_font_desc(Some(&fd));
     }
 
     fn fill_italic(&mut self) {
         let mut fd = self.font_desc();
         fd.set_style(gtk::pango::Style::Italic);
-        let tag = TextTag::builder().name("italic").font_desc(&fd).build();
-        self.table.add(&tag);
+        self.tag("italic").set_font_desc(Some(&fd));
     }
 
-    fn fill_strikethrough(&mut self) {}
+    fn fill_strikethrough(&mut self) {
+        self.tag("strikethrough").set_strikethrough(true);
+    }
+
+    fn fill_blockquote(&mut self) {
+        let tag = self.tag("blockquote");
+        tag.set_left_margin(24);
+        tag.set_foreground(Some(&self.color("#5C6370", "#9DA5B4")));
+        tag.set_paragraph_background(Some(&self.color("#F3F5F7", "#24272B")));
+    }
+
+    fn fill_list(&mut self) {
+        let tag = self.tag("list-marker");
+        tag.set_foreground(Some(&self.color("#4078F2", "#61AFEF")));
+        tag.set_weight(gtk::pango::Weight::Bold.into_glib());
+        let tag = self.tag("task-marker");
+        tag.set_family(Some("Monospace"));
+        tag.set_foreground(Some(&self.color("#4078F2", "#61AFEF")));
+        // Keeps the room of the source under its checkbox.
+        self.tag("task-box").set_foreground(Some("rgba(0,0,0,0)"));
+        let tag = self.tag("task-done");
+        tag.set_strikethrough(true);
+        tag.set_foreground(Some(&self.color("#A0A1A7", "#7F848E")));
+    }
+
+    /// A break is drawn as a wide struck line over its dashes.
+    fn fill_thematic_break(&mut self) {
+        let tag = self.tag("thematic-break");
+        let color = self.color("#A0A1A7", "#5C6370");
+        tag.set_foreground(Some(&color));
+        tag.set_strikethrough(true);
+        tag.set_letter_spacing(self.font_size * 4 * gtk::pango::SCALE);
+        tag.set_justification(gtk::Justification::Center);
+    }
+
+    fn fill_table(&mut self) {
+        self.tag("table").set_family(Some("Monospace"));
+        self.tag("table-header")
+            .set_weight(gtk::pango::Weight::Bold.into_glib());
+    }
+
+    fn fill_html(&mut self) {
+        let tag = self.tag("html");
+        tag.set_family(Some("Monospace"));
+        tag.set_foreground(Some(&self.color("#A0A1A7", "#7F848E")));
+    }
+
+    /// Section or block a link points to, shown for a while after following
+    /// the link.
+    fn fill_target(&mut self) {
+        let tag = self.tag("target");
+        tag.set_paragraph_background(Some(&self.color("#FFF3C4", "#3E3A24")));
+    }
+
+    /// Matches of the find bar.
+    fn fill_found(&mut self) {
+        let tag = self.tag("found");
+        tag.set_background(Some(&self.color("#FFE066", "#6B5B16")));
+    }
 
     fn fill_hidden(&mut self) {
-        let tag = TextTag::builder().name("hidden").invisible(true).build();
-        self.table.add(&tag);
+        self.tag("hidden").set_invisible(true);
     }
 }
+
+fn code_colors(config: &Editor) -> HashMap<&'static str, String> {
+    KINDS
+        .iter()
+        .filter_map(|k| config.code_color(k).map(|c| (*k, c.to_string())))
+        .collect()
+}
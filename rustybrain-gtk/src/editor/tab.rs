@@ -0,0 +1,406 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gdk::{Key, ModifierType};
+use gtk::{
+    gio, glib, prelude::*, DropTarget, EventControllerFocus,
+    EventControllerKey, GestureClick, Inhibit, ScrolledWindow, TextView,
+};
+use relm4::send;
+use rustybrain_core::kasten::{self, Kasten};
+
+use super::complete::Completion;
+use super::{link_at, EditingZettel, Links, Msg, Saving};
+
+/// Notes opened in one tab, the zettel on top of the stack is shown.
+pub struct Tab {
+    pub view: TextView,
+    pub stack: Vec<EditingZettel>,
+    /// Offers zettels for the link being typed.
+    pub completion: Rc<Completion>,
+    links: Links,
+
+    page: ScrolledWindow,
+    head: gtk::Box,
+    label: gtk::Label,
+}
+
+/// Tabs shown side by side with other panes.
+pub struct Pane {
+    pub notebook: gtk::Notebook,
+    pub tabs: Vec<Tab>,
+}
+
+impl Tab {
+    pub fn new(
+        kasten: Rc<RefCell<Kasten>>,
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let view = gtk::TextView::builder()
+            .vexpand(true)
+            .hexpand(true)
+            .pixels_inside_wrap(10)
+            .wrap_mode(gtk::WrapMode::Char)
+            .build();
+        let page = ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(10)
+            .margin_bottom(10)
+            .child(&view)
+            .build();
+
+        let label = gtk::Label::new(None);
+        let close_btn = gtk::Button::builder()
+            .icon_name("window-close-symbolic")
+            .has_frame(false)
+            .tooltip_text("Close tab")
+            .build();
+        let (s, v) = (sender.clone(), view.clone());
+        close_btn
+            .connect_clicked(move |_| send!(s, Msg::CloseTab(Some(v.clone()))));
+        let head = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(4)
+            .build();
+        head.append(&label);
+        head.append(&close_btn);
+
+        let focus_ctrl = EventControllerFocus::new();
+        let s = sender.clone();
+        focus_ctrl.connect_enter(move |c| {
+            if let Ok(view) = c.widget().downcast::<TextView>() {
+                send!(s, Msg::Focus(view));
+            }
+        });
+        view.add_controller(&focus_ctrl);
+
+        // Lines move as the view is resized, or its text is laid out again.
+        for adj in [view.hadjustment(), view.vadjustment()]
+            .into_iter()
+            .flatten()
+        {
+            let (s, v) = (sender.clone(), view.clone());
+            adj.connect_changed(move |_| send!(s, Msg::Relayout(v.clone())));
+        }
+
+        let completion = Rc::new(Completion::new(&view, sender.clone()));
+        let tab = Tab {
+            view,
+            stack: vec![],
+            completion,
+            links: Rc::new(RefCell::new(vec![])),
+            page,
+            head,
+            label,
+        };
+        tab.listen_completion_events(sender.clone());
+        tab.listen_link_events(kasten, sender.clone());
+        tab.listen_attach_events(sender);
+        tab
+    }
+
+    /// Zettel shown in the tab.
+    pub fn top(&self) -> Option<&EditingZettel> {
+        self.stack.last()
+    }
+
+    /// Save and close every zettel on the stack, the top first. A zettel
+    /// failing to save, or refused for a conflict, stays open with those
+    /// under it, returns whether all are closed.
+    pub fn close(
+        &mut self,
+        kasten: &mut Kasten,
+        parent_sender: relm4::Sender<crate::Msg>,
+    ) -> bool {
+        while let Some(z) = self.stack.last_mut() {
+            // The failure is reported by the save.
+            match z.save(kasten, parent_sender.clone()) {
+                Saving::Saved | Saving::Unmodified => self.stack.pop(),
+                Saving::Failed | Saving::Conflict => return false,
+            };
+        }
+        true
+    }
+
+    pub fn sync_links(&self) {
+        *self.links.borrow_mut() = match self.top() {
+            Some(z) => z.link_spans(),
+            None => vec![],
+        };
+    }
+
+    /// Show the zettel on top, with its title in the tab label.
+    pub fn sync_view(&self) {
+        let ez = match self.top() {
+            Some(ez) => ez,
+            None => return,
+        };
+        for (i, z) in self.stack.iter().enumerate() {
+            z.show_overlays(i + 1 == self.stack.len());
+        }
+        if self.view.buffer() != ez.buffer {
+            self.view.set_buffer(Some(&ez.buffer));
+            ez.place_overlays();
+        }
+        let title = ez.title.text();
+        let title = if title.is_empty() { "Untitled" } else { &title };
+        if ez.buffer.is_modified() {
+            self.label.set_text(&format!("• {}", title));
+        } else {
+            self.label.set_text(title);
+        }
+    }
+
+    /// Ctrl+click or Enter on a link follows it, hovering shows a preview.
+    fn listen_link_events(
+        &self,
+        kasten: Rc<RefCell<Kasten>>,
+        sender: relm4::Sender<Msg>,
+    ) {
+        let view = &self.view;
+
+        let click = GestureClick::new();
+        let (s, links) = (sender.clone(), self.links.clone());
+        click.connect_pressed(move |g, _, x, y| {
+            if !g.current_event_state().contains(ModifierType::CONTROL_MASK) {
+                return;
+            }
+            let view = match g.widget().downcast::<TextView>() {
+                Ok(view) => view,
+                Err(_) => return,
+            };
+            let (bx, by) = view.window_to_buffer_coords(
+                gtk::TextWindowType::Widget,
+                x as i32,
+                y as i32,
+            );
+            let target = view
+                .iter_at_location(bx, by)
+                .and_then(|iter| link_at(&links, iter.offset()));
+            if let Some(target) = target {
+                g.set_state(gtk::EventSequenceState::Claimed);
+                send!(s, Msg::ActivateLink(target));
+            }
+        });
+        view.add_controller(&click);
+
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let links = self.links.clone();
+        key_ctrl.connect_key_pressed(move |c, k, _, m| {
+            let mods = m & gtk::accelerator_get_default_mod_mask();
+            if !mods.is_empty() || (k != Key::Return && k != Key::KP_Enter) {
+                return Inhibit(false);
+            }
+            let offset = match c.widget().downcast::<TextView>() {
+                Ok(view) => view.buffer().cursor_position(),
+                Err(_) => return Inhibit(false),
+            };
+            match link_at(&links, offset) {
+                Some(target) => {
+                    send!(sender, Msg::ActivateLink(target));
+                    Inhibit(true)
+                }
+                None => Inhibit(false),
+            }
+        });
+        view.add_controller(&key_ctrl);
+
+        view.set_has_tooltip(true);
+        let links = self.links.clone();
+        view.connect_query_tooltip(move |view, x, y, keyboard, tooltip| {
+            let offset = if keyboard {
+                view.buffer().cursor_position()
+            } else {
+                let (bx, by) = view.window_to_buffer_coords(
+                    gtk::TextWindowType::Widget,
+                    x,
+                    y,
+                );
+                match view.iter_at_location(bx, by) {
+                    Some(iter) => iter.offset(),
+                    None => return false,
+                }
+            };
+            let target = match link_at(&links, offset) {
+                Some(target) => target,
+                None => return false,
+            };
+            if kasten::is_external(&target) {
+                tooltip.set_text(Some(&target));
+                return true;
+            }
+            let kasten = match kasten.try_borrow() {
+                Ok(kasten) => kasten,
+                Err(_) => return false,
+            };
+            match kasten.resolve_link(&target) {
+                Ok(z) => {
+                    let z = z.borrow();
+                    let preview = match z.first_paragraph() {
+                        Some(p) => format!("{}\n\n{}", z.title(), p),
+                        None => z.title().to_string(),
+                    };
+                    tooltip.set_text(Some(&preview));
+                }
+                Err(_) => tooltip.set_text(Some("No such note")),
+            }
+            true
+        });
+    }
+}
+
+impl Tab {
+    /// Keys choose among the zettels offered for the link being typed,
+    /// while they are.
+    fn listen_completion_events(&self, sender: relm4::Sender<Msg>) {
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let completion = self.completion.clone();
+        key_ctrl.connect_key_pressed(move |c, k, _, m| {
+            let mods = m & gtk::accelerator_get_default_mod_mask();
+            if !completion.is_shown() || !mods.is_empty() {
+                return Inhibit(false);
+            }
+            match k {
+                Key::Up => completion.step(-1),
+                Key::Down => completion.step(1),
+                Key::Escape => completion.hide(),
+                Key::Return | Key::KP_Enter | Key::Tab => {
+                    let view = c.widget().downcast::<TextView>();
+                    match (view, completion.selected()) {
+                        (Ok(view), Some(i)) => {
+                            send!(sender, Msg::Complete(view, i))
+                        }
+                        _ => return Inhibit(false),
+                    }
+                }
+                _ => return Inhibit(false),
+            }
+            Inhibit(true)
+        });
+        self.view.add_controller(&key_ctrl);
+    }
+}
+
+impl Drop for Tab {
+    fn drop(&mut self) {
+        self.completion.unparent();
+    }
+}
+
+impl Tab {
+    /// Files dropped on the view, or pasted with pictures, are attached to
+    /// the zettel.
+    ///
+    /// Files are taken one by one as `GFile`, lists of files are for GTK 4.6
+    /// and later.
+    fn listen_attach_events(&self, sender: relm4::Sender<Msg>) {
+        let view = &self.view;
+
+        let drop =
+            DropTarget::new(gio::File::static_type(), gdk::DragAction::COPY);
+        let s = sender.clone();
+        drop.connect_drop(move |target, value, x, y| {
+            let (view, path) = match (
+                target.widget().downcast::<TextView>(),
+                value.get::<gio::File>().ok().and_then(|f| f.path()),
+            ) {
+                (Ok(view), Some(path)) => (view, path),
+                _ => return false,
+            };
+            // Links go where the files are dropped.
+            let (bx, by) = view.window_to_buffer_coords(
+                gtk::TextWindowType::Widget,
+                x as i32,
+                y as i32,
+            );
+            if let Some(iter) = view.iter_at_location(bx, by) {
+                view.buffer().place_cursor(&iter);
+            }
+            send!(s, Msg::Attach(view, vec![path]));
+            true
+        });
+        view.add_controller(&drop);
+
+        view.connect_paste_clipboard(move |view| {
+            let clipboard = view.clipboard();
+            let formats = clipboard.formats();
+            let (v, s) = (view.clone(), sender.clone());
+            if formats.contains_type(gio::File::static_type()) {
+                view.stop_signal_emission_by_name("paste-clipboard");
+                clipboard.read_value_async(
+                    gio::File::static_type(),
+                    glib::PRIORITY_DEFAULT,
+                    None::<&gio::Cancellable>,
+                    move |value| {
+                        let path = value
+                            .ok()
+                            .and_then(|v| v.get::<gio::File>().ok())
+                            .and_then(|f| f.path());
+                        if let Some(path) = path {
+                            send!(s, Msg::Attach(v, vec![path]));
+                        }
+                    },
+                );
+            } else if formats.contains_type(gdk::Texture::static_type()) {
+                view.stop_signal_emission_by_name("paste-clipboard");
+                clipboard.read_texture_async(
+                    None::<&gio::Cancellable>,
+                    move |texture| {
+                        if let Ok(Some(texture)) = texture {
+                            send!(s, Msg::AttachPicture(v, texture));
+                        }
+                    },
+                );
+            }
+        });
+    }
+}
+
+impl Pane {
+    pub fn new(sender: relm4::Sender<Msg>) -> Self {
+        let notebook = gtk::Notebook::builder()
+            .scrollable(true)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        notebook.connect_switch_page(move |_, page, _| {
+            let view = page
+                .downcast_ref::<ScrolledWindow>()
+                .and_then(|w| w.child())
+                .and_then(|c| c.downcast::<TextView>().ok());
+            if let Some(view) = view {
+                send!(sender, Msg::Focus(view));
+            }
+        });
+        Pane {
+            notebook,
+            tabs: vec![],
+        }
+    }
+
+    /// Tab in front.
+    pub fn current(&self) -> Option<usize> {
+        self.notebook.current_page().map(|i| i as usize)
+    }
+
+    /// Append `tab`, and return its index.
+    pub fn push(&mut self, tab: Tab) -> usize {
+        self.notebook.append_page(&tab.page, Some(&tab.head));
+        self.tabs.push(tab);
+        self.tabs.len() - 1
+    }
+
+    pub fn remove(&mut self, i: usize) -> Tab {
+        self.notebook.remove_page(Some(i as u32));
+        self.tabs.remove(i)
+    }
+
+    pub fn select(&self, i: usize) {
+        self.notebook.set_current_page(Some(i as u32));
+    }
+}
@@ -1,19 +1,30 @@
+mod attachments;
 mod backlinks;
+mod command;
 mod editor;
 mod listview;
+mod merge;
 mod msg;
+mod outline;
+mod palette;
+mod replace;
 mod search;
+mod settings;
+mod snapshots;
+mod tasks;
+mod versions;
 
 use std::cell::RefCell;
+use std::ops::Range;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use gtk::gio;
 use gtk::ApplicationWindow;
 use gtk::CssProvider;
 use gtk::MessageType;
 use gtk::StyleContext;
-use gtk::{
-    prelude::*, CallbackAction, Shortcut, ShortcutController, ShortcutTrigger,
-};
+use gtk::{prelude::*, EventControllerKey, Inhibit};
 use relm4::send;
 use relm4::AppUpdate;
 use relm4::Components;
@@ -21,30 +32,155 @@ use relm4::Model;
 use relm4::RelmApp;
 use relm4::RelmComponent;
 use relm4::Widgets;
-use rustybrain_core::config::Config;
-use rustybrain_core::kasten::Kasten;
+use rustybrain_core::config::{Config, Theme};
+use rustybrain_core::history::History;
+use rustybrain_core::journal::Swap;
+use rustybrain_core::kasten::{Kasten, Mention};
+use rustybrain_core::state::State;
 use rustybrain_core::zettel::Zettel;
 
 #[derive(Clone, Debug)]
 pub enum Msg {
+    /// Quit, asking first if there are unsaved notes.
     Quit,
+    Exit,
+    SaveAndQuit,
+    DiscardAndQuit,
+    /// Count of unsaved notes in the editor.
+    Unsaved(usize),
+    /// Saving the zettel is refused for changes on disk.
+    Conflict(Rc<RefCell<Zettel>>),
+    /// Zettel is replaced by the text, resolved from a conflict or restored
+    /// from its versions.
+    Merged(Rc<RefCell<Zettel>>, String),
+    /// The merge of a conflict is cancelled.
+    Unresolved,
+    /// Reopen notes with edits left by a crashed session.
+    Recover(Vec<Swap>),
+    DiscardJournal,
     StartSearch,
+    /// Ask for the title of a new zettel.
+    StartNewZettel,
     StartInsert,
+    /// Pick a zettel to merge into the current one.
+    StartMerge,
+    MergeZettel(Rc<RefCell<Zettel>>),
+    /// Move the selection into a new zettel.
+    ExtractZettel,
+    /// Ask where to move the current zettel to.
+    StartMove,
+    MoveZettel(PathBuf),
+    /// Zettels are created or deleted, lists should catch up.
+    ZettelsChanged,
+    /// Find text in the focused zettel.
+    StartFind,
+    /// Replace text in all zettels.
+    StartReplace,
+    /// Zettels of the ids are changed by a replace, or by undoing it.
+    Replaced(Vec<String>),
+    StartPreferences,
+    StartPalette,
+    /// Show saved versions of the current zettel.
+    StartVersionHistory,
+    /// Show attached files no note links to, and links to missing files.
+    StartAttachments,
+    /// Ask to delete the count of attached files no note links to.
+    AskDeleteOrphans(usize),
+    /// Deleting them is confirmed, the editor tells which files open notes
+    /// link to.
+    DeleteOrphans,
+    /// Delete attached files no note links to, except the files.
+    DeleteOrphansExcept(Vec<PathBuf>),
+    /// Show open tasks of all zettels.
+    StartTasks,
+    OpenDaily,
+    /// Ask for a directory to export notes to.
+    StartExport,
+    ExportTo(PathBuf),
+    Reindex,
+    Save,
+    EditTitle,
+    ToggleList,
+    ToggleBacklinks,
+    ToggleVersions,
+    ToggleOutline,
+    /// Headings of the focused zettel, or the cursor among them, changed.
+    OutlineChanged(outline::Outline),
+    /// Put the cursor at the line of the focused zettel, from 0.
+    GotoLine(i32),
+    /// Fold the section of the heading at the line, or above the cursor.
+    ToggleFold(Option<i32>),
+    /// Select the next, or previous with a negative step, note of the list.
+    StepList(i32),
     Init(ApplicationWindow),
     ChangeZettel(Rc<RefCell<Zettel>>),
+    /// Open the zettel with the byte range of its content highlighted.
+    ChangeZettelAt(Rc<RefCell<Zettel>>, Range<usize>),
+    OpenInTab(Rc<RefCell<Zettel>>),
+    /// Pick a zettel to open in a new tab.
+    NewTab,
+    /// Pick a zettel to open in a new pane.
+    SplitPane,
+    CloseTab,
+    /// Zettel of another tab is focused in the editor.
+    Focused(Rc<RefCell<Zettel>>),
+    /// Zettels shown by the editor are changed, by panes and tabs.
+    LayoutChanged(Vec<Vec<String>>),
+    /// Open the zettel of the id, e.g. from the recent list.
+    Visit(String),
+    /// Open the zettel of the id with the cursor at the line, from 0.
+    VisitLine(String, i32),
+    Back,
+    Forward,
+    /// Window is shown, reopen the last notes or start searching.
+    Started,
     InsertZettel(Rc<RefCell<Zettel>>),
     /// Means insert current zettel to previous zettel after save.
     OpenZettelOnStack(Rc<RefCell<Zettel>>),
     NewZettel(String, bool),
     ShowMsg(MessageType, String),
+    ShowError(String, Rc<rustybrain_core::Error>),
+    /// Show the errors at once, one under another.
+    ShowErrors(String, Vec<Rc<rustybrain_core::Error>>),
+    SwitchWorkspace(String),
+    /// Config file is changed on disk.
+    ReloadConfig,
+    /// Config is reloaded, with the repo path before.
+    ConfigChanged(String),
+    /// Editor has saved and closed all zettels.
+    EditorClosed,
+    /// Rewrite the mention into a link to the zettel of the id.
+    LinkMention(Rc<Mention>, String),
+    MentionLinked,
+}
+
+/// What to do once the editor has closed all zettels.
+enum AfterClose {
+    /// Switch to the workspace of the name.
+    Switch(String),
+    /// Open the repo of config, with the repo path before.
+    Reload(String),
 }
 
 pub struct AppModel {
-    show_list: bool,
-    show_back: bool,
+    state: State,
+    /// Bumped on every config reload, for widgets to catch up.
+    config_version: usize,
 
     config: Rc<RefCell<Config>>,
     kasten: Rc<RefCell<Kasten>>,
+    commands: Rc<RefCell<command::Registry>>,
+    /// Ids of zettels opened in this session.
+    history: History<String>,
+    unsaved: usize,
+
+    app_win: Option<ApplicationWindow>,
+    /// Kept alive until the user picks a directory.
+    export_chooser: Option<gtk::FileChooserNative>,
+    move_chooser: Option<gtk::FileChooserNative>,
+    /// Waiting for the editor to close, the kasten can't be swapped before
+    /// its zettels are saved.
+    after_close: Option<AfterClose>,
 }
 
 pub struct AppComponents {
@@ -53,6 +189,15 @@ pub struct AppComponents {
     backlinks: RelmComponent<backlinks::Model, AppModel>,
     search: RelmComponent<search::Model, AppModel>,
     msg: RelmComponent<msg::Model, AppModel>,
+    settings: RelmComponent<settings::Model, AppModel>,
+    palette: RelmComponent<palette::Model, AppModel>,
+    merge: RelmComponent<merge::Model, AppModel>,
+    versions: RelmComponent<versions::Model, AppModel>,
+    snapshots: RelmComponent<snapshots::Model, AppModel>,
+    attachments: RelmComponent<attachments::Model, AppModel>,
+    tasks: RelmComponent<tasks::Model, AppModel>,
+    replace: RelmComponent<replace::Model, AppModel>,
+    outline: RelmComponent<outline::Model, AppModel>,
 }
 
 impl Components<AppModel> for AppComponents {
@@ -65,7 +210,19 @@ impl Components<AppModel> for AppComponents {
             listview: RelmComponent::new(parent_model, parent_sender.clone()),
             backlinks: RelmComponent::new(parent_model, parent_sender.clone()),
             search: RelmComponent::new(parent_model, parent_sender.clone()),
-            msg: RelmComponent::new(parent_model, parent_sender),
+            msg: RelmComponent::new(parent_model, parent_sender.clone()),
+            settings: RelmComponent::new(parent_model, parent_sender.clone()),
+            palette: RelmComponent::new(parent_model, parent_sender.clone()),
+            merge: RelmComponent::new(parent_model, parent_sender.clone()),
+            versions: RelmComponent::new(parent_model, parent_sender.clone()),
+            snapshots: RelmComponent::new(parent_model, parent_sender.clone()),
+            attachments: RelmComponent::new(
+                parent_model,
+                parent_sender.clone(),
+            ),
+            tasks: RelmComponent::new(parent_model, parent_sender.clone()),
+            replace: RelmComponent::new(parent_model, parent_sender.clone()),
+            outline: RelmComponent::new(parent_model, parent_sender),
         }
     }
 
@@ -74,11 +231,21 @@ impl Components<AppModel> for AppComponents {
 
 pub struct AppWidgets {
     window: ApplicationWindow,
+    workspaces: gtk::DropDown,
+    back_btn: gtk::Button,
+    forward_btn: gtk::Button,
+    recent: gtk::ListBox,
+    config_version: usize,
+
+    #[allow(dead_code)]
+    config_monitor: Option<gio::FileMonitor>,
 
     main_layout: gtk::Box,
     left: gtk::ScrolledWindow,
     center: gtk::Box,
     right: gtk::ScrolledWindow,
+    versions: gtk::Box,
+    outline: gtk::ScrolledWindow,
 }
 
 impl Model for AppModel {
@@ -96,15 +263,174 @@ impl AppUpdate for AppModel {
         components: &Self::Components,
         sender: relm4::Sender<Self::Msg>,
     ) -> bool {
+        let state = self.state.clone();
         match msg {
-            Msg::Quit => relm4::gtk_application().quit(),
+            Msg::Quit => {
+                if self.unsaved > 0 {
+                    self.ask_quit(&sender);
+                } else {
+                    send!(sender, Msg::Exit);
+                }
+            }
+            Msg::Exit => {
+                self.save_state(&sender);
+                relm4::gtk_application().quit()
+            }
+            Msg::SaveAndQuit => {
+                send!(components.editor.sender(), editor::Msg::SaveAndQuit)
+            }
+            Msg::DiscardAndQuit => {
+                send!(components.editor.sender(), editor::Msg::DiscardAndQuit)
+            }
+            Msg::Unsaved(n) => self.unsaved = n,
+            Msg::Conflict(z) => {
+                send!(components.merge.sender(), merge::Msg::Show(z))
+            }
+            Msg::Merged(z, text) => {
+                send!(components.editor.sender(), editor::Msg::Merged(z, text))
+            }
+            Msg::Unresolved => {
+                send!(components.editor.sender(), editor::Msg::Unresolved)
+            }
+            Msg::Recover(swaps) => {
+                let journal = self.kasten.borrow().journal();
+                for swap in swaps {
+                    let resolved = self.kasten.borrow().resolve(&swap.zid);
+                    match resolved {
+                        Ok(z) => send!(
+                            components.editor.sender(),
+                            editor::Msg::Recover(z, swap)
+                        ),
+                        // The note is gone, nothing to recover into.
+                        Err(_) => {
+                            let _ = journal.remove(&swap.zid);
+                        }
+                    }
+                }
+            }
+            Msg::DiscardJournal => self.discard_journal(&sender),
+            Msg::SwitchWorkspace(name) => {
+                if name != self.config.borrow().workspace() {
+                    self.after_close = Some(AfterClose::Switch(name));
+                    send!(components.editor.sender(), editor::Msg::Close);
+                }
+            }
             Msg::ChangeZettel(z) => {
-                send!(components.editor.sender(), editor::Msg::Open(z.clone()));
+                self.history.visit(z.borrow().zid().to_string());
+                self.open(z, components);
+            }
+            Msg::ChangeZettelAt(z, range) => {
+                self.history.visit(z.borrow().zid().to_string());
+                self.open(z, components);
+                send!(
+                    components.editor.sender(),
+                    editor::Msg::Highlight(range)
+                );
+            }
+            Msg::OpenInTab(z) => {
+                send!(components.editor.sender(), editor::Msg::OpenInTab(z))
+            }
+            Msg::NewTab => {
+                send!(
+                    components.search.sender(),
+                    search::Msg::Show(search::Mode::InTab)
+                )
+            }
+            Msg::SplitPane => {
+                send!(components.editor.sender(), editor::Msg::Split);
+                send!(
+                    components.search.sender(),
+                    search::Msg::Show(search::Mode::InTab)
+                );
+            }
+            Msg::CloseTab => {
+                send!(components.editor.sender(), editor::Msg::CloseTab(None))
+            }
+            Msg::Focused(z) => {
+                let zid = z.borrow().zid().to_string();
+                if self.history.current() != Some(&zid) {
+                    self.history.visit(zid.clone());
+                }
+                self.state.visit(&zid);
+                send!(
+                    components.versions.sender(),
+                    versions::Msg::ChangeZettel(z.clone())
+                );
                 send!(
                     components.backlinks.sender(),
                     backlinks::Msg::ChangeZettel(z)
                 );
             }
+            Msg::LayoutChanged(layout) => self.state.set_layout(layout),
+            Msg::Visit(zid) => {
+                let resolved = self.kasten.borrow().resolve(&zid);
+                match resolved {
+                    Ok(z) => send!(sender, Msg::ChangeZettel(z)),
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowError(
+                            "Open note failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::VisitLine(zid, line) => {
+                let resolved = self.kasten.borrow().resolve(&zid);
+                match resolved {
+                    Ok(z) => {
+                        self.history.visit(zid);
+                        self.open(z, components);
+                        send!(
+                            components.editor.sender(),
+                            editor::Msg::GotoLine(line)
+                        );
+                    }
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowError(
+                            "Open note failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::Back | Msg::Forward => {
+                let back = matches!(msg, Msg::Back);
+                let zid = if back {
+                    self.history.peek_back().cloned()
+                } else {
+                    self.history.peek_forward().cloned()
+                };
+                if let Some(zid) = zid {
+                    let resolved = self.kasten.borrow().resolve(&zid);
+                    match resolved {
+                        // Moved only once the zettel is there to open.
+                        Ok(z) => {
+                            if back {
+                                self.history.back();
+                            } else {
+                                self.history.forward();
+                            }
+                            self.open(z, components)
+                        }
+                        Err(e) => send!(
+                            sender,
+                            Msg::ShowError(
+                                "Open note failed!".to_string(),
+                                Rc::new(e)
+                            )
+                        ),
+                    }
+                }
+            }
+            Msg::Started => {
+                let restored = self.restore_layout(components, &sender);
+                let recovering = self.ask_recover(&sender);
+                if !restored && !recovering {
+                    send!(sender, Msg::StartSearch);
+                }
+            }
             Msg::InsertZettel(z) => {
                 send!(components.editor.sender(), editor::Msg::Insert(z))
             }
@@ -112,20 +438,264 @@ impl AppUpdate for AppModel {
                 send!(components.editor.sender(), editor::Msg::OpenOnStack(z))
             }
             Msg::Init(w) => {
+                self.reload_commands(&sender);
+                self.app_win = Some(w.clone());
+                send!(
+                    components.palette.sender(),
+                    palette::Msg::Init(w.clone())
+                );
+                send!(components.merge.sender(), merge::Msg::Init(w.clone()));
+                send!(
+                    components.snapshots.sender(),
+                    snapshots::Msg::Init(w.clone())
+                );
+                send!(
+                    components.attachments.sender(),
+                    attachments::Msg::Init(w.clone())
+                );
+                send!(components.tasks.sender(), tasks::Msg::Init(w.clone()));
+                send!(
+                    components.replace.sender(),
+                    replace::Msg::Init(w.clone())
+                );
+                send!(
+                    components.settings.sender(),
+                    settings::Msg::Init(w.clone())
+                );
                 send!(
                     components.search.sender(),
                     search::Msg::Init(w, self.kasten.clone())
                 )
             }
+            Msg::StartPreferences => {
+                send!(components.settings.sender(), settings::Msg::Show)
+            }
+            Msg::ReloadConfig => {
+                let old_repo = self.config.borrow().repo_path().to_string();
+                let reloaded = self.config.borrow_mut().reload();
+                match reloaded {
+                    Ok(true) => send!(sender, Msg::ConfigChanged(old_repo)),
+                    Ok(false) => {}
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowError(
+                            "Reload config failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::ConfigChanged(old_repo) => {
+                self.config_version += 1;
+                self.reload_commands(&sender);
+                send!(components.editor.sender(), editor::Msg::Restyle);
+                if old_repo != self.config.borrow().repo_path() {
+                    self.after_close = Some(AfterClose::Reload(old_repo));
+                    send!(components.editor.sender(), editor::Msg::Close);
+                }
+            }
+            Msg::EditorClosed => match self.after_close.take() {
+                Some(AfterClose::Switch(name)) => {
+                    self.switch_workspace(&name, components, &sender)
+                }
+                Some(AfterClose::Reload(old_repo)) => {
+                    self.save_state_at(&old_repo, &sender);
+                    self.reload_kasten(components, &sender);
+                }
+                None => {}
+            },
+            Msg::StartPalette => {
+                send!(components.palette.sender(), palette::Msg::Show)
+            }
+            Msg::StartVersionHistory => {
+                let current = self
+                    .history
+                    .current()
+                    .and_then(|zid| self.kasten.borrow().resolve(zid).ok());
+                if let Some(z) = current {
+                    send!(
+                        components.snapshots.sender(),
+                        snapshots::Msg::Show(z)
+                    )
+                }
+            }
+            Msg::StartAttachments => {
+                send!(components.attachments.sender(), attachments::Msg::Show)
+            }
+            Msg::AskDeleteOrphans(n) => self.ask_delete_orphans(n, &sender),
+            Msg::DeleteOrphans => {
+                send!(components.editor.sender(), editor::Msg::LinkedFiles)
+            }
+            Msg::DeleteOrphansExcept(files) => send!(
+                components.attachments.sender(),
+                attachments::Msg::DeleteExcept(files)
+            ),
+            Msg::StartTasks => {
+                send!(components.tasks.sender(), tasks::Msg::Show)
+            }
+            Msg::OpenDaily => {
+                let daily = self.kasten.borrow_mut().daily();
+                match daily {
+                    Ok(z) => send!(sender, Msg::ChangeZettel(z)),
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowError(
+                            "Open daily note failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::StartExport => self.start_export(&sender),
+            Msg::ExportTo(dir) => {
+                self.export_chooser = None;
+                match self.kasten.borrow().export(&dir) {
+                    Ok(n) => send!(
+                        sender,
+                        Msg::ShowMsg(
+                            MessageType::Info,
+                            format!(
+                                "Exported {} notes to {}",
+                                n,
+                                dir.display()
+                            )
+                        )
+                    ),
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowError(
+                            "Export notes failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::Reindex => {
+                let reindexed = self.kasten.borrow_mut().reindex();
+                match reindexed {
+                    Ok(_) => Self::reload_components(components),
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowError(
+                            "Reindex slip-box failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::Save => send!(components.editor.sender(), editor::Msg::Save),
+            Msg::EditTitle => {
+                send!(components.editor.sender(), editor::Msg::EditTitle)
+            }
+            Msg::ToggleList => {
+                let show = self.state.show_list();
+                self.state.set_show_list(!show);
+            }
+            Msg::ToggleBacklinks => {
+                let show = self.state.show_back();
+                self.state.set_show_back(!show);
+            }
+            Msg::ToggleVersions => {
+                let show = self.state.show_versions();
+                self.state.set_show_versions(!show);
+                send!(components.versions.sender(), versions::Msg::Show(!show))
+            }
+            Msg::ToggleOutline => {
+                let show = self.state.show_outline();
+                self.state.set_show_outline(!show);
+            }
+            Msg::OutlineChanged(o) => {
+                send!(components.outline.sender(), outline::Msg::Change(o))
+            }
+            Msg::GotoLine(line) => {
+                send!(components.editor.sender(), editor::Msg::GotoLine(line))
+            }
+            Msg::ToggleFold(line) => {
+                send!(components.editor.sender(), editor::Msg::ToggleFold(line))
+            }
+            Msg::StepList(step) => {
+                send!(components.listview.sender(), listview::Msg::Step(step))
+            }
             Msg::StartSearch => {
-                send!(components.search.sender(), search::Msg::Show(false))
+                send!(
+                    components.search.sender(),
+                    search::Msg::Show(search::Mode::Open)
+                )
+            }
+            Msg::StartNewZettel => {
+                send!(
+                    components.search.sender(),
+                    search::Msg::Show(search::Mode::Create)
+                )
             }
             Msg::StartInsert => {
-                send!(components.search.sender(), search::Msg::Show(true))
+                send!(
+                    components.search.sender(),
+                    search::Msg::Show(search::Mode::Insert)
+                )
+            }
+            Msg::StartMerge => {
+                send!(
+                    components.search.sender(),
+                    search::Msg::Show(search::Mode::Merge)
+                )
+            }
+            Msg::MergeZettel(z) => {
+                send!(components.editor.sender(), editor::Msg::MergeIn(z))
+            }
+            Msg::ExtractZettel => {
+                send!(components.editor.sender(), editor::Msg::Extract)
+            }
+            Msg::StartMove => self.start_move(&sender),
+            Msg::MoveZettel(path) => {
+                self.move_chooser = None;
+                let repo_path = self.kasten.borrow().repo_path();
+                match path.strip_prefix(&repo_path) {
+                    Ok(rel) => send!(
+                        components.editor.sender(),
+                        editor::Msg::Move(format!("@/{}", rel.display()))
+                    ),
+                    Err(_) => send!(
+                        sender,
+                        Msg::ShowMsg(
+                            MessageType::Warning,
+                            "A note can only move inside the slip-box."
+                                .to_string()
+                        )
+                    ),
+                }
+            }
+            Msg::LinkMention(m, zid) => send!(
+                components.editor.sender(),
+                editor::Msg::LinkMention(m, zid)
+            ),
+            Msg::MentionLinked => {
+                send!(components.backlinks.sender(), backlinks::Msg::Refresh)
+            }
+            Msg::ZettelsChanged => {
+                send!(components.listview.sender(), listview::Msg::Reload);
+                send!(components.search.sender(), search::Msg::Reload);
+            }
+            Msg::StartFind => {
+                send!(components.editor.sender(), editor::Msg::StartFind)
+            }
+            Msg::StartReplace => {
+                send!(components.replace.sender(), replace::Msg::Show)
+            }
+            Msg::Replaced(zids) => {
+                send!(components.editor.sender(), editor::Msg::Reload(zids));
+                send!(components.listview.sender(), listview::Msg::Reload);
+                send!(components.search.sender(), search::Msg::Reload);
             }
             Msg::ShowMsg(t, s) => {
                 send!(components.msg.sender(), msg::Msg::Show(t, s))
             }
+            Msg::ShowError(s, e) => {
+                send!(components.msg.sender(), msg::Msg::ShowError(s, e))
+            }
+            Msg::ShowErrors(s, errors) => {
+                send!(components.msg.sender(), msg::Msg::ShowErrors(s, errors))
+            }
             Msg::NewZettel(title, inserting) => {
                 match self.kasten.borrow_mut().create(&title) {
                     Ok(z) => {
@@ -137,16 +707,346 @@ impl AppUpdate for AppModel {
                     }
                     Err(e) => send!(
                         sender,
-                        Msg::ShowMsg(
-                            MessageType::Error,
-                            format!("Create note failed: {:?}!", e)
+                        Msg::ShowError(
+                            "Create note failed!".to_string(),
+                            Rc::new(e)
                         )
                     ),
                 }
             }
         }
+        // Kept on disk as it changes, not lost if the app is killed.
+        if self.state != state {
+            self.save_state(&sender);
+        }
+        true
+    }
+}
+
+impl AppModel {
+    fn open(&mut self, z: Rc<RefCell<Zettel>>, components: &AppComponents) {
+        self.state.visit(z.borrow().zid());
+        send!(components.editor.sender(), editor::Msg::Open(z.clone()));
+        send!(
+            components.versions.sender(),
+            versions::Msg::ChangeZettel(z.clone())
+        );
+        send!(
+            components.backlinks.sender(),
+            backlinks::Msg::ChangeZettel(z)
+        );
+    }
+
+    /// Rebuild key bindings from config, and report the bad ones.
+    fn reload_commands(&self, sender: &relm4::Sender<Msg>) {
+        let (registry, errors) = command::Registry::new(&self.config.borrow());
+        *self.commands.borrow_mut() = registry;
+        if !errors.is_empty() {
+            send!(
+                sender,
+                Msg::ShowErrors(
+                    "Invalid key bindings in config!".to_string(),
+                    errors.into_iter().map(Rc::new).collect()
+                )
+            );
+        }
+    }
+
+    fn save_state(&self, sender: &relm4::Sender<Msg>) {
+        let repo = self.kasten.borrow().repo_path();
+        self.save_state_at(&repo, sender);
+    }
+
+    fn save_state_at(&self, repo: &str, sender: &relm4::Sender<Msg>) {
+        if let Err(e) = self.state.save(repo) {
+            send!(
+                sender,
+                Msg::ShowError("Save state failed!".to_string(), Rc::new(e))
+            );
+        }
+    }
+
+    /// Open another slip-box, and reload every component from it. The
+    /// editor should be closed before.
+    fn switch_workspace(
+        &mut self,
+        name: &str,
+        components: &AppComponents,
+        sender: &relm4::Sender<Msg>,
+    ) {
+        self.save_state(sender);
+
+        let switched = self.config.borrow_mut().switch_workspace(name);
+        if let Err(e) = switched {
+            send!(
+                sender,
+                Msg::ShowError(
+                    format!("Switch to workspace {} failed!", name),
+                    Rc::new(e)
+                )
+            );
+            return;
+        }
+        self.reload_kasten(components, sender);
+    }
+
+    /// Build kasten from the repo of config, and reload every component.
+    fn reload_kasten(
+        &mut self,
+        components: &AppComponents,
+        sender: &relm4::Sender<Msg>,
+    ) {
+        match Kasten::new(self.config.clone()) {
+            Ok(kasten) => *self.kasten.borrow_mut() = kasten,
+            Err(e) => {
+                send!(
+                    sender,
+                    Msg::ShowError("Open repo failed!".to_string(), Rc::new(e))
+                );
+                return;
+            }
+        }
+        let repo = self.kasten.borrow().repo_path();
+        self.state = State::load(&repo).unwrap_or_else(|e| {
+            send!(
+                sender,
+                Msg::ShowError("Load state failed!".to_string(), Rc::new(e))
+            );
+            State::default()
+        });
+        self.history = History::default();
+        Self::reload_components(components);
+        self.restore_layout(components, sender);
+    }
+
+    /// Ask to recover edits left in the journal, return false if there are
+    /// none.
+    fn ask_recover(&self, sender: &relm4::Sender<Msg>) -> bool {
+        let swaps = match self.kasten.borrow().journal().entries() {
+            Ok(swaps) if !swaps.is_empty() => swaps,
+            Ok(_) => return false,
+            Err(e) => {
+                send!(
+                    sender,
+                    Msg::ShowError(
+                        "Read journal failed!".to_string(),
+                        Rc::new(e)
+                    )
+                );
+                return false;
+            }
+        };
+        let detail = format!(
+            "{} notes were not saved when RustyBrain exited last time.",
+            swaps.len()
+        );
+        self.ask(
+            "Recover unsaved changes?",
+            &detail,
+            &[
+                ("Discard", gtk::ResponseType::Reject),
+                ("Recover", gtk::ResponseType::Accept),
+            ],
+            sender,
+            move |resp| match resp {
+                gtk::ResponseType::Accept => Some(Msg::Recover(swaps.clone())),
+                gtk::ResponseType::Reject => Some(Msg::DiscardJournal),
+                _ => None,
+            },
+        );
         true
     }
+
+    fn ask_quit(&self, sender: &relm4::Sender<Msg>) {
+        let detail =
+            format!("{} notes have changes that are not saved.", self.unsaved);
+        self.ask(
+            "Save changes before quitting?",
+            &detail,
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Quit without saving", gtk::ResponseType::Reject),
+                ("Save", gtk::ResponseType::Accept),
+            ],
+            sender,
+            |resp| match resp {
+                gtk::ResponseType::Accept => Some(Msg::SaveAndQuit),
+                gtk::ResponseType::Reject => Some(Msg::DiscardAndQuit),
+                _ => None,
+            },
+        );
+    }
+
+    fn ask_delete_orphans(&self, n: usize, sender: &relm4::Sender<Msg>) {
+        let detail = format!(
+            "{} attached files no saved note links to will be deleted, \
+             except those linked from open notes.",
+            n
+        );
+        self.ask(
+            "Delete unused files?",
+            &detail,
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Delete", gtk::ResponseType::Accept),
+            ],
+            sender,
+            |resp| match resp {
+                gtk::ResponseType::Accept => Some(Msg::DeleteOrphans),
+                _ => None,
+            },
+        );
+    }
+
+    /// Show a question, the message `respond` makes of the answer is sent.
+    fn ask<F>(
+        &self,
+        text: &str,
+        detail: &str,
+        buttons: &[(&str, gtk::ResponseType)],
+        sender: &relm4::Sender<Msg>,
+        respond: F,
+    ) where
+        F: Fn(gtk::ResponseType) -> Option<Msg> + 'static,
+    {
+        let dialog = gtk::MessageDialog::builder()
+            .modal(true)
+            .message_type(MessageType::Question)
+            .text(text)
+            .secondary_text(detail)
+            .build();
+        dialog.set_transient_for(self.app_win.as_ref());
+        for (label, resp) in buttons {
+            dialog.add_button(label, *resp);
+        }
+        let s = sender.clone();
+        dialog.connect_response(move |d, resp| {
+            d.close();
+            if let Some(msg) = respond(resp) {
+                send!(s, msg);
+            }
+        });
+        dialog.show();
+    }
+
+    fn discard_journal(&self, sender: &relm4::Sender<Msg>) {
+        if let Err(e) = self.kasten.borrow().journal().clear() {
+            send!(
+                sender,
+                Msg::ShowError("Clear journal failed!".to_string(), Rc::new(e))
+            );
+        }
+    }
+
+    /// Reopen tabs of last session, or the last opened zettel. Return false
+    /// if nothing is opened.
+    fn restore_layout(
+        &self,
+        components: &AppComponents,
+        sender: &relm4::Sender<Msg>,
+    ) -> bool {
+        let kasten = self.kasten.borrow();
+        let layout: Vec<Vec<_>> = self
+            .state
+            .layout()
+            .iter()
+            .map(|zids| {
+                zids.iter()
+                    .filter_map(|zid| kasten.resolve(zid).ok())
+                    .collect()
+            })
+            .filter(|zs: &Vec<_>| !zs.is_empty())
+            .collect();
+        if !layout.is_empty() {
+            send!(components.editor.sender(), editor::Msg::Restore(layout));
+            return true;
+        }
+        match self.state.last().and_then(|zid| kasten.resolve(zid).ok()) {
+            Some(z) => {
+                send!(sender, Msg::ChangeZettel(z));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Let components pick up the rebuilt kasten.
+    fn reload_components(components: &AppComponents) {
+        send!(components.listview.sender(), listview::Msg::Reload);
+        send!(components.backlinks.sender(), backlinks::Msg::Clear);
+        send!(components.outline.sender(), outline::Msg::Clear);
+        send!(components.versions.sender(), versions::Msg::Clear);
+        send!(components.search.sender(), search::Msg::Reload);
+    }
+
+    fn start_export(&mut self, sender: &relm4::Sender<Msg>) {
+        let chooser = gtk::FileChooserNative::new(
+            Some("Export notes"),
+            self.app_win.as_ref(),
+            gtk::FileChooserAction::SelectFolder,
+            Some("Export"),
+            Some("Cancel"),
+        );
+        chooser.set_modal(true);
+        let s = sender.clone();
+        chooser.connect_response(move |c, resp| {
+            let dir = c.file().and_then(|f| f.path());
+            match dir {
+                Some(dir) if resp == gtk::ResponseType::Accept => {
+                    send!(s, Msg::ExportTo(dir))
+                }
+                _ => {}
+            }
+        });
+        chooser.show();
+        self.export_chooser = Some(chooser);
+    }
+
+    /// Ask for the file to move the current zettel to, in the slip-box.
+    fn start_move(&mut self, sender: &relm4::Sender<Msg>) {
+        let current = self
+            .history
+            .current()
+            .and_then(|zid| self.kasten.borrow().resolve(zid).ok());
+        let path = match current {
+            Some(z) => z.borrow().path().to_path_buf(),
+            None => {
+                return send!(
+                    sender,
+                    Msg::ShowMsg(
+                        MessageType::Warning,
+                        "Open the note to move first.".to_string()
+                    )
+                )
+            }
+        };
+        let chooser = gtk::FileChooserNative::new(
+            Some("Move note"),
+            self.app_win.as_ref(),
+            gtk::FileChooserAction::Save,
+            Some("Move"),
+            Some("Cancel"),
+        );
+        chooser.set_modal(true);
+        if let Some(dir) = path.parent() {
+            let _ = chooser.set_current_folder(&gio::File::for_path(dir));
+        }
+        if let Some(name) = path.file_name() {
+            chooser.set_current_name(&name.to_string_lossy());
+        }
+        let s = sender.clone();
+        chooser.connect_response(move |c, resp| {
+            let path = c.file().and_then(|f| f.path());
+            match path {
+                Some(path) if resp == gtk::ResponseType::Accept => {
+                    send!(s, Msg::MoveZettel(path))
+                }
+                _ => {}
+            }
+        });
+        chooser.show();
+        self.move_chooser = Some(chooser);
+    }
 }
 
 impl Widgets<AppModel, ()> for AppWidgets {
@@ -164,6 +1064,57 @@ impl Widgets<AppModel, ()> for AppWidgets {
         window.set_default_size(1200, 800);
         send!(sender, Msg::Init(window.clone()));
 
+        let header = gtk::HeaderBar::new();
+        let c = (*model.config).borrow();
+        let workspaces = gtk::DropDown::from_strings(&c.workspaces());
+        workspaces.set_tooltip_text(Some("Workspace"));
+        let s = sender.clone();
+        workspaces.connect_selected_notify(move |dd| {
+            let name = dd
+                .selected_item()
+                .and_then(|item| item.downcast::<gtk::StringObject>().ok())
+                .map(|item| item.string().to_string());
+            if let Some(name) = name {
+                send!(s, Msg::SwitchWorkspace(name));
+            }
+        });
+        let back_btn = gtk::Button::from_icon_name("go-previous-symbolic");
+        back_btn.set_tooltip_text(Some("Back"));
+        let s = sender.clone();
+        back_btn.connect_clicked(move |_| send!(s, Msg::Back));
+        let forward_btn = gtk::Button::from_icon_name("go-next-symbolic");
+        forward_btn.set_tooltip_text(Some("Forward"));
+        let s = sender.clone();
+        forward_btn.connect_clicked(move |_| send!(s, Msg::Forward));
+
+        let recent = gtk::ListBox::new();
+        let recent_btn = gtk::MenuButton::builder()
+            .icon_name("document-open-recent-symbolic")
+            .tooltip_text("Recent notes")
+            .popover(&gtk::Popover::builder().child(&recent).build())
+            .build();
+        let (s, btn) = (sender.clone(), recent_btn.clone());
+        recent.connect_row_activated(move |_, row| {
+            btn.popdown();
+            send!(s, Msg::Visit(row.widget_name().to_string()));
+        });
+
+        header.pack_start(&back_btn);
+        header.pack_start(&forward_btn);
+        header.pack_start(&recent_btn);
+        header.pack_start(&workspaces);
+        let pref_btn = gtk::Button::from_icon_name("emblem-system-symbolic");
+        pref_btn.set_tooltip_text(Some("Preferences"));
+        let s = sender.clone();
+        pref_btn.connect_clicked(move |_| send!(s, Msg::StartPreferences));
+        header.pack_end(&pref_btn);
+        let palette_btn = gtk::Button::from_icon_name("open-menu-symbolic");
+        palette_btn.set_tooltip_text(Some("Commands"));
+        let s = sender.clone();
+        palette_btn.connect_clicked(move |_| send!(s, Msg::StartPalette));
+        header.pack_end(&palette_btn);
+        window.set_titlebar(Some(&header));
+
         let box_ = gtk::Box::builder()
             .orientation(gtk::Orientation::Horizontal)
             .spacing(6)
@@ -174,38 +1125,63 @@ impl Widgets<AppModel, ()> for AppWidgets {
         let left = components.listview.root_widget().clone();
         let center = components.editor.root_widget().clone();
         let right = components.backlinks.root_widget().clone();
+        let versions = components.versions.root_widget().clone();
+        let outline = components.outline.root_widget().clone();
 
         window.set_child(Some(&box_));
 
-        let shortcut_ctrl = ShortcutController::builder()
-            .scope(gtk::ShortcutScope::Global)
-            .build();
+        let key_ctrl = EventControllerKey::new();
+        // Bubble phase, keys the focused widget takes, like typing in a
+        // note, never reach bindings.
+        key_ctrl.set_propagation_phase(gtk::PropagationPhase::Bubble);
+        let (s, commands) = (sender.clone(), model.commands.clone());
+        key_ctrl.connect_key_pressed(move |_, k, _, m| {
+            match commands.borrow_mut().press(k, m) {
+                command::Press::Fire(msg) => send!(s, msg),
+                command::Press::Pending => {}
+                command::Press::Unbound => return Inhibit(false),
+            }
+            Inhibit(true)
+        });
+        window.add_controller(&key_ctrl);
+        Self::apply_theme(&c);
 
-        let c = (*model.config).borrow();
-        shortcut_ctrl.add_shortcut(&Self::bind_key(
-            sender.clone(),
-            c.shortcut().find(),
-            Msg::StartSearch,
-        ));
-        shortcut_ctrl.add_shortcut(&Self::bind_key(
-            sender.clone(),
-            c.shortcut().insert(),
-            Msg::StartInsert,
-        ));
-        shortcut_ctrl.add_shortcut(&Self::bind_key(
-            sender.clone(),
-            c.shortcut().quit(),
-            Msg::Quit,
-        ));
-        window.add_controller(&shortcut_ctrl);
-        window.connect_show(move |_| send!(sender, Msg::StartSearch));
+        let monitor = gio::File::for_path(c.path())
+            .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+            .ok();
+        if let Some(m) = monitor.as_ref() {
+            let s = sender.clone();
+            m.connect_changed(move |_, _, _, event| {
+                if matches!(
+                    event,
+                    gio::FileMonitorEvent::ChangesDoneHint
+                        | gio::FileMonitorEvent::Created
+                ) {
+                    send!(s, Msg::ReloadConfig);
+                }
+            });
+        }
+        let s = sender.clone();
+        window.connect_close_request(move |_| {
+            send!(s, Msg::Quit);
+            Inhibit(true)
+        });
+        window.connect_show(move |_| send!(sender, Msg::Started));
 
         AppWidgets {
             window,
+            workspaces,
+            back_btn,
+            forward_btn,
+            recent,
+            config_version: model.config_version,
+            config_monitor: monitor,
             main_layout: box_,
             left,
             right,
             center,
+            versions,
+            outline,
         }
     }
 
@@ -217,13 +1193,35 @@ impl Widgets<AppModel, ()> for AppWidgets {
         while let Some(c) = self.main_layout.last_child() {
             self.main_layout.remove(&c);
         }
-        if model.show_list {
+        let c = model.config.borrow();
+        if self.config_version != model.config_version {
+            self.config_version = model.config_version;
+            Self::apply_theme(&c);
+        }
+        if let Some(i) = c.workspaces().iter().position(|w| *w == c.workspace())
+        {
+            if self.workspaces.selected() != i as u32 {
+                self.workspaces.set_selected(i as u32);
+            }
+        }
+
+        self.back_btn.set_sensitive(model.history.can_back());
+        self.forward_btn.set_sensitive(model.history.can_forward());
+        self.fill_recent(model);
+
+        if model.state.show_list() {
             self.main_layout.append(&self.left);
         }
+        if model.state.show_outline() {
+            self.main_layout.append(&self.outline);
+        }
         self.main_layout.append(&self.center);
-        if model.show_back {
+        if model.state.show_back() {
             self.main_layout.append(&self.right);
         }
+        if model.state.show_versions() {
+            self.main_layout.append(&self.versions);
+        }
 
         let provider = CssProvider::new();
         provider.load_from_resource("/dev/rustybrain/app/assets/css/main.css");
@@ -236,28 +1234,55 @@ impl Widgets<AppModel, ()> for AppWidgets {
 }
 
 impl AppWidgets {
-    fn bind_key(sender: relm4::Sender<Msg>, key: &str, msg: Msg) -> Shortcut {
-        let action = CallbackAction::new(move |_, _| {
-            send!(sender, msg.clone());
-            true
-        });
-        let trigger = ShortcutTrigger::parse_string(key).unwrap();
-        Shortcut::builder()
-            .trigger(&trigger)
-            .action(&action)
-            .build()
+    fn fill_recent(&self, model: &AppModel) {
+        while let Some(c) = self.recent.last_child() {
+            self.recent.remove(&c);
+        }
+        let kasten = model.kasten.borrow();
+        for zid in model.state.recent() {
+            if let Ok(z) = kasten.resolve(zid) {
+                let label = gtk::Label::builder()
+                    .label(z.borrow().title())
+                    .halign(gtk::Align::Start)
+                    .build();
+                let row =
+                    gtk::ListBoxRow::builder().name(zid).child(&label).build();
+                self.recent.append(&row);
+            }
+        }
+    }
+
+    fn apply_theme(c: &Config) {
+        if let Some(settings) = gtk::Settings::default() {
+            let dark = c.editor().theme() == Theme::Dark;
+            settings.set_gtk_application_prefer_dark_theme(dark);
+        }
     }
 }
 
-pub fn run(config: Rc<RefCell<Config>>) {
+pub fn run(config: Rc<RefCell<Config>>) -> Result<(), rustybrain_core::Error> {
     gio::resources_register_include!("app.gresource").unwrap();
 
+    // Like a reload, a bad state file only loses the layout.
+    let state = State::load(config.borrow().repo_path()).unwrap_or_else(|e| {
+        eprintln!("Load state failed: {}", e);
+        State::default()
+    });
     let model = AppModel {
-        show_list: false,
-        show_back: true,
+        state,
+        config_version: 0,
         config: config.clone(),
-        kasten: Rc::new(RefCell::new(Kasten::new(config).unwrap())),
+        kasten: Rc::new(RefCell::new(Kasten::new(config)?)),
+        // Filled on init, key names can be parsed only after GTK is up.
+        commands: Rc::new(RefCell::new(command::Registry::default())),
+        app_win: None,
+        export_chooser: None,
+        move_chooser: None,
+        after_close: None,
+        history: History::default(),
+        unsaved: 0,
     };
     let app = RelmApp::new(model);
     app.run();
+    Ok(())
 }
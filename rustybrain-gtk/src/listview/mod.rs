@@ -17,17 +17,23 @@ use rustybrain_core::zettel::Zettel;
 
 pub struct Model {
     kasten: Rc<RefCell<Kasten>>,
+
+    selected: Option<usize>,
+    /// Row to be selected in view.
+    select: Option<usize>,
 }
 
 pub enum Msg {
     RowSelected(ListBoxRow),
     ZettelSelected(Rc<RefCell<Zettel>>),
+    /// Kasten is rebuilt, e.g. switched to another workspace.
+    Reload,
+    /// Move the selection by rows.
+    Step(i32),
 }
 
 pub struct ListView {
     window: ScrolledWindow,
-
-    #[allow(dead_code)]
     view: ListBox,
 }
 
@@ -44,16 +50,18 @@ impl relm4::Model for RowModel {
 }
 
 pub struct ListViewComponents {
-    rows: Vec<RelmComponent<item::Model, RowModel>>,
+    rows: RefCell<Vec<RelmComponent<item::Model, RowModel>>>,
+
+    /// List the rows are in, to put new ones in on reload.
+    view: Option<ListBox>,
 }
 
-impl Components<Model> for ListViewComponents {
-    fn init_components(
-        parent_model: &Model,
+impl ListViewComponents {
+    fn rows(
+        kasten: &Kasten,
         parent_sender: relm4::Sender<Msg>,
-    ) -> Self {
+    ) -> Vec<RelmComponent<item::Model, RowModel>> {
         let mut items = vec![];
-        let kasten = parent_model.kasten.borrow();
         for zettel in kasten.iter() {
             let model = RowModel {
                 zettel: zettel.clone(),
@@ -61,10 +69,40 @@ impl Components<Model> for ListViewComponents {
             let item = RelmComponent::new(&model, parent_sender.clone());
             items.push(item);
         }
-        ListViewComponents { rows: items }
+        items
     }
 
-    fn connect_parent(&mut self, _parent_widgets: &ListView) {}
+    fn reload(&self, kasten: &Kasten, parent_sender: relm4::Sender<Msg>) {
+        let mut rows = self.rows.borrow_mut();
+        if let Some(view) = self.view.as_ref() {
+            for item in rows.iter() {
+                view.remove(item.root_widget());
+            }
+        }
+        *rows = Self::rows(kasten, parent_sender);
+        if let Some(view) = self.view.as_ref() {
+            for item in rows.iter() {
+                view.append(item.root_widget());
+            }
+        }
+    }
+}
+
+impl Components<Model> for ListViewComponents {
+    fn init_components(
+        parent_model: &Model,
+        parent_sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let kasten = parent_model.kasten.borrow();
+        ListViewComponents {
+            rows: RefCell::new(Self::rows(&kasten, parent_sender)),
+            view: None,
+        }
+    }
+
+    fn connect_parent(&mut self, parent_widgets: &ListView) {
+        self.view = Some(parent_widgets.view.clone());
+    }
 }
 
 impl relm4::Model for Model {
@@ -79,6 +117,8 @@ impl ComponentUpdate<super::AppModel> for Model {
     fn init_model(parent_model: &super::AppModel) -> Self {
         Model {
             kasten: parent_model.kasten.clone(),
+            selected: None,
+            select: None,
         }
     }
 
@@ -86,12 +126,14 @@ impl ComponentUpdate<super::AppModel> for Model {
         &mut self,
         msg: Self::Msg,
         components: &Self::Components,
-        _sender: relm4::Sender<Self::Msg>,
+        sender: relm4::Sender<Self::Msg>,
         parent_sender: relm4::Sender<super::Msg>,
     ) {
+        self.select = None;
         match msg {
             Msg::RowSelected(row) => {
-                for item in &components.rows {
+                self.selected = Some(row.index() as usize);
+                for item in components.rows.borrow().iter() {
                     if item.root_widget() == &row {
                         send!(
                             item.sender(),
@@ -103,6 +145,23 @@ impl ComponentUpdate<super::AppModel> for Model {
             Msg::ZettelSelected(zettel) => {
                 send!(parent_sender, super::Msg::ChangeZettel(zettel))
             }
+            Msg::Reload => {
+                self.selected = None;
+                components.reload(&self.kasten.borrow(), sender);
+            }
+            Msg::Step(step) => {
+                let count = components.rows.borrow().len();
+                if count == 0 {
+                    return;
+                }
+                let last = count as i32 - 1;
+                let i = match self.selected {
+                    Some(i) => (i as i32 + step).clamp(0, last),
+                    None if step < 0 => last,
+                    None => 0,
+                };
+                self.select = Some(i as usize);
+            }
         }
     }
 }
@@ -116,7 +175,7 @@ impl Widgets<Model, super::AppModel> for ListView {
         sender: relm4::Sender<Msg>,
     ) -> Self {
         let view = ListBox::new();
-        for item in &components.rows {
+        for item in components.rows.borrow().iter() {
             view.append(item.root_widget());
         }
         view.connect_row_selected(move |_, row| {
@@ -135,5 +194,12 @@ impl Widgets<Model, super::AppModel> for ListView {
         self.window.clone()
     }
 
-    fn view(&mut self, _model: &Model, _sender: relm4::Sender<Msg>) {}
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        if let Some(i) = model.select {
+            if let Some(row) = self.view.row_at_index(i as i32) {
+                self.view.select_row(Some(&row));
+                row.grab_focus();
+            }
+        }
+    }
 }
@@ -0,0 +1,358 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gdk::{Key, ModifierType};
+use gtk::{prelude::*, ApplicationWindow, Dialog, EventControllerKey};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::merge::{self, Kind, Line};
+use rustybrain_core::zettel::Zettel;
+
+use crate::AppModel;
+
+/// Versions of a zettel changed both in the editor and on disk.
+struct Versions {
+    /// None if the loaded version is not kept, the sides are merged as if
+    /// both were written from scratch.
+    base: Option<String>,
+    mine: String,
+    disk: String,
+}
+
+pub struct Model {
+    kasten: Rc<RefCell<Kasten>>,
+    app_win: Option<ApplicationWindow>,
+    zettel: Option<Rc<RefCell<Zettel>>>,
+    versions: Option<Versions>,
+    show: bool,
+
+    /// Views should be filled from versions again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    /// Resolve a save refused for changes on disk.
+    Show(Rc<RefCell<Zettel>>),
+    Hide,
+    KeepMine,
+    TakeDisk,
+    /// Save the text of the merged view.
+    Resolve(String),
+}
+
+pub struct Merge {
+    dialog: Dialog,
+    title: gtk::Label,
+    base: gtk::TextView,
+    mine: gtk::TextView,
+    disk: gtk::TextView,
+    merged: gtk::TextView,
+    conflicts: gtk::Label,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Merge;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            kasten: parent_model.kasten.clone(),
+            app_win: None,
+            zettel: None,
+            versions: None,
+            show: false,
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        match msg {
+            Msg::Init(w) => self.app_win = Some(w),
+            Msg::Show(z) => {
+                let versions = {
+                    let zettel = z.borrow();
+                    zettel.text().and_then(|mine| {
+                        Ok(Versions {
+                            base: self.kasten.borrow().base_of(&zettel)?,
+                            disk: zettel.disk_text()?,
+                            mine,
+                        })
+                    })
+                };
+                match versions {
+                    Ok(v) => {
+                        self.versions = Some(v);
+                        self.zettel = Some(z);
+                        self.show = true;
+                        self.reset = true;
+                    }
+                    Err(e) => send!(
+                        parent_sender,
+                        super::Msg::ShowError(
+                            "Read the versions of the note failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    ),
+                }
+            }
+            Msg::Hide => {
+                if self.zettel.take().is_some() {
+                    send!(parent_sender, super::Msg::Unresolved);
+                }
+                self.versions = None;
+                self.show = false;
+            }
+            Msg::KeepMine | Msg::TakeDisk => {
+                let text = match (&msg, &self.versions) {
+                    (Msg::KeepMine, Some(v)) => v.mine.clone(),
+                    (_, Some(v)) => v.disk.clone(),
+                    _ => return,
+                };
+                send!(sender, Msg::Resolve(text));
+            }
+            Msg::Resolve(text) => {
+                if let Some(z) = self.zettel.take() {
+                    send!(parent_sender, super::Msg::Merged(z, text));
+                }
+                self.versions = None;
+                self.show = false;
+            }
+        }
+    }
+}
+
+impl Widgets<Model, AppModel> for Merge {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Note changed on disk")
+            .default_width(1000)
+            .default_height(700)
+            .build();
+        let title = gtk::Label::builder()
+            .wrap(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let view = |editable| {
+            gtk::TextView::builder()
+                .editable(editable)
+                .monospace(true)
+                .wrap_mode(gtk::WrapMode::WordChar)
+                .hexpand(true)
+                .vexpand(true)
+                .build()
+        };
+        let (base, mine, disk, merged) =
+            (view(false), view(false), view(false), view(true));
+        let column = |name: &str, view: &gtk::TextView| {
+            let box_ = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(4)
+                .build();
+            let label = gtk::Label::builder()
+                .label(name)
+                .halign(gtk::Align::Start)
+                .css_classes(vec!["heading".to_string()])
+                .build();
+            let window = gtk::ScrolledWindow::builder()
+                .hexpand(true)
+                .vexpand(true)
+                .child(view)
+                .build();
+            box_.append(&label);
+            box_.append(&window);
+            box_
+        };
+        let columns = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .homogeneous(true)
+            .build();
+        columns.append(&column("Loaded", &base));
+        columns.append(&column("Mine", &mine));
+        columns.append(&column("On disk", &disk));
+
+        let conflicts = gtk::Label::builder().halign(gtk::Align::Start).build();
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let cancel_btn = gtk::Button::builder().label("Cancel").build();
+        let mine_btn = gtk::Button::builder().label("Keep mine").build();
+        let disk_btn = gtk::Button::builder().label("Take disk").build();
+        let save_btn = gtk::Button::builder().label("Save merged").build();
+        buttons.append(&cancel_btn);
+        buttons.append(&mine_btn);
+        buttons.append(&disk_btn);
+        buttons.append(&save_btn);
+
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        box_.append(&title);
+        box_.append(&columns);
+        box_.append(&column("Merged", &merged));
+        box_.append(&conflicts);
+        box_.append(&buttons);
+        dialog.set_child(Some(&box_));
+
+        let s = sender.clone();
+        cancel_btn.connect_clicked(move |_| send!(s, Msg::Hide));
+        let s = sender.clone();
+        mine_btn.connect_clicked(move |_| send!(s, Msg::KeepMine));
+        let s = sender.clone();
+        disk_btn.connect_clicked(move |_| send!(s, Msg::TakeDisk));
+        let (s, m) = (sender.clone(), merged.clone());
+        save_btn.connect_clicked(move |_| {
+            let buffer = m.buffer();
+            let (start, end) = buffer.bounds();
+            let text = buffer.text(&start, &end, true).to_string();
+            send!(s, Msg::Resolve(text));
+        });
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        Merge {
+            dialog,
+            title,
+            base,
+            mine,
+            disk,
+            merged,
+            conflicts,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        if let (true, Some(v), Some(z)) =
+            (model.reset, &model.versions, &model.zettel)
+        {
+            self.fill(v, &z.borrow());
+        }
+        if model.show {
+            self.dialog.show();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
+
+impl Merge {
+    fn fill(&self, v: &Versions, zettel: &Zettel) {
+        let mut title = format!(
+            "{} is changed on disk since it was opened. Pick a version, or \
+             edit the merged one and save it.",
+            zettel.path().display()
+        );
+        if v.base.is_none() {
+            title.push_str(
+                " The opened version is not kept, so both are kept as one \
+                 conflict.",
+            );
+        }
+        self.title.set_text(&title);
+        let base_text = v.base.as_deref().unwrap_or("");
+        let mine = merge::diff(base_text, &v.mine);
+        let disk = merge::diff(base_text, &v.disk);
+        let kept = |l: &&Line| l.kind != Kind::Added;
+        // Lines of the loaded version, marked if either side removed them.
+        let base: Vec<Line> = mine
+            .iter()
+            .filter(kept)
+            .zip(disk.iter().filter(kept))
+            .map(|(m, d)| Line {
+                kind: if m.kind == Kind::Same && d.kind == Kind::Same {
+                    Kind::Same
+                } else {
+                    Kind::Removed
+                },
+                text: m.text.clone(),
+            })
+            .collect();
+        let added = |lines: Vec<Line>| -> Vec<Line> {
+            lines
+                .into_iter()
+                .filter(|l| l.kind != Kind::Removed)
+                .collect()
+        };
+        Self::show_lines(&self.base, &base);
+        Self::show_lines(&self.mine, &added(mine));
+        Self::show_lines(&self.disk, &added(disk));
+
+        let merged = merge::merge3(base_text, &v.mine, &v.disk);
+        self.merged.buffer().set_text(&merged.text);
+        self.conflicts.set_text(&match merged.conflicts {
+            0 => "Merged without conflicts.".to_string(),
+            n => format!(
+                "{} conflicts, marked between <<<<<<< and >>>>>>> lines.",
+                n
+            ),
+        });
+    }
+
+    /// Show lines of a diff, the changed ones highlighted.
+    pub(crate) fn show_lines(view: &gtk::TextView, lines: &[Line]) {
+        let buffer = gtk::TextBuffer::new(None);
+        buffer.create_tag(
+            Some("added"),
+            &[("paragraph-background", &"rgba(46, 160, 67, 0.25)")],
+        );
+        buffer.create_tag(
+            Some("removed"),
+            &[("paragraph-background", &"rgba(248, 81, 73, 0.25)")],
+        );
+        for line in lines {
+            let mut end = buffer.end_iter();
+            match line.kind {
+                Kind::Same => buffer.insert(&mut end, &line.text),
+                Kind::Added => buffer.insert_with_tags_by_name(
+                    &mut end,
+                    &line.text,
+                    &["added"],
+                ),
+                Kind::Removed => buffer.insert_with_tags_by_name(
+                    &mut end,
+                    &line.text,
+                    &["removed"],
+                ),
+            }
+        }
+        view.set_buffer(Some(&buffer));
+    }
+}
@@ -1,17 +1,23 @@
+use std::rc::Rc;
+
 use gtk::prelude::*;
 use gtk::{MessageDialog, MessageType};
 use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::Error;
 
 use super::AppModel;
 
 pub enum Msg {
     Show(gtk::MessageType, String),
+    ShowError(String, Rc<Error>),
+    ShowErrors(String, Vec<Rc<Error>>),
     Hide,
 }
 
 pub struct Model {
     show: bool,
     title: String,
+    detail: Option<String>,
     type_: MessageType,
 }
 
@@ -32,6 +38,7 @@ impl ComponentUpdate<AppModel> for Model {
         Model {
             show: false,
             title: "".to_string(),
+            detail: None,
             type_: MessageType::Error,
         }
     }
@@ -47,6 +54,21 @@ impl ComponentUpdate<AppModel> for Model {
             Msg::Show(type_, title) => {
                 self.type_ = type_;
                 self.title = title;
+                self.detail = None;
+                self.show = true;
+            }
+            Msg::ShowError(title, err) => {
+                self.type_ = MessageType::Error;
+                self.title = title;
+                self.detail = Some(Self::describe(&err));
+                self.show = true;
+            }
+            Msg::ShowErrors(title, errors) => {
+                self.type_ = MessageType::Error;
+                self.title = title;
+                let details: Vec<String> =
+                    errors.iter().map(|e| Self::describe(e)).collect();
+                self.detail = Some(details.join("\n\n"));
                 self.show = true;
             }
             Msg::Hide => self.show = false,
@@ -54,6 +76,26 @@ impl ComponentUpdate<AppModel> for Model {
     }
 }
 
+impl Model {
+    /// Tell the user what went wrong and where to look for fixing it.
+    fn describe(err: &Error) -> String {
+        match (err, err.path(), err.position()) {
+            (_, Some(path), Some(pos)) => format!(
+                "{}\n\nOpen {} at line {} to fix it.",
+                err,
+                path.display(),
+                pos.line
+            ),
+            (Error::Io { .. }, Some(path), None) => format!(
+                "{}\n\nCheck that {} exists and is writable.",
+                err,
+                path.display()
+            ),
+            _ => err.to_string(),
+        }
+    }
+}
+
 impl Widgets<Model, AppModel> for Message {
     type Root = MessageDialog;
 
@@ -74,6 +116,7 @@ impl Widgets<Model, AppModel> for Message {
     fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
         self.dialog.set_message_type(model.type_);
         self.dialog.set_text(Some(&model.title));
+        self.dialog.set_secondary_text(model.detail.as_deref());
         if model.show {
             self.dialog.show();
         } else {
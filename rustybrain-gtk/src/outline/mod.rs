@@ -0,0 +1,178 @@
+use gtk::prelude::*;
+use gtk::ListBox;
+use gtk::ScrolledWindow;
+use relm4::send;
+use relm4::ComponentUpdate;
+use relm4::Widgets;
+use rustybrain_core::zettel::Heading;
+
+use crate::AppModel;
+
+/// Indent of a heading for each level below the first.
+const INDENT: i32 = 12;
+
+/// Headings of the focused zettel, as the editor shows it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outline {
+    pub headings: Vec<Heading>,
+    /// Lines of the headings with their sections folded.
+    pub folded: Vec<usize>,
+    /// Line of the cursor.
+    pub cursor: usize,
+}
+
+impl Outline {
+    /// Index of the heading of the section holding the cursor.
+    fn current(&self) -> Option<usize> {
+        self.headings.iter().rposition(|h| h.line <= self.cursor)
+    }
+}
+
+pub struct Model {
+    outline: Outline,
+
+    /// Rows should be filled from headings again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Change(Outline),
+    Clear,
+    /// Put the cursor at the n-th heading.
+    Jump(usize),
+    /// Fold or unfold the section of the n-th heading.
+    Fold(usize),
+}
+
+pub struct OutlinePanel {
+    window: ScrolledWindow,
+    list: ListBox,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = OutlinePanel;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(_parent_model: &AppModel) -> Self {
+        Model {
+            outline: Outline::default(),
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &Self::Components,
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        match msg {
+            Msg::Change(outline) => {
+                self.reset = outline.headings != self.outline.headings
+                    || outline.folded != self.outline.folded;
+                self.outline = outline;
+            }
+            Msg::Clear => {
+                self.outline = Outline::default();
+                self.reset = true;
+            }
+            Msg::Jump(i) => {
+                if let Some(h) = self.outline.headings.get(i) {
+                    send!(parent_sender, super::Msg::GotoLine(h.line as i32));
+                }
+            }
+            Msg::Fold(i) => {
+                if let Some(h) = self.outline.headings.get(i) {
+                    send!(
+                        parent_sender,
+                        super::Msg::ToggleFold(Some(h.line as i32))
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Widgets<Model, super::AppModel> for OutlinePanel {
+    type Root = gtk::ScrolledWindow;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let list = ListBox::builder().build();
+        list.connect_row_activated(move |_, row| {
+            send!(sender, Msg::Jump(row.index() as usize))
+        });
+        let window = ScrolledWindow::builder()
+            .width_request(200)
+            .child(&list)
+            .build();
+        OutlinePanel { window, list }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.window.clone()
+    }
+
+    fn view(&mut self, model: &Model, sender: relm4::Sender<Msg>) {
+        let outline = &model.outline;
+        if model.reset {
+            while let Some(c) = self.list.last_child() {
+                self.list.remove(&c);
+            }
+            for (i, h) in outline.headings.iter().enumerate() {
+                self.list.append(&Self::row(i, h, outline, &sender));
+            }
+        }
+        let current = outline
+            .current()
+            .and_then(|i| self.list.row_at_index(i as i32));
+        if self.list.selected_row() != current {
+            self.list.select_row(current.as_ref());
+        }
+    }
+}
+
+impl OutlinePanel {
+    fn row(
+        i: usize,
+        heading: &Heading,
+        outline: &Outline,
+        sender: &relm4::Sender<Msg>,
+    ) -> gtk::ListBoxRow {
+        let folded = outline.folded.contains(&heading.line);
+        let fold_btn = gtk::Button::builder()
+            .icon_name(if folded {
+                "pan-end-symbolic"
+            } else {
+                "pan-down-symbolic"
+            })
+            .has_frame(false)
+            .tooltip_text(if folded { "Unfold" } else { "Fold" })
+            .build();
+        let s = sender.clone();
+        fold_btn.connect_clicked(move |_| send!(s, Msg::Fold(i)));
+        let label = gtk::Label::builder()
+            .label(&heading.text)
+            .halign(gtk::Align::Start)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(4)
+            .margin_start(INDENT * (heading.level as i32 - 1))
+            .build();
+        box_.append(&fold_btn);
+        box_.append(&label);
+        gtk::ListBoxRow::builder().child(&box_).build()
+    }
+}
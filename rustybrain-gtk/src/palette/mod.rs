@@ -0,0 +1,247 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gdk::{Key, ModifierType};
+use gtk::{prelude::*, ApplicationWindow, Dialog, EventControllerKey};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::config::Config;
+
+use crate::command::Registry;
+use crate::AppModel;
+
+/// Action listed in the palette.
+struct Entry {
+    title: String,
+    keys: String,
+    msg: super::Msg,
+}
+
+pub struct Model {
+    app_win: Option<ApplicationWindow>,
+    config: Rc<RefCell<Config>>,
+    commands: Rc<RefCell<Registry>>,
+    show: bool,
+
+    entries: Vec<Entry>,
+    /// Indexes of entries matching the query, the best first.
+    matches: Vec<usize>,
+    /// Entry should be cleared in view.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    Show,
+    Hide,
+    Changed(String),
+    /// Run the n-th matched action.
+    Activate(usize),
+}
+
+pub struct Palette {
+    dialog: Dialog,
+    entry: gtk::SearchEntry,
+    list_box: gtk::ListBox,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Palette;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            app_win: None,
+            config: parent_model.config.clone(),
+            commands: parent_model.commands.clone(),
+            show: false,
+            entries: vec![],
+            matches: vec![],
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        match msg {
+            Msg::Init(w) => self.app_win = Some(w),
+            Msg::Show => {
+                self.fill();
+                self.filter("");
+                self.show = true;
+                self.reset = true;
+            }
+            Msg::Hide => self.show = false,
+            Msg::Changed(s) => self.filter(&s),
+            Msg::Activate(i) => {
+                if let Some(e) = self.matches.get(i).map(|i| &self.entries[*i])
+                {
+                    send!(sender, Msg::Hide);
+                    send!(parent_sender, e.msg.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Model {
+    /// Collect actions from the registry, and one for each workspace.
+    fn fill(&mut self) {
+        self.entries.clear();
+        let commands = self.commands.borrow();
+        for c in commands.commands().iter().filter(|c| c.id() != "palette") {
+            self.entries.push(Entry {
+                title: c.title().to_string(),
+                keys: commands.bindings_of(c.id()).join(", "),
+                msg: c.msg(),
+            });
+        }
+        let config = self.config.borrow();
+        for w in config.workspaces() {
+            if w != config.workspace() {
+                self.entries.push(Entry {
+                    title: format!("Switch to workspace {}", w),
+                    keys: String::new(),
+                    msg: super::Msg::SwitchWorkspace(w.to_string()),
+                });
+            }
+        }
+    }
+
+    fn filter(&mut self, query: &str) {
+        let mut scored: Vec<(i32, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| fuzzy_score(query, &e.title).map(|s| (s, i)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+    }
+}
+
+/// Score `text` if every char of `query` appears in it in order, ignoring
+/// case. Consecutive chars and word starts score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut last: Option<usize> = None;
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut pos = 0;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = pos + text[pos..].iter().position(|c| *c == q)?;
+        score += 1;
+        if last.map_or(false, |l| l + 1 == i) {
+            score += 4;
+        }
+        if i == 0 || text[i - 1] == ' ' {
+            score += 2;
+        }
+        last = Some(i);
+        pos = i + 1;
+    }
+    Some(score)
+}
+
+impl Widgets<Model, AppModel> for Palette {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Commands")
+            .build();
+        let entry = gtk::SearchEntry::builder()
+            .hexpand(true)
+            .placeholder_text("Type to find a command")
+            .build();
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+        let list_box = gtk::ListBox::builder().build();
+        let window = gtk::ScrolledWindow::builder()
+            .hexpand(true)
+            .height_request(300)
+            .width_request(600)
+            .child(&list_box)
+            .build();
+        box_.append(&entry);
+        box_.append(&window);
+        dialog.set_child(Some(&box_));
+
+        let s = sender.clone();
+        entry.connect_changed(move |e| {
+            send!(s, Msg::Changed(e.text().as_str().to_string()))
+        });
+        let s = sender.clone();
+        entry.connect_activate(move |_| send!(s, Msg::Activate(0)));
+        let s = sender.clone();
+        list_box.connect_row_activated(move |_, row| {
+            send!(s, Msg::Activate(row.index() as usize))
+        });
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        Palette {
+            dialog,
+            entry,
+            list_box,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        if model.reset {
+            self.entry.set_text("");
+        }
+        while let Some(c) = self.list_box.last_child() {
+            self.list_box.remove(&c);
+        }
+        for e in model.matches.iter().map(|i| &model.entries[*i]) {
+            let box_ = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(12)
+                .build();
+            let title = gtk::Label::builder()
+                .label(&e.title)
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .build();
+            let keys = gtk::Label::builder().label(&e.keys).build();
+            keys.add_css_class("dim-label");
+            box_.append(&title);
+            box_.append(&keys);
+            self.list_box
+                .append(&gtk::ListBoxRow::builder().child(&box_).build());
+        }
+        if model.show {
+            self.dialog.show();
+            self.entry.grab_focus();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
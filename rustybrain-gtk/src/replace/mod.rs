@@ -0,0 +1,356 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gdk::{Key, ModifierType};
+use gtk::{prelude::*, ApplicationWindow, Dialog, EventControllerKey};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::find::{FileMatches, Finder, Query, Replacement};
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::Result;
+
+use crate::AppModel;
+
+pub struct Model {
+    app_win: Option<ApplicationWindow>,
+    kasten: Rc<RefCell<Kasten>>,
+    query: Query,
+    /// Text to replace the matches with.
+    with: String,
+    /// Lines with matches of each zettel, as they read before and after.
+    preview: Vec<FileMatches>,
+    /// Why the query is bad.
+    invalid: Option<String>,
+    /// Last replace done, to undo it.
+    last: Option<Replacement>,
+    show: bool,
+
+    /// The preview should be filled again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    Show,
+    Hide,
+    Change(Query, String),
+    /// Replace the matches in all zettels.
+    Apply,
+    /// Undo the last replace.
+    Undo,
+}
+
+pub struct ReplaceDialog {
+    dialog: Dialog,
+    title: gtk::Label,
+    list: gtk::ListBox,
+    apply_btn: gtk::Button,
+    undo_btn: gtk::Button,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = ReplaceDialog;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            app_win: None,
+            kasten: parent_model.kasten.clone(),
+            query: Query::default(),
+            with: String::new(),
+            preview: vec![],
+            invalid: None,
+            last: None,
+            show: false,
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        let result = match msg {
+            Msg::Init(w) => {
+                self.app_win = Some(w);
+                Ok(())
+            }
+            Msg::Show => {
+                self.show = true;
+                self.preview();
+                Ok(())
+            }
+            Msg::Hide => {
+                self.show = false;
+                Ok(())
+            }
+            Msg::Change(query, with) => {
+                self.query = query;
+                self.with = with;
+                self.preview();
+                Ok(())
+            }
+            Msg::Apply => self.apply(parent_sender.clone()),
+            Msg::Undo => self.undo(parent_sender.clone()),
+        };
+        if let Err(e) = result {
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Replace in notes failed!".to_string(),
+                    Rc::new(e)
+                )
+            );
+        }
+    }
+}
+
+impl Model {
+    fn preview(&mut self) {
+        self.preview.clear();
+        self.invalid = None;
+        self.reset = true;
+        if self.query.pattern.is_empty() {
+            return;
+        }
+        match Finder::new(&self.query) {
+            Ok(finder) => {
+                match self.kasten.borrow().find_all(&finder, &self.with) {
+                    Ok(found) => self.preview = found,
+                    Err(e) => self.invalid = Some(e.to_string()),
+                }
+            }
+            Err(e) => self.invalid = Some(e.to_string()),
+        }
+    }
+
+    fn apply(
+        &mut self,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) -> Result<()> {
+        if self.query.pattern.is_empty() {
+            return Ok(());
+        }
+        let finder = Finder::new(&self.query)?;
+        let replaced =
+            self.kasten.borrow_mut().replace_all(&finder, &self.with);
+        // Zettels replaced before a failure are shown, and can be undone.
+        self.preview();
+        let edits = &replaced.replacement.edits;
+        if !edits.is_empty() {
+            let zids = edits.iter().map(|e| e.zid.clone()).collect();
+            send!(parent_sender, super::Msg::Replaced(zids));
+            self.last = Some(replaced.replacement);
+        }
+        match replaced.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn undo(&mut self, parent_sender: relm4::Sender<super::Msg>) -> Result<()> {
+        let last = match self.last.take() {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        let undone = self.kasten.borrow_mut().undo_replace(&last);
+        self.preview();
+        undone?;
+        let zids = last.edits.into_iter().map(|e| e.zid).collect();
+        send!(parent_sender, super::Msg::Replaced(zids));
+        Ok(())
+    }
+}
+
+impl Widgets<Model, AppModel> for ReplaceDialog {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Replace in notes")
+            .default_width(700)
+            .default_height(500)
+            .build();
+
+        let find_entry = gtk::Entry::builder()
+            .hexpand(true)
+            .placeholder_text("Find")
+            .build();
+        let with_entry = gtk::Entry::builder()
+            .hexpand(true)
+            .placeholder_text("Replace with")
+            .build();
+        let regex_btn = gtk::CheckButton::builder()
+            .label("Regular expression")
+            .build();
+        let case_btn = gtk::CheckButton::builder().label("Match case").build();
+        let options = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        options.append(&regex_btn);
+        options.append(&case_btn);
+
+        let changed: Rc<dyn Fn()> = {
+            let (f, w) = (find_entry.clone(), with_entry.clone());
+            let (r, c) = (regex_btn.clone(), case_btn.clone());
+            let s = sender.clone();
+            Rc::new(move || {
+                let query = Query {
+                    pattern: f.text().to_string(),
+                    regex: r.is_active(),
+                    match_case: c.is_active(),
+                };
+                send!(s, Msg::Change(query, w.text().to_string()));
+            })
+        };
+        let c = changed.clone();
+        find_entry.connect_changed(move |_| c());
+        let c = changed.clone();
+        with_entry.connect_changed(move |_| c());
+        let c = changed.clone();
+        regex_btn.connect_toggled(move |_| c());
+        case_btn.connect_toggled(move |_| changed());
+
+        let title = gtk::Label::builder()
+            .wrap(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        let list_window = gtk::ScrolledWindow::builder()
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let close_btn = gtk::Button::builder().label("Close").build();
+        let undo_btn = gtk::Button::builder().label("Undo replace").build();
+        let apply_btn = gtk::Button::builder().label("Replace all").build();
+        buttons.append(&close_btn);
+        buttons.append(&undo_btn);
+        buttons.append(&apply_btn);
+
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        box_.append(&find_entry);
+        box_.append(&with_entry);
+        box_.append(&options);
+        box_.append(&title);
+        box_.append(&list_window);
+        box_.append(&buttons);
+        dialog.set_child(Some(&box_));
+
+        let s = sender.clone();
+        close_btn.connect_clicked(move |_| send!(s, Msg::Hide));
+        let s = sender.clone();
+        undo_btn.connect_clicked(move |_| send!(s, Msg::Undo));
+        let s = sender.clone();
+        apply_btn.connect_clicked(move |_| send!(s, Msg::Apply));
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        ReplaceDialog {
+            dialog,
+            title,
+            list,
+            apply_btn,
+            undo_btn,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        self.apply_btn.set_sensitive(!model.preview.is_empty());
+        self.undo_btn.set_sensitive(model.last.is_some());
+        if model.reset {
+            self.fill(model);
+        }
+        if model.show {
+            self.dialog.show();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
+
+impl ReplaceDialog {
+    fn fill(&self, model: &Model) {
+        while let Some(c) = self.list.last_child() {
+            self.list.remove(&c);
+        }
+        let lines: usize = model.preview.iter().map(|m| m.lines.len()).sum();
+        self.title.set_text(&match &model.invalid {
+            Some(e) => e.clone(),
+            None if model.query.pattern.is_empty() => {
+                "Type what to find in all notes.".to_string()
+            }
+            None if lines == 0 => "No matches.".to_string(),
+            None => format!(
+                "{} lines of {} notes match, they read as below once \
+                 replaced.",
+                lines,
+                model.preview.len()
+            ),
+        });
+        for m in model.preview.iter() {
+            let title = gtk::Label::builder()
+                .label(&m.title)
+                .halign(gtk::Align::Start)
+                .margin_top(8)
+                .build();
+            title.add_css_class("heading");
+            self.list
+                .append(&gtk::ListBoxRow::builder().child(&title).build());
+            for l in m.lines.iter() {
+                let label = gtk::Label::builder()
+                    .label(&format!(
+                        "{}: {}\n→ {}",
+                        l.line + 1,
+                        l.text.trim(),
+                        l.replaced.trim()
+                    ))
+                    .halign(gtk::Align::Start)
+                    .ellipsize(gtk::pango::EllipsizeMode::End)
+                    .margin_start(12)
+                    .build();
+                self.list
+                    .append(&gtk::ListBoxRow::builder().child(&label).build());
+            }
+        }
+    }
+}
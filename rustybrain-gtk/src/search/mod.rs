@@ -14,23 +14,46 @@ pub struct Model {
     app_win: Option<ApplicationWindow>,
     zettels: Vec<Rc<RefCell<Zettel>>>,
     searching: String,
-    inserting: bool,
+    mode: Mode,
     show: bool,
     kasten: Option<Rc<RefCell<Kasten>>>,
     config: Rc<RefCell<Config>>,
 }
 
+/// What the picked zettel is for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Open it in the current tab.
+    Open,
+    /// Insert a link to it.
+    Insert,
+    /// Open it in a new tab.
+    InTab,
+    /// Ask for the title of a new zettel, listing ones alike, created once
+    /// confirmed.
+    Create,
+    /// Merge it into the current one.
+    Merge,
+}
+
 pub enum Msg {
     Init(ApplicationWindow, Rc<RefCell<Kasten>>),
-    Show(bool),
+    /// Kasten is rebuilt, e.g. switched to another workspace.
+    Reload,
+    Show(Mode),
     Hide,
     Changed(String),
+    /// Enter is pressed in the entry.
+    Confirm,
     Search(Rc<RefCell<Kasten>>, String),
     Activate(Option<Rc<RefCell<Zettel>>>),
 }
 
 pub struct Search {
     dialog: Dialog,
+    entry: gtk::SearchEntry,
+    /// Placeholder of the entry when searching.
+    hint: String,
     list_box: gtk::ListBox,
 }
 
@@ -50,7 +73,7 @@ impl ComponentUpdate<AppModel> for Model {
             kasten: None,
             show: false,
             searching: "".to_string(),
-            inserting: false,
+            mode: Mode::Open,
             config: parent_model.config.clone(),
             zettels,
         }
@@ -64,9 +87,9 @@ impl ComponentUpdate<AppModel> for Model {
         parent_sender: relm4::Sender<super::Msg>,
     ) {
         match msg {
-            Msg::Show(inserting) => {
+            Msg::Show(mode) => {
                 self.show = true;
-                self.inserting = inserting;
+                self.mode = mode;
             }
             Msg::Hide => self.show = false,
             Msg::Init(w, k) => {
@@ -74,32 +97,47 @@ impl ComponentUpdate<AppModel> for Model {
                 self.handle_init(&k.borrow());
                 self.kasten = Some(k);
             }
+            Msg::Reload => {
+                self.zettels.clear();
+                self.searching.clear();
+                if let Some(kasten) = self.kasten.clone() {
+                    self.handle_init(&kasten.borrow());
+                }
+            }
             Msg::Changed(s) => {
                 self.searching = s.clone();
                 if let Some(kasten) = &self.kasten {
                     send!(sender, Msg::Search(kasten.clone(), s));
                 }
             }
+            Msg::Confirm => {
+                // Only a new zettel is what Enter can mean, searching picks
+                // from the list.
+                if self.mode == Mode::Create
+                    && !self.searching.trim().is_empty()
+                {
+                    send!(sender, Msg::Activate(None));
+                }
+            }
             Msg::Search(k, s) => {
                 self.handle_search(&k.borrow(), parent_sender, &s)
             }
             Msg::Activate(item) => {
                 send!(sender, Msg::Hide);
-                if let Some(z) = item {
-                    if self.inserting {
-                        send!(parent_sender, super::Msg::InsertZettel(z));
-                    } else {
-                        send!(parent_sender, super::Msg::ChangeZettel(z));
+                let msg = match (item, self.mode) {
+                    (Some(z), Mode::Insert) => super::Msg::InsertZettel(z),
+                    (Some(z), Mode::Merge) => super::Msg::MergeZettel(z),
+                    (Some(z), Mode::InTab) => super::Msg::OpenInTab(z),
+                    (Some(z), Mode::Open | Mode::Create) => {
+                        super::Msg::ChangeZettel(z)
                     }
-                } else {
-                    send!(
-                        parent_sender,
-                        super::Msg::NewZettel(
-                            self.searching.to_string(),
-                            self.inserting
-                        )
-                    );
-                }
+                    (None, Mode::Merge) => return,
+                    (None, mode) => super::Msg::NewZettel(
+                        self.searching.to_string(),
+                        mode == Mode::Insert,
+                    ),
+                };
+                send!(parent_sender, msg);
             }
         }
     }
@@ -160,9 +198,10 @@ impl Widgets<Model, AppModel> for Search {
         let c = (*model.config).borrow();
         let f = c.shortcut().find();
         let i = c.shortcut().insert();
+        let hint = format!("Press {} or {} to start search!", f, i);
         let entry = gtk::SearchEntry::builder()
             .hexpand(true)
-            .placeholder_text(&format!("Press {} or {} to start search!", f, i))
+            .placeholder_text(&hint)
             .build();
         let box_ = gtk::Box::builder()
             .orientation(gtk::Orientation::Vertical)
@@ -182,6 +221,8 @@ impl Widgets<Model, AppModel> for Search {
         entry.connect_changed(move |e| {
             send!(s, Msg::Changed(e.text().as_str().to_string()))
         });
+        let s = sender.clone();
+        entry.connect_activate(move |_| send!(s, Msg::Confirm));
         let key_ctrl = EventControllerKey::new();
         key_ctrl.connect_key_released(move |_, k, _, m| {
             if m == ModifierType::empty() && k == Key::Escape {
@@ -190,7 +231,12 @@ impl Widgets<Model, AppModel> for Search {
         });
         dialog.add_controller(&key_ctrl);
 
-        Search { dialog, list_box }
+        Search {
+            dialog,
+            entry,
+            hint,
+            list_box,
+        }
     }
 
     fn root_widget(&self) -> Self::Root {
@@ -199,6 +245,12 @@ impl Widgets<Model, AppModel> for Search {
 
     fn view(&mut self, model: &Model, sender: relm4::Sender<Msg>) {
         self.dialog.set_transient_for(model.app_win.as_ref());
+        self.entry
+            .set_placeholder_text(Some(if model.mode == Mode::Create {
+                "Title of the new note"
+            } else {
+                &self.hint
+            }));
         if model.show {
             self.dialog.show();
         } else {
@@ -213,21 +265,12 @@ impl Widgets<Model, AppModel> for Search {
         }
 
         for item in model.zettels.iter() {
-            if model.inserting {
-                self.list_box.append(&self.row(
-                    item.borrow().title(),
-                    true,
-                    Some(item.clone()),
-                    sender.clone(),
-                ));
-            } else {
-                self.list_box.append(&self.row(
-                    item.borrow().title(),
-                    false,
-                    Some(item.clone()),
-                    sender.clone(),
-                ));
-            }
+            self.list_box.append(&self.row(
+                item.borrow().title(),
+                model.mode == Mode::Insert,
+                Some(item.clone()),
+                sender.clone(),
+            ));
         }
     }
 }
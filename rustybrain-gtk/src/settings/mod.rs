@@ -0,0 +1,325 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gdk::{Key, ModifierType};
+use gtk::pango::FontDescription;
+use gtk::{
+    prelude::*, ApplicationWindow, Dialog, EventControllerKey, MessageType,
+};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::config::{Config, Theme, Value, DEFAULT_WORKSPACE};
+
+use crate::{command, AppModel};
+
+/// Values of the preferences form.
+pub struct Form {
+    repo: String,
+    find: String,
+    insert: String,
+    quit: String,
+    preferences: String,
+    font: String,
+    theme: Theme,
+    autosave: u32,
+}
+
+pub struct Model {
+    app_win: Option<ApplicationWindow>,
+    config: Rc<RefCell<Config>>,
+    show: bool,
+
+    /// Form should be filled from config again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    Show,
+    Hide,
+    Save(Form),
+}
+
+pub struct Settings {
+    dialog: Dialog,
+    repo: gtk::Entry,
+    find: gtk::Entry,
+    insert: gtk::Entry,
+    quit: gtk::Entry,
+    preferences: gtk::Entry,
+    font: gtk::FontButton,
+    theme: gtk::DropDown,
+    autosave: gtk::SpinButton,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Settings;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            app_win: None,
+            config: parent_model.config.clone(),
+            show: false,
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        match msg {
+            Msg::Init(w) => self.app_win = Some(w),
+            Msg::Show => {
+                self.show = true;
+                self.reset = true;
+            }
+            Msg::Hide => self.show = false,
+            Msg::Save(form) => {
+                if self.save(form, parent_sender) {
+                    send!(sender, Msg::Hide);
+                }
+            }
+        }
+    }
+}
+
+impl Model {
+    /// Write changed values into the config file.
+    fn save(
+        &mut self,
+        form: Form,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) -> bool {
+        let shortcuts = [
+            ("find", form.find),
+            ("insert", form.insert),
+            ("quit", form.quit),
+            ("preferences", form.preferences),
+        ];
+        let keys: Vec<(&str, &str)> = shortcuts
+            .iter()
+            .map(|(id, key)| (*id, key.as_str()))
+            .collect();
+        if let Err(message) = command::Registry::check_shortcuts(&keys) {
+            send!(
+                parent_sender,
+                super::Msg::ShowMsg(
+                    MessageType::Error,
+                    format!("Shortcut {}!", message)
+                )
+            );
+            return false;
+        }
+
+        let old_repo = self.config.borrow().repo_path().to_string();
+        let workspace = self.config.borrow().workspace().to_string();
+        let mut edits = vec![];
+        if form.repo != old_repo {
+            let repo = Value::String(form.repo);
+            if workspace == DEFAULT_WORKSPACE {
+                edits.push(("repo", "path", repo));
+            } else {
+                edits.push(("workspaces", workspace.as_str(), repo));
+            }
+        }
+        for (name, key) in shortcuts.iter() {
+            edits.push(("shortcut", name, Value::String(key.to_string())));
+        }
+        let fd = FontDescription::from_string(&form.font);
+        if let Some(family) = fd.family() {
+            edits.push(("editor", "font", Value::String(family.to_string())));
+        }
+        if fd.size() > 0 {
+            let size = (fd.size() / gtk::pango::SCALE) as i64;
+            edits.push(("editor", "font_size", Value::Integer(size)));
+        }
+        let theme = Value::String(form.theme.as_str().to_string());
+        edits.push(("editor", "theme", theme));
+        let autosave = Value::Integer(form.autosave.into());
+        edits.push(("editor", "autosave", autosave));
+
+        let saved = self.config.borrow_mut().save(&edits);
+        match saved {
+            Ok(_) => {
+                send!(parent_sender, super::Msg::ConfigChanged(old_repo));
+                true
+            }
+            Err(e) => {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowError(
+                        "Save preferences failed!".to_string(),
+                        Rc::new(e)
+                    )
+                );
+                false
+            }
+        }
+    }
+}
+
+impl Widgets<Model, AppModel> for Settings {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Preferences")
+            .build();
+
+        let grid = gtk::Grid::builder()
+            .row_spacing(6)
+            .column_spacing(12)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        let entry = || gtk::Entry::builder().hexpand(true).build();
+        let repo = entry();
+        let find = entry();
+        let insert = entry();
+        let quit = entry();
+        let preferences = entry();
+        let font = gtk::FontButton::new();
+        let themes: Vec<&str> = Theme::ALL.iter().map(|t| t.as_str()).collect();
+        let theme = gtk::DropDown::from_strings(&themes);
+        let autosave = gtk::SpinButton::with_range(0.0, 3600.0, 1.0);
+        autosave
+            .set_tooltip_text(Some("Seconds after the last edit, 0 is off"));
+
+        let rows: [(&str, &gtk::Widget); 8] = [
+            ("Repo path", repo.upcast_ref()),
+            ("Find note", find.upcast_ref()),
+            ("Insert link", insert.upcast_ref()),
+            ("Quit", quit.upcast_ref()),
+            ("Preferences", preferences.upcast_ref()),
+            ("Font", font.upcast_ref()),
+            ("Theme", theme.upcast_ref()),
+            ("Autosave", autosave.upcast_ref()),
+        ];
+        for (i, (name, widget)) in rows.iter().enumerate() {
+            let label = gtk::Label::builder()
+                .label(name)
+                .halign(gtk::Align::Start)
+                .build();
+            grid.attach(&label, 0, i as i32, 1, 1);
+            grid.attach(*widget, 1, i as i32, 1, 1);
+        }
+
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let cancel_btn = gtk::Button::builder().label("Cancel").build();
+        let save_btn = gtk::Button::builder().label("Save").build();
+        buttons.append(&cancel_btn);
+        buttons.append(&save_btn);
+        grid.attach(&buttons, 1, rows.len() as i32, 1, 1);
+        dialog.set_child(Some(&grid));
+
+        let s = sender.clone();
+        cancel_btn.connect_clicked(move |_| send!(s, Msg::Hide));
+
+        let s = sender.clone();
+        let (r, f, i, q, p, fb, t, a) = (
+            repo.clone(),
+            find.clone(),
+            insert.clone(),
+            quit.clone(),
+            preferences.clone(),
+            font.clone(),
+            theme.clone(),
+            autosave.clone(),
+        );
+        save_btn.connect_clicked(move |_| {
+            let theme = Theme::ALL
+                .get(t.selected() as usize)
+                .copied()
+                .unwrap_or(Theme::Light);
+            let form = Form {
+                repo: r.text().to_string(),
+                find: f.text().to_string(),
+                insert: i.text().to_string(),
+                quit: q.text().to_string(),
+                preferences: p.text().to_string(),
+                font: fb.font().map(|f| f.to_string()).unwrap_or_default(),
+                theme,
+                autosave: a.value_as_int().max(0) as u32,
+            };
+            send!(s, Msg::Save(form));
+        });
+
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        Settings {
+            dialog,
+            repo,
+            find,
+            insert,
+            quit,
+            preferences,
+            font,
+            theme,
+            autosave,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        if model.reset {
+            self.fill(&model.config.borrow());
+        }
+        if model.show {
+            self.dialog.show();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
+
+impl Settings {
+    fn fill(&self, config: &Config) {
+        self.repo.set_text(config.repo_path());
+        let shortcut = config.shortcut();
+        self.find.set_text(shortcut.find());
+        self.insert.set_text(shortcut.insert());
+        self.quit.set_text(shortcut.quit());
+        self.preferences.set_text(shortcut.preferences());
+        let editor = config.editor();
+        self.font.set_font(&format!(
+            "{} {}",
+            editor.font(),
+            editor.font_size()
+        ));
+        let theme = Theme::ALL.iter().position(|t| *t == editor.theme());
+        self.theme.set_selected(theme.unwrap_or(0) as u32);
+        self.autosave.set_value(editor.autosave().into());
+    }
+}
@@ -0,0 +1,267 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gdk::{Key, ModifierType};
+use gtk::{prelude::*, ApplicationWindow, Dialog, EventControllerKey};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::merge::{self, Line};
+use rustybrain_core::snapshot::Snapshot;
+use rustybrain_core::zettel::Zettel;
+use rustybrain_core::Result;
+
+use crate::AppModel;
+
+pub struct Model {
+    app_win: Option<ApplicationWindow>,
+    kasten: Rc<RefCell<Kasten>>,
+    zettel: Option<Rc<RefCell<Zettel>>>,
+    snapshots: Vec<Snapshot>,
+    /// Text of the selected snapshot.
+    selected: Option<String>,
+    /// Changes from the selected snapshot to the zettel as it is now.
+    diff: Vec<Line>,
+    show: bool,
+
+    /// The list should be filled from snapshots again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    Show(Rc<RefCell<Zettel>>),
+    Hide,
+    Select(usize),
+    /// Replace the zettel with the selected snapshot.
+    Restore,
+}
+
+pub struct History {
+    dialog: Dialog,
+    title: gtk::Label,
+    list: gtk::ListBox,
+    diff: gtk::TextView,
+    restore_btn: gtk::Button,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = History;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            app_win: None,
+            kasten: parent_model.kasten.clone(),
+            zettel: None,
+            snapshots: vec![],
+            selected: None,
+            diff: vec![],
+            show: false,
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        let result = match msg {
+            Msg::Init(w) => {
+                self.app_win = Some(w);
+                Ok(())
+            }
+            Msg::Show(z) => self.show(z),
+            Msg::Hide => {
+                self.show = false;
+                Ok(())
+            }
+            Msg::Select(i) => self.select(i),
+            Msg::Restore => {
+                if let (Some(text), Some(z)) =
+                    (self.selected.take(), self.zettel.take())
+                {
+                    send!(parent_sender, super::Msg::Merged(z, text));
+                }
+                self.show = false;
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Read the version history failed!".to_string(),
+                    Rc::new(e)
+                )
+            );
+        }
+    }
+}
+
+impl Model {
+    fn show(&mut self, z: Rc<RefCell<Zettel>>) -> Result<()> {
+        let zid = z.borrow().zid().to_string();
+        self.snapshots = self.kasten.borrow().snapshots().list(&zid)?;
+        self.zettel = Some(z);
+        self.selected = None;
+        self.diff.clear();
+        self.show = true;
+        self.reset = true;
+        Ok(())
+    }
+
+    fn select(&mut self, i: usize) -> Result<()> {
+        let (snapshot, z) = match (self.snapshots.get(i), &self.zettel) {
+            (Some(s), Some(z)) => (s, z),
+            _ => return Ok(()),
+        };
+        let text = self.kasten.borrow().snapshots().read(snapshot)?;
+        self.diff = merge::diff(&text, &z.borrow().text()?);
+        self.selected = Some(text);
+        Ok(())
+    }
+}
+
+impl Widgets<Model, AppModel> for History {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Version history")
+            .default_width(900)
+            .default_height(600)
+            .build();
+        let title = gtk::Label::builder()
+            .wrap(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let list = gtk::ListBox::builder().build();
+        let s = sender.clone();
+        list.connect_row_activated(move |_, row| {
+            send!(s, Msg::Select(row.index() as usize))
+        });
+        let list_window = gtk::ScrolledWindow::builder()
+            .width_request(200)
+            .vexpand(true)
+            .child(&list)
+            .build();
+        let diff = gtk::TextView::builder()
+            .editable(false)
+            .monospace(true)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .build();
+        let diff_window = gtk::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .child(&diff)
+            .build();
+        let columns = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        columns.append(&list_window);
+        columns.append(&diff_window);
+
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let close_btn = gtk::Button::builder().label("Close").build();
+        let restore_btn = gtk::Button::builder().label("Restore").build();
+        buttons.append(&close_btn);
+        buttons.append(&restore_btn);
+
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        box_.append(&title);
+        box_.append(&columns);
+        box_.append(&buttons);
+        dialog.set_child(Some(&box_));
+
+        let s = sender.clone();
+        close_btn.connect_clicked(move |_| send!(s, Msg::Hide));
+        let s = sender.clone();
+        restore_btn.connect_clicked(move |_| send!(s, Msg::Restore));
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        History {
+            dialog,
+            title,
+            list,
+            diff,
+            restore_btn,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        self.restore_btn.set_sensitive(model.selected.is_some());
+        crate::merge::Merge::show_lines(&self.diff, &model.diff);
+        if let (true, Some(z)) = (model.reset, &model.zettel) {
+            self.fill(model, &z.borrow());
+        }
+        if model.show {
+            self.dialog.show();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
+
+impl History {
+    fn fill(&self, model: &Model, zettel: &Zettel) {
+        self.title.set_text(&if model.snapshots.is_empty() {
+            format!("No saved versions of {} yet.", zettel.title())
+        } else {
+            format!(
+                "Saved versions of {}, pick one to see what changed since.",
+                zettel.title()
+            )
+        });
+        while let Some(c) = self.list.last_child() {
+            self.list.remove(&c);
+        }
+        for s in model.snapshots.iter() {
+            let label = gtk::Label::builder()
+                .label(&s.time().format("%Y-%m-%d %H:%M:%S").to_string())
+                .halign(gtk::Align::Start)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            self.list
+                .append(&gtk::ListBoxRow::builder().child(&label).build());
+        }
+    }
+}
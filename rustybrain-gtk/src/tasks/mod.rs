@@ -0,0 +1,276 @@
+use std::{cell::RefCell, rc::Rc};
+
+use chrono::{Duration, Local, NaiveDate};
+use gdk::{Key, ModifierType};
+use gtk::{prelude::*, ApplicationWindow, Dialog, EventControllerKey};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::task::Task;
+
+use crate::AppModel;
+
+/// Choices of the due date filter, by position in its drop down.
+const DUE_CHOICES: [&str; 4] = [
+    "Any date",
+    "Due by today",
+    "Due within a week",
+    "No due date",
+];
+
+pub struct Model {
+    app_win: Option<ApplicationWindow>,
+    kasten: Rc<RefCell<Kasten>>,
+    /// Open tasks of all zettels.
+    tasks: Vec<Task>,
+    tags: Vec<String>,
+    /// Only tasks of zettels with the tag are shown.
+    tag: Option<String>,
+    /// Position of the due date filter in `DUE_CHOICES`.
+    due: u32,
+    show: bool,
+
+    /// Tags should be filled again.
+    reset: bool,
+}
+
+pub enum Msg {
+    Init(ApplicationWindow),
+    Show,
+    Hide,
+    FilterTag(Option<String>),
+    FilterDue(u32),
+    /// Open the zettel of the n-th shown task, at its line.
+    Open(usize),
+}
+
+pub struct TasksDialog {
+    dialog: Dialog,
+    tags: gtk::DropDown,
+    title: gtk::Label,
+    list: gtk::ListBox,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = TasksDialog;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            app_win: None,
+            kasten: parent_model.kasten.clone(),
+            tasks: vec![],
+            tags: vec![],
+            tag: None,
+            due: 0,
+            show: false,
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        match msg {
+            Msg::Init(w) => self.app_win = Some(w),
+            Msg::Show => {
+                let kasten = self.kasten.borrow();
+                self.tasks =
+                    kasten.tasks().into_iter().filter(|t| !t.done).collect();
+                self.tags = kasten.tags();
+                self.tag = None;
+                self.show = true;
+                self.reset = true;
+            }
+            Msg::Hide => self.show = false,
+            Msg::FilterTag(tag) => self.tag = tag,
+            Msg::FilterDue(due) => self.due = due,
+            Msg::Open(i) => {
+                let task = self.shown().nth(i).map(|t| (t.zid.clone(), t.line));
+                if let Some((zid, line)) = task {
+                    send!(
+                        parent_sender,
+                        super::Msg::VisitLine(zid, line as i32)
+                    );
+                    self.show = false;
+                }
+            }
+        }
+    }
+}
+
+impl Model {
+    /// Tasks passing the filters.
+    fn shown(&self) -> impl Iterator<Item = &Task> {
+        let today = Local::now().date_naive();
+        self.tasks.iter().filter(move |t| {
+            self.tag.as_ref().map_or(true, |tag| t.has_tag(tag))
+                && self.due_passes(t, today)
+        })
+    }
+
+    fn due_passes(&self, task: &Task, today: NaiveDate) -> bool {
+        match self.due {
+            1 => task.due_by(today),
+            2 => task.due_by(today + Duration::days(7)),
+            3 => task.due.is_none(),
+            _ => true,
+        }
+    }
+}
+
+impl Widgets<Model, AppModel> for TasksDialog {
+    type Root = Dialog;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let dialog = gtk::Dialog::builder()
+            .destroy_with_parent(true)
+            .decorated(true)
+            .modal(true)
+            .title("Tasks")
+            .default_width(700)
+            .default_height(500)
+            .build();
+
+        let tags = gtk::DropDown::from_strings(&["All tags"]);
+        tags.set_tooltip_text(Some("Tag"));
+        let s = sender.clone();
+        tags.connect_selected_notify(move |dd| {
+            let tag = match dd.selected() {
+                0 => None,
+                _ => dd
+                    .selected_item()
+                    .and_then(|item| item.downcast::<gtk::StringObject>().ok())
+                    .map(|item| item.string().to_string()),
+            };
+            send!(s, Msg::FilterTag(tag));
+        });
+        let due = gtk::DropDown::from_strings(&DUE_CHOICES);
+        due.set_tooltip_text(Some("Due date"));
+        let s = sender.clone();
+        due.connect_selected_notify(move |dd| {
+            send!(s, Msg::FilterDue(dd.selected()))
+        });
+        let filters = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        filters.append(&tags);
+        filters.append(&due);
+
+        let title = gtk::Label::builder()
+            .wrap(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let list = gtk::ListBox::builder().build();
+        let s = sender.clone();
+        list.connect_row_activated(move |_, row| {
+            send!(s, Msg::Open(row.index() as usize))
+        });
+        let list_window = gtk::ScrolledWindow::builder()
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        let buttons = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .halign(gtk::Align::End)
+            .build();
+        let close_btn = gtk::Button::builder().label("Close").build();
+        buttons.append(&close_btn);
+
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        box_.append(&filters);
+        box_.append(&title);
+        box_.append(&list_window);
+        box_.append(&buttons);
+        dialog.set_child(Some(&box_));
+
+        let s = sender.clone();
+        close_btn.connect_clicked(move |_| send!(s, Msg::Hide));
+        let key_ctrl = EventControllerKey::new();
+        key_ctrl.connect_key_released(move |_, k, _, m| {
+            if m == ModifierType::empty() && k == Key::Escape {
+                send!(sender, Msg::Hide);
+            }
+        });
+        dialog.add_controller(&key_ctrl);
+
+        TasksDialog {
+            dialog,
+            tags,
+            title,
+            list,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.dialog.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.dialog.set_transient_for(model.app_win.as_ref());
+        if model.reset {
+            let mut tags = vec!["All tags"];
+            tags.extend(model.tags.iter().map(|t| t.as_str()));
+            self.tags.set_model(Some(&gtk::StringList::new(&tags)));
+        }
+        if model.show {
+            self.fill(model);
+            self.dialog.show();
+        } else {
+            self.dialog.hide();
+        }
+    }
+}
+
+impl TasksDialog {
+    fn fill(&self, model: &Model) {
+        while let Some(c) = self.list.last_child() {
+            self.list.remove(&c);
+        }
+        let mut n = 0;
+        for t in model.shown() {
+            let text = match t.due {
+                Some(due) => format!("☐ {}\n{}, due {}", t.text, t.title, due),
+                None => format!("☐ {}\n{}", t.text, t.title),
+            };
+            let label = gtk::Label::builder()
+                .label(&text)
+                .halign(gtk::Align::Start)
+                .wrap(true)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            self.list
+                .append(&gtk::ListBoxRow::builder().child(&label).build());
+            n += 1;
+        }
+        self.title.set_text(&if n == 0 {
+            "No open tasks.".to_string()
+        } else {
+            format!("{} open tasks, pick one to open its note.", n)
+        });
+    }
+}
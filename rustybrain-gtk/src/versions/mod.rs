@@ -0,0 +1,324 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::ListBox;
+use gtk::ScrolledWindow;
+use relm4::send;
+use relm4::ComponentUpdate;
+use relm4::Widgets;
+use rustybrain_core::git::Commit;
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::merge::{self, Line};
+use rustybrain_core::zettel::Zettel;
+use rustybrain_core::Result;
+
+use crate::AppModel;
+
+/// How many commits the timeline shows.
+const TIMELINE_MAX: usize = 100;
+
+pub struct Model {
+    kasten: Rc<RefCell<Kasten>>,
+    zettel: Option<Rc<RefCell<Zettel>>>,
+    /// Show commits across the kasten instead of those of the zettel.
+    timeline: bool,
+    /// Versioning is on in the config.
+    enabled: bool,
+    /// The panel is visible, versions are only read while it is.
+    shown: bool,
+    commits: Vec<Commit>,
+    /// Text of the zettel as of the selected commit.
+    selected: Option<String>,
+    diff: Vec<Line>,
+
+    /// The list should be filled from commits again.
+    reset: bool,
+}
+
+pub enum Msg {
+    ChangeZettel(Rc<RefCell<Zettel>>),
+    Clear,
+    Refresh,
+    /// The panel was shown or hidden.
+    Show(bool),
+    ShowTimeline(bool),
+    /// Show the changes of the n-th commit, or open its note on the
+    /// timeline.
+    Select(usize),
+    /// Replace the zettel with the selected version.
+    Restore,
+}
+
+pub struct Versions {
+    box_: gtk::Box,
+    status: gtk::Label,
+    list: ListBox,
+    diff: gtk::TextView,
+    restore_btn: gtk::Button,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Versions;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        Model {
+            kasten: parent_model.kasten.clone(),
+            zettel: None,
+            timeline: false,
+            enabled: false,
+            shown: parent_model.state.show_versions(),
+            commits: vec![],
+            selected: None,
+            diff: vec![],
+            reset: false,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &Self::Components,
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        self.reset = false;
+        match msg {
+            Msg::ChangeZettel(z) => self.zettel = Some(z),
+            Msg::Clear => self.zettel = None,
+            Msg::Refresh => {}
+            Msg::Show(shown) => self.shown = shown,
+            Msg::ShowTimeline(timeline) => self.timeline = timeline,
+            Msg::Select(i) => {
+                if let Err(e) = self.select(i, &parent_sender) {
+                    send!(
+                        parent_sender,
+                        super::Msg::ShowError(
+                            "Read the version failed!".to_string(),
+                            Rc::new(e)
+                        )
+                    );
+                }
+                return;
+            }
+            Msg::Restore => {
+                if let (Some(text), Some(z)) =
+                    (self.selected.as_ref(), self.zettel.as_ref())
+                {
+                    // The kasten may be rebuilt since the zettel was shown.
+                    let zid = z.borrow().zid().to_string();
+                    let z = self
+                        .kasten
+                        .borrow()
+                        .resolve(&zid)
+                        .unwrap_or_else(|_| z.clone());
+                    send!(parent_sender, super::Msg::Merged(z, text.clone()));
+                }
+                return;
+            }
+        }
+        if !self.shown {
+            return;
+        }
+        if let Err(e) = self.collect() {
+            send!(
+                parent_sender,
+                super::Msg::ShowError(
+                    "Read the versions failed!".to_string(),
+                    Rc::new(e)
+                )
+            );
+        }
+    }
+}
+
+impl Model {
+    fn collect(&mut self) -> Result<()> {
+        self.commits.clear();
+        self.selected = None;
+        self.diff.clear();
+        self.reset = true;
+        let versions = self.kasten.borrow().versions()?;
+        self.enabled = versions.is_some();
+        let versions = match versions {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        if self.timeline {
+            self.commits = versions.timeline(TIMELINE_MAX)?;
+        } else if let Some(z) = self.zettel.as_ref() {
+            self.commits = versions.history(z.borrow().path())?;
+        }
+        Ok(())
+    }
+
+    fn select(
+        &mut self,
+        i: usize,
+        parent_sender: &relm4::Sender<super::Msg>,
+    ) -> Result<()> {
+        let commit = match self.commits.get(i) {
+            Some(c) => c,
+            None => return Ok(()),
+        };
+        if self.timeline {
+            if let Some(path) = commit.paths.first() {
+                let zid = format!("@/{}", path.display());
+                send!(parent_sender, super::Msg::Visit(zid));
+            }
+            return Ok(());
+        }
+        let (z, versions) =
+            match (&self.zettel, self.kasten.borrow().versions()?) {
+                (Some(z), Some(v)) => (z.clone(), v),
+                _ => return Ok(()),
+            };
+        let path = z.borrow().path().to_path_buf();
+        let text = versions.file_at(&commit.id, &path)?.unwrap_or_default();
+        let before = match commit.parent.as_ref() {
+            Some(id) => versions.file_at(id, &path)?.unwrap_or_default(),
+            None => String::new(),
+        };
+        self.diff = merge::diff(&before, &text);
+        self.selected = Some(text);
+        Ok(())
+    }
+}
+
+impl Widgets<Model, super::AppModel> for Versions {
+    type Root = gtk::Box;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let scope = gtk::DropDown::from_strings(&["This note", "All notes"]);
+        scope.set_hexpand(true);
+        let s = sender.clone();
+        scope.connect_selected_notify(move |dd| {
+            send!(s, Msg::ShowTimeline(dd.selected() == 1));
+        });
+        let refresh_btn = gtk::Button::builder()
+            .icon_name("view-refresh-symbolic")
+            .tooltip_text("Refresh")
+            .build();
+        let s = sender.clone();
+        refresh_btn.connect_clicked(move |_| send!(s, Msg::Refresh));
+        let head = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(4)
+            .build();
+        head.append(&scope);
+        head.append(&refresh_btn);
+
+        let status = gtk::Label::builder()
+            .wrap(true)
+            .max_width_chars(30)
+            .halign(gtk::Align::Start)
+            .build();
+        let list = ListBox::builder().build();
+        let s = sender.clone();
+        list.connect_row_activated(move |_, row| {
+            send!(s, Msg::Select(row.index() as usize))
+        });
+        let list_window =
+            ScrolledWindow::builder().vexpand(true).child(&list).build();
+        let diff = gtk::TextView::builder()
+            .editable(false)
+            .monospace(true)
+            .wrap_mode(gtk::WrapMode::WordChar)
+            .build();
+        let diff_window =
+            ScrolledWindow::builder().vexpand(true).child(&diff).build();
+        let restore_btn = gtk::Button::builder()
+            .label("Restore this version")
+            .sensitive(false)
+            .build();
+        restore_btn.connect_clicked(move |_| send!(sender, Msg::Restore));
+
+        let box_ = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .width_request(200)
+            .spacing(6)
+            .build();
+        box_.append(&head);
+        box_.append(&status);
+        box_.append(&list_window);
+        box_.append(&diff_window);
+        box_.append(&restore_btn);
+        Versions {
+            box_,
+            status,
+            list,
+            diff,
+            restore_btn,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.box_.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        self.restore_btn
+            .set_sensitive(!model.timeline && model.selected.is_some());
+        let status = if !model.enabled {
+            "Versioning is off, set git = true in [repo] of the config to \
+             turn it on."
+        } else if model.commits.is_empty() {
+            "No versions yet."
+        } else {
+            ""
+        };
+        self.status.set_text(status);
+        self.status.set_visible(!status.is_empty());
+        crate::merge::Merge::show_lines(&self.diff, &model.diff);
+        if !model.reset {
+            return;
+        }
+        while let Some(c) = self.list.last_child() {
+            self.list.remove(&c);
+        }
+        for c in model.commits.iter() {
+            let box_ = gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(4)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            let message = gtk::Label::builder()
+                .label(&c.message)
+                .halign(gtk::Align::Start)
+                .wrap(true)
+                .max_width_chars(30)
+                .css_classes(vec!["heading".to_string()])
+                .build();
+            let time = gtk::Label::builder()
+                .label(&c.time.format("%Y-%m-%d %H:%M").to_string())
+                .halign(gtk::Align::Start)
+                .css_classes(vec!["dim-label".to_string()])
+                .build();
+            box_.append(&message);
+            box_.append(&time);
+            if model.timeline {
+                for path in c.paths.iter() {
+                    let label = gtk::Label::builder()
+                        .label(&path.display().to_string())
+                        .halign(gtk::Align::Start)
+                        .ellipsize(gtk::pango::EllipsizeMode::Middle)
+                        .build();
+                    box_.append(&label);
+                }
+            }
+            self.list
+                .append(&gtk::ListBoxRow::builder().child(&box_).build());
+        }
+    }
+}
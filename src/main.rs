@@ -3,12 +3,63 @@ use std::{cell::RefCell, rc::Rc};
 use rustybrain_core::config::ConfigLoader;
 use rustybrain_gtk::run;
 
+const USAGE: &str =
+    "Usage: rustybrain [--config <file>] [--repo <dir>] [--workspace <name>]";
+
 fn main() -> Result<(), anyhow::Error> {
     if std::env::var("RUST_LIB_BACKTRACE").is_err() {
         std::env::set_var("RUST_LIB_BACKTRACE", "1")
     }
     color_eyre::install().unwrap();
-    let config = ConfigLoader::new().load()?;
-    run(Rc::new(RefCell::new(config)));
+    let loader = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(loader)) => loader,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(err) => anyhow::bail!("{}\n{}", err, USAGE),
+    };
+    let config = match loader.load() {
+        Ok(config) => config,
+        Err(err) => {
+            if let (Some(path), Some(pos)) = (err.path(), err.position()) {
+                eprintln!(
+                    "Open the config file {} at line {} to fix it.",
+                    path.display(),
+                    pos.line
+                );
+            }
+            return Err(err.into());
+        }
+    };
+    run(Rc::new(RefCell::new(config)))?;
     Ok(())
 }
+
+/// Build the config loader from command line flags, `None` means the help
+/// is asked for.
+fn parse_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<Option<ConfigLoader>, String> {
+    let mut loader = ConfigLoader::new();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-c" | "--config" | "-r" | "--repo" | "-w" | "--workspace" => {}
+            _ => return Err(format!("Unknown option {}", flag)),
+        }
+        let value = inline
+            .or_else(|| args.next())
+            .ok_or_else(|| format!("Missing value for {}", flag))?;
+        loader = match flag.as_str() {
+            "-c" | "--config" => loader.with_path(&value),
+            "-r" | "--repo" => loader.with_repo(&value),
+            _ => loader.with_workspace(&value),
+        };
+    }
+    Ok(Some(loader))
+}